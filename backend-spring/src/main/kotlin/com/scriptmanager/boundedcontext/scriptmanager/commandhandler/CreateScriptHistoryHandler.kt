@@ -16,10 +16,15 @@ class CreateScriptHistoryHandler(
 
     override fun handle(eventQueue: EventQueue, command: CreateScriptHistoryCommand) {
         val scriptId = command.scriptId
-        val pastHistory = scriptHistoryRepository.findFirstByShellScriptId(scriptId)
+        val pastHistory = scriptHistoryRepository.findFirstByShellScriptIdOrderByExecutedAtDesc(scriptId)
         var event: ScriptHistoryCreatedEvent
 
-        if (pastHistory != null) {
+        if (pastHistory?.runId != null) {
+            // The Tauri executor already recorded this run with its outcome
+            event = ScriptHistoryCreatedEvent(
+                history = pastHistory.toDTO()
+            )
+        } else if (pastHistory != null) {
             pastHistory.executedAt = command.time.toDouble()
 
             event = ScriptHistoryCreatedEvent(
@@ -20,6 +20,10 @@ class HistoricalShellScript(
     @Column(name = "execution_time", nullable = false)
     var executedAt: Double = 0.0,
 
+    // Set when the row was recorded by the Tauri executor
+    @Column(name = "run_id")
+    var runId: String? = null,
+
     @Column(name = "created_at")
     @Generated
     val createdAt: Double? = null,
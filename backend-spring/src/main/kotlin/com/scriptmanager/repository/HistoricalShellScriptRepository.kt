@@ -11,6 +11,7 @@ interface HistoricalShellScriptRepository : JpaRepository<HistoricalShellScript,
     fun findAllByShellScriptId(shellScriptId: Int): List<HistoricalShellScript>
     fun findAllByShellScriptIdOrderByExecutedAtDesc(shellScriptId: Int): List<HistoricalShellScript>
     fun findFirstByShellScriptId(shellScriptId: Int): HistoricalShellScript?
+    fun findFirstByShellScriptIdOrderByExecutedAtDesc(shellScriptId: Int): HistoricalShellScript?
 
     @Query(
         """
@@ -20,6 +21,9 @@ interface HistoricalShellScriptRepository : JpaRepository<HistoricalShellScript,
         left join fetch folder1.parentWorkspace workspace1
         left join fetch folder1.parentFolder folder2
         left join fetch folder2.parentWorkspace workspace2
+        where history.id in (
+            select max(latest.id) from HistoricalShellScript latest group by latest.shellScriptId
+        )
         order by history.executedAt desc
         limit 10
     """
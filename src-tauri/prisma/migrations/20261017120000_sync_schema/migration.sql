@@ -0,0 +1,544 @@
+-- Brings databases created from the migrations above up to the current schema. Until now the
+-- app synced the schema on every launch instead of migrating, so existing installs may already
+-- have any of these tables, indexes and columns. The runner skips ADD COLUMN for columns that
+-- already exist and the rest is safe to run again.
+
+-- AlterTable
+ALTER TABLE "application_state" ADD COLUMN "selected_aiprofile_id" INTEGER;
+ALTER TABLE "application_state" ADD COLUMN "watchers_paused" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "application_state" ADD COLUMN "terminal_emulator" TEXT;
+ALTER TABLE "application_state" ADD COLUMN "terminal_template" TEXT;
+ALTER TABLE "application_state" ADD COLUMN "shell_multiplexer" TEXT;
+ALTER TABLE "scripts_folder" ADD COLUMN "system_level" TEXT NOT NULL DEFAULT 'USER';
+ALTER TABLE "shell_script" ADD COLUMN "is_editing" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "shell_script" ADD COLUMN "is_markdown" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "shell_script" ADD COLUMN "locked" BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE "shell_script" ADD COLUMN "show_shell" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "shell_script" ADD COLUMN "interpreter" TEXT;
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_rel_scriptsfolder_shellscript" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "scripts_folder_id" INTEGER NOT NULL,
+    "shell_script_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "rel_scriptsfolder_shellscript_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_scriptsfolder_shellscript_scripts_folder_id_fkey" FOREIGN KEY ("scripts_folder_id") REFERENCES "scripts_folder" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+INSERT INTO "new_rel_scriptsfolder_shellscript" ("created_at", "created_at_hk", "id", "scripts_folder_id", "shell_script_id") SELECT "created_at", "created_at_hk", "id", "scripts_folder_id", "shell_script_id" FROM "rel_scriptsfolder_shellscript";
+DROP TABLE "rel_scriptsfolder_shellscript";
+ALTER TABLE "new_rel_scriptsfolder_shellscript" RENAME TO "rel_scriptsfolder_shellscript";
+CREATE UNIQUE INDEX "rel_scriptsfolder_shellscript_shell_script_id_scripts_folder_id_key" ON "rel_scriptsfolder_shellscript"("shell_script_id", "scripts_folder_id");
+CREATE INDEX "rel_scriptsfolder_shellscript_scripts_folder_id_idx" ON "rel_scriptsfolder_shellscript"("scripts_folder_id");
+CREATE INDEX "rel_scriptsfolder_shellscript_shell_script_id_idx" ON "rel_scriptsfolder_shellscript"("shell_script_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "scripts_folder_id_idx" ON "scripts_folder"("id");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "shell_script_id_idx" ON "shell_script"("id");
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "shell_script_execution_limit" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "timeout_seconds" INTEGER,
+    "max_output_bytes" INTEGER,
+    "cpu_seconds" INTEGER,
+    "address_space_mb" INTEGER,
+    "open_files" INTEGER,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "shell_script_execution_limit_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "shell_script_run_config" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "working_dir" TEXT,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "shell_script_run_config_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "shell_script_env_var" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "value" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "shell_script_env_var_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "shell_script_parameter_value" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "value" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "shell_script_parameter_value_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_chain" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_chain_step" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "script_chain_id" INTEGER NOT NULL,
+    "shell_script_id" INTEGER NOT NULL,
+    "ordering" INTEGER NOT NULL,
+    "continue_on_error" BOOLEAN NOT NULL DEFAULT false,
+    "retry_count" INTEGER NOT NULL DEFAULT 0,
+    "delay_ms" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_chain_step_script_chain_id_fkey" FOREIGN KEY ("script_chain_id") REFERENCES "script_chain" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "script_chain_step_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_dag" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "max_parallel" INTEGER NOT NULL DEFAULT 4,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_dag_node" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "script_dag_id" INTEGER NOT NULL,
+    "shell_script_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_dag_node_script_dag_id_fkey" FOREIGN KEY ("script_dag_id") REFERENCES "script_dag" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "script_dag_node_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_dag_edge" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "from_node_id" INTEGER NOT NULL,
+    "to_node_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_dag_edge_from_node_id_fkey" FOREIGN KEY ("from_node_id") REFERENCES "script_dag_node" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "script_dag_edge_to_node_id_fkey" FOREIGN KEY ("to_node_id") REFERENCES "script_dag_node" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_schedule" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "cron_expression" TEXT NOT NULL,
+    "timezone" TEXT NOT NULL DEFAULT 'UTC',
+    "misfire_policy" TEXT NOT NULL DEFAULT 'SKIP',
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "last_fired_at" REAL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_schedule_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_watch_trigger" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "base_dir" TEXT NOT NULL,
+    "patterns" TEXT NOT NULL,
+    "debounce_ms" INTEGER NOT NULL DEFAULT 500,
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_watch_trigger_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "ssh_target" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "host" TEXT NOT NULL,
+    "port" INTEGER NOT NULL DEFAULT 22,
+    "username" TEXT NOT NULL,
+    "auth_method" TEXT NOT NULL DEFAULT 'AGENT',
+    "key_path" TEXT,
+    "known_hosts_path" TEXT,
+    "remote_dir" TEXT,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "ssh_host_group" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "max_parallel" INTEGER NOT NULL DEFAULT 8,
+    "timeout_seconds" INTEGER,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "ssh_host_group_member" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "ssh_host_group_id" INTEGER NOT NULL,
+    "ssh_target_id" INTEGER NOT NULL,
+    CONSTRAINT "ssh_host_group_member_ssh_host_group_id_fkey" FOREIGN KEY ("ssh_host_group_id") REFERENCES "ssh_host_group" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "ssh_host_group_member_ssh_target_id_fkey" FOREIGN KEY ("ssh_target_id") REFERENCES "ssh_target" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_folder_folder" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "parent_folder_id" INTEGER NOT NULL,
+    "child_folder_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "rel_folder_folder_parent_folder_id_fkey" FOREIGN KEY ("parent_folder_id") REFERENCES "scripts_folder" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_folder_folder_child_folder_id_fkey" FOREIGN KEY ("child_folder_id") REFERENCES "scripts_folder" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "workspace" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "workspace_env_var" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "workspace_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "value" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "workspace_env_var_workspace_id_fkey" FOREIGN KEY ("workspace_id") REFERENCES "workspace" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "environment_profile" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "environment_profile_var" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "environment_profile_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "value" TEXT NOT NULL,
+    "ordering" INTEGER NOT NULL DEFAULT 0,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "environment_profile_var_environment_profile_id_fkey" FOREIGN KEY ("environment_profile_id") REFERENCES "environment_profile" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_workspace_envprofile" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "workspace_id" INTEGER NOT NULL,
+    "environment_profile_id" INTEGER NOT NULL,
+    "is_active" BOOLEAN NOT NULL DEFAULT false,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "rel_workspace_envprofile_workspace_id_fkey" FOREIGN KEY ("workspace_id") REFERENCES "workspace" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_workspace_envprofile_environment_profile_id_fkey" FOREIGN KEY ("environment_profile_id") REFERENCES "environment_profile" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "secret_reference" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "backend" TEXT NOT NULL,
+    "updated_at" REAL NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_workspace_folder" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "workspace_id" INTEGER NOT NULL,
+    "folder_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "rel_workspace_folder_workspace_id_fkey" FOREIGN KEY ("workspace_id") REFERENCES "workspace" ("id") ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT "rel_workspace_folder_folder_id_fkey" FOREIGN KEY ("folder_id") REFERENCES "scripts_folder" ("id") ON DELETE RESTRICT ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "historical_shell_script" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "execution_time" REAL NOT NULL,
+    "finished_at" REAL,
+    "run_id" TEXT,
+    "execution_mode" TEXT,
+    "command" TEXT,
+    "working_dir" TEXT,
+    "exit_code" INTEGER,
+    "env_profile_id" INTEGER,
+    "env_profile_name" TEXT,
+    "stdout" TEXT,
+    "stderr" TEXT,
+    "output_truncated" BOOLEAN NOT NULL DEFAULT false,
+    "remote_host" TEXT,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "historical_script_chain" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "script_chain_id" INTEGER NOT NULL,
+    "chain_name" TEXT NOT NULL,
+    "run_id" TEXT NOT NULL,
+    "status" TEXT NOT NULL,
+    "executed_at" REAL NOT NULL,
+    "finished_at" REAL NOT NULL,
+    "step_results" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "event" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "request_id" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    "event_type" TEXT NOT NULL,
+    "event" TEXT NOT NULL,
+    "request_user_email" TEXT NOT NULL,
+    "success" BOOLEAN NOT NULL DEFAULT true,
+    "failure_reason" TEXT NOT NULL DEFAULT ''
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "ai_profile" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "description" TEXT NOT NULL,
+    "selected_model_config_id" INTEGER,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "ai_profile_selected_model_config_id_fkey" FOREIGN KEY ("selected_model_config_id") REFERENCES "model_config" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "script_ai_config" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "enabled_ai_search" BOOLEAN NOT NULL DEFAULT false,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_shellscript_aiconfig" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "script_ai_config_id" INTEGER NOT NULL,
+    CONSTRAINT "rel_shellscript_aiconfig_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_shellscript_aiconfig_script_ai_config_id_fkey" FOREIGN KEY ("script_ai_config_id") REFERENCES "script_ai_config" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "model_config" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "model_source" TEXT NOT NULL DEFAULT 'AZURE_OPENAI',
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_aiprofile_modelconfig" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "ai_profile_id" INTEGER NOT NULL,
+    "model_config_id" INTEGER NOT NULL,
+    CONSTRAINT "rel_aiprofile_modelconfig_ai_profile_id_fkey" FOREIGN KEY ("ai_profile_id") REFERENCES "ai_profile" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_aiprofile_modelconfig_model_config_id_fkey" FOREIGN KEY ("model_config_id") REFERENCES "model_config" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "azure_model_config" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "model_config_id" INTEGER NOT NULL,
+    "azure_openai_api_key" TEXT NOT NULL,
+    "azure_openai_endpoint" TEXT NOT NULL,
+    "azure_openai_api_version" TEXT NOT NULL,
+    "azure_openai_model" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "azure_model_config_model_config_id_fkey" FOREIGN KEY ("model_config_id") REFERENCES "model_config" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "openai_model_config" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "model_config_id" INTEGER NOT NULL,
+    "openai_api_key" TEXT NOT NULL,
+    "openai_model" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "openai_model_config_model_config_id_fkey" FOREIGN KEY ("model_config_id") REFERENCES "model_config" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "ai_scripted_tool" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL,
+    "tool_description" TEXT NOT NULL,
+    "is_enabled" BOOLEAN NOT NULL DEFAULT true,
+    "shell_script_id" INTEGER NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "ai_scripted_tool_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_aiprofile_aiscriptedtool" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "ai_profile_id" INTEGER NOT NULL,
+    "ai_scripted_tool_id" INTEGER NOT NULL,
+    CONSTRAINT "rel_aiprofile_aiscriptedtool_ai_profile_id_fkey" FOREIGN KEY ("ai_profile_id") REFERENCES "ai_profile" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_aiprofile_aiscriptedtool_ai_scripted_tool_id_fkey" FOREIGN KEY ("ai_scripted_tool_id") REFERENCES "ai_scripted_tool" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "workspace_status" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "name" TEXT NOT NULL DEFAULT 'ACTIVE'
+);
+
+-- CreateTable
+CREATE TABLE IF NOT EXISTS "rel_workspace_workspacestatus" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "workspace_status_id" INTEGER NOT NULL,
+    "workspace_id" INTEGER NOT NULL,
+    CONSTRAINT "rel_workspace_workspacestatus_workspace_status_id_fkey" FOREIGN KEY ("workspace_status_id") REFERENCES "workspace_status" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "rel_workspace_workspacestatus_workspace_id_fkey" FOREIGN KEY ("workspace_id") REFERENCES "workspace" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- AlterTable
+ALTER TABLE "historical_shell_script" ADD COLUMN "finished_at" REAL;
+ALTER TABLE "historical_shell_script" ADD COLUMN "run_id" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "execution_mode" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "command" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "working_dir" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "exit_code" INTEGER;
+ALTER TABLE "historical_shell_script" ADD COLUMN "env_profile_id" INTEGER;
+ALTER TABLE "historical_shell_script" ADD COLUMN "env_profile_name" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "stdout" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "stderr" TEXT;
+ALTER TABLE "historical_shell_script" ADD COLUMN "output_truncated" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "historical_shell_script" ADD COLUMN "remote_host" TEXT;
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "shell_script_execution_limit_shell_script_id_key" ON "shell_script_execution_limit"("shell_script_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "shell_script_run_config_shell_script_id_key" ON "shell_script_run_config"("shell_script_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "shell_script_env_var_shell_script_id_name_key" ON "shell_script_env_var"("shell_script_id", "name");
+CREATE INDEX IF NOT EXISTS "shell_script_env_var_shell_script_id_idx" ON "shell_script_env_var"("shell_script_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "shell_script_parameter_value_shell_script_id_name_key" ON "shell_script_parameter_value"("shell_script_id", "name");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "script_chain_step_script_chain_id_idx" ON "script_chain_step"("script_chain_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "script_dag_node_script_dag_id_shell_script_id_key" ON "script_dag_node"("script_dag_id", "shell_script_id");
+CREATE INDEX IF NOT EXISTS "script_dag_node_script_dag_id_idx" ON "script_dag_node"("script_dag_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "script_dag_edge_from_node_id_to_node_id_key" ON "script_dag_edge"("from_node_id", "to_node_id");
+CREATE INDEX IF NOT EXISTS "script_dag_edge_from_node_id_idx" ON "script_dag_edge"("from_node_id");
+CREATE INDEX IF NOT EXISTS "script_dag_edge_to_node_id_idx" ON "script_dag_edge"("to_node_id");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "script_schedule_shell_script_id_idx" ON "script_schedule"("shell_script_id");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "script_watch_trigger_shell_script_id_idx" ON "script_watch_trigger"("shell_script_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "ssh_target_name_key" ON "ssh_target"("name");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "ssh_host_group_name_key" ON "ssh_host_group"("name");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "ssh_host_group_member_ssh_host_group_id_ssh_target_id_key" ON "ssh_host_group_member"("ssh_host_group_id", "ssh_target_id");
+CREATE INDEX IF NOT EXISTS "ssh_host_group_member_ssh_target_id_idx" ON "ssh_host_group_member"("ssh_target_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "rel_folder_folder_parent_folder_id_child_folder_id_key" ON "rel_folder_folder"("parent_folder_id", "child_folder_id");
+CREATE INDEX IF NOT EXISTS "rel_folder_folder_parent_folder_id_idx" ON "rel_folder_folder"("parent_folder_id");
+CREATE INDEX IF NOT EXISTS "rel_folder_folder_child_folder_id_idx" ON "rel_folder_folder"("child_folder_id");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "workspace_id_idx" ON "workspace"("id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "workspace_env_var_workspace_id_name_key" ON "workspace_env_var"("workspace_id", "name");
+CREATE INDEX IF NOT EXISTS "workspace_env_var_workspace_id_idx" ON "workspace_env_var"("workspace_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "environment_profile_name_key" ON "environment_profile"("name");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "environment_profile_var_environment_profile_id_name_key" ON "environment_profile_var"("environment_profile_id", "name");
+CREATE INDEX IF NOT EXISTS "environment_profile_var_environment_profile_id_idx" ON "environment_profile_var"("environment_profile_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "rel_workspace_envprofile_workspace_id_environment_profile_id_key" ON "rel_workspace_envprofile"("workspace_id", "environment_profile_id");
+CREATE INDEX IF NOT EXISTS "rel_workspace_envprofile_workspace_id_idx" ON "rel_workspace_envprofile"("workspace_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "secret_reference_name_key" ON "secret_reference"("name");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "rel_workspace_folder_workspace_id_folder_id_key" ON "rel_workspace_folder"("workspace_id", "folder_id");
+CREATE INDEX IF NOT EXISTS "rel_workspace_folder_workspace_id_idx" ON "rel_workspace_folder"("workspace_id");
+CREATE INDEX IF NOT EXISTS "rel_workspace_folder_folder_id_idx" ON "rel_workspace_folder"("folder_id");
+
+-- CreateIndex
+CREATE INDEX IF NOT EXISTS "historical_shell_script_shell_script_id_idx" ON "historical_shell_script"("shell_script_id");
+CREATE INDEX IF NOT EXISTS "historical_shell_script_execution_time_idx" ON "historical_shell_script"("execution_time");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "historical_script_chain_run_id_key" ON "historical_script_chain"("run_id");
+CREATE INDEX IF NOT EXISTS "historical_script_chain_script_chain_id_idx" ON "historical_script_chain"("script_chain_id");
+
+-- CreateIndex
+CREATE UNIQUE INDEX IF NOT EXISTS "event_id_key" ON "event"("id");
@@ -0,0 +1,16 @@
+-- AlterTable
+ALTER TABLE "application_state" ADD COLUMN "mirror_dir" TEXT;
+
+-- CreateTable
+CREATE TABLE "library_mirror_entry" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "path" TEXT NOT NULL,
+    "synced_hash" TEXT NOT NULL,
+    "synced_at" REAL NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours')))
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "library_mirror_entry_shell_script_id_key" ON "library_mirror_entry"("shell_script_id");
@@ -0,0 +1,16 @@
+-- AlterTable
+ALTER TABLE "application_state" ADD COLUMN "revisions_kept" INTEGER DEFAULT 100;
+
+-- CreateTable
+CREATE TABLE "script_revision" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "shell_script_id" INTEGER NOT NULL,
+    "name" TEXT NOT NULL,
+    "command" TEXT NOT NULL,
+    "created_at" REAL NOT NULL DEFAULT (CAST((julianday('now') - 2440587.5) * 86400000.0 AS REAL)),
+    "created_at_hk" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', datetime('now', '+8 hours'))),
+    CONSTRAINT "script_revision_shell_script_id_fkey" FOREIGN KEY ("shell_script_id") REFERENCES "shell_script" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "script_revision_shell_script_id_idx" ON "script_revision"("shell_script_id");
@@ -0,0 +1,23 @@
+-- Revisions are recorded by the database, so edits saved through the Spring backend are kept
+-- just like the app's own. Only changes to the name or the command count.
+
+-- CreateTrigger
+CREATE TRIGGER "shell_script_record_revision"
+AFTER UPDATE OF "name", "command" ON "shell_script"
+WHEN OLD."name" IS NOT NEW."name" OR OLD."command" IS NOT NEW."command"
+BEGIN
+    -- Scripts saved before revisions existed also keep the state the edit replaces
+    INSERT INTO "script_revision" ("shell_script_id", "name", "command")
+    SELECT OLD."id", OLD."name", OLD."command"
+    WHERE NOT EXISTS (SELECT 1 FROM "script_revision" WHERE "shell_script_id" = OLD."id");
+    INSERT INTO "script_revision" ("shell_script_id", "name", "command")
+    VALUES (NEW."id", NEW."name", NEW."command");
+    -- Keep the newest "revisions_kept"; null keeps them all, no state row keeps 100
+    DELETE FROM "script_revision"
+    WHERE "shell_script_id" = NEW."id" AND "id" NOT IN (
+        SELECT "id" FROM "script_revision"
+        WHERE "shell_script_id" = NEW."id"
+        ORDER BY "id" DESC
+        LIMIT COALESCE((SELECT COALESCE("revisions_kept", -1) FROM "application_state" LIMIT 1), 100)
+    );
+END;
@@ -0,0 +1,336 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use rusqlite::{backup::Progress, Connection, DatabaseName, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::jobs::{self, JobState};
+
+/// Snapshots kept per reason in `backups/` next to the database; older ones are deleted.
+/// Counting each reason on its own keeps manual and daily backups from pushing out the copies
+/// taken before migrations, restores and imports.
+pub const KEEP: usize = 10;
+
+const DAILY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// How long to wait for a connection that is writing, e.g. the Spring backend's
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+// How long a restore waits for background work to let go of the database
+const GATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Background work holds the read side while it uses the database; a restore takes the write side
+static DATABASE_GATE: RwLock<()> = RwLock::const_new(());
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BackupReason {
+    Manual,
+    Daily,
+    /// Taken before pending migrations are applied to a database that already holds data.
+    PreMigration,
+    /// The database as it was before a backup was restored over it.
+    PreRestore,
+    /// Taken before a library archive is imported.
+    PreImport,
+}
+
+const REASONS: [BackupReason; 5] = [
+    BackupReason::Manual,
+    BackupReason::Daily,
+    BackupReason::PreMigration,
+    BackupReason::PreRestore,
+    BackupReason::PreImport,
+];
+
+impl BackupReason {
+    // Part of the file name
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupReason::Manual => "manual",
+            BackupReason::Daily => "daily",
+            BackupReason::PreMigration => "pre-migration",
+            BackupReason::PreRestore => "pre-restore",
+            BackupReason::PreImport => "pre-import",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// File name, used to pick a backup to restore.
+    pub name: String,
+    pub path: String,
+    pub reason: BackupReason,
+    /// Epoch milliseconds.
+    pub created_at: f64,
+    pub size_bytes: u64,
+}
+
+/// Snapshot the database with SQLite's online backup API, which copies a consistent state
+/// even while other connections write to it. The copy has to pass `PRAGMA integrity_check`
+/// before it is kept, then the oldest snapshots beyond [`KEEP`] of each reason are deleted.
+pub async fn create(db_path: &str, reason: BackupReason) -> Result<BackupInfo, String> {
+    let db_path = PathBuf::from(db_path);
+    tokio::task::spawn_blocking(move || {
+        let path = snapshot(&db_path, reason)?;
+        rotate(&db_path)?;
+        info(&db_path, &path)
+    })
+    .await
+    .map_err(|e| format!("Backup task failed: {}", e))?
+}
+
+/// Every backup of the database, newest first.
+pub fn list(db_path: &str) -> Result<Vec<BackupInfo>, String> {
+    let db_path = Path::new(db_path);
+    let dir = dir(db_path)?;
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut backups: Vec<BackupInfo> = entries
+        .flatten()
+        .filter_map(|entry| info(db_path, &entry.path()).ok())
+        .collect();
+    backups.sort_by(|a, b| b.created_at.total_cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Held for as long as runs, chains, graphs, fan-outs, schedules, watch triggers, mirror syncs,
+/// backups and imports use the database, so a restore never replaces the data underneath them.
+/// Work started during a restore waits until it is done. Not re-entrant: code holding one must
+/// not wait for another.
+pub async fn use_database() -> RwLockReadGuard<'static, ()> {
+    DATABASE_GATE.read().await
+}
+
+/// Exclusive use of the database for [`restore`].
+pub struct RestoreLock {
+    _gate: RwLockWriteGuard<'static, ()>,
+}
+
+/// Refuse while jobs are running, otherwise wait for background work to pause and keep it
+/// paused until the lock is dropped.
+pub async fn lock_for_restore() -> Result<RestoreLock, String> {
+    refuse_running_jobs()?;
+    let gate = tokio::time::timeout(GATE_TIMEOUT, DATABASE_GATE.write())
+        .await
+        .map_err(|_| {
+            "Background tasks are still using the database, try again once they have finished"
+                .to_string()
+        })?;
+    // One may have started while waiting
+    refuse_running_jobs()?;
+    Ok(RestoreLock { _gate: gate })
+}
+
+fn refuse_running_jobs() -> Result<(), String> {
+    let running: Vec<String> = jobs::list()?
+        .into_iter()
+        .filter(|job| job.state == JobState::Running)
+        .map(|job| job.run_id)
+        .collect();
+    if running.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Cannot restore while {} job(s) are running ({}); stop them or wait for them to finish",
+        running.len(),
+        running.join(", ")
+    ))
+}
+
+/// Replace the contents of the database with the backup `name`, after checking the backup and
+/// taking a [`BackupReason::PreRestore`] snapshot of the current state. Nothing else may write
+/// to the database in the meantime: the caller holds a [`RestoreLock`] and stops the Spring
+/// backend first. The Prisma client is reconnected to the restored file and migrations run
+/// afterwards, since the backup may predate the current schema.
+pub async fn restore(_lock: &RestoreLock, db_path: &str, name: &str) -> Result<BackupInfo, String> {
+    let backup = list(db_path)?
+        .into_iter()
+        .find(|backup| backup.name == name)
+        .ok_or_else(|| format!("Backup {} not found", name))?;
+
+    let path = PathBuf::from(db_path);
+    let source = PathBuf::from(&backup.path);
+    let safety = tokio::task::spawn_blocking(move || {
+        verify(&source)?;
+        let safety = snapshot(&path, BackupReason::PreRestore)?;
+
+        println!("Restoring database from {}", source.display());
+        let mut target = Connection::open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        target
+            .busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+        target
+            .restore(DatabaseName::Main, &source, None::<fn(Progress)>)
+            .map_err(|e| format!("Failed to restore {}: {}", source.display(), e))?;
+        drop(target);
+        verify(&path)?;
+
+        // Only now, so rotation cannot delete the backup being restored
+        rotate(&path)?;
+        info(&path, &safety)
+    })
+    .await
+    .map_err(|e| format!("Restore task failed: {}", e))??;
+
+    crate::db::get_db::reconnect().await?;
+    crate::db::migrations::run(crate::db::get_db::get_db(), db_path).await?;
+    println!("Database restored from {}", backup.name);
+    Ok(safety)
+}
+
+/// Take a daily backup in the background whenever the newest one is a day old.
+pub fn start(db_path: String) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let database = use_database().await;
+            match daily_backup_due(&db_path) {
+                Ok(true) => match create(&db_path, BackupReason::Daily).await {
+                    Ok(backup) => println!("Daily backup written to {}", backup.path),
+                    Err(e) => eprintln!("Daily backup failed: {}", e),
+                },
+                Ok(false) => {}
+                Err(e) => eprintln!("Daily backup: {}", e),
+            }
+            drop(database);
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+fn daily_backup_due(db_path: &str) -> Result<bool, String> {
+    let newest = list(db_path)?
+        .into_iter()
+        .find(|backup| backup.reason == BackupReason::Daily);
+    Ok(!newest.is_some_and(|backup| {
+        (Local::now().timestamp_millis() as f64 - backup.created_at)
+            < DAILY_INTERVAL.as_millis() as f64
+    }))
+}
+
+// Writes to a temporary name first, so a failed or interrupted copy is never listed
+fn snapshot(db_path: &Path, reason: BackupReason) -> Result<PathBuf, String> {
+    let dir = dir(db_path)?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let name = format!(
+        "{}-{}-{}.db",
+        stem(db_path),
+        Local::now().format(TIMESTAMP_FORMAT),
+        reason.as_str()
+    );
+    let path = dir.join(&name);
+    let partial = dir.join(format!("{}.partial", name));
+
+    let result = copy(db_path, &partial).and_then(|_| verify(&partial));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, &path)
+        .map_err(|e| format!("Failed to move backup to {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+fn copy(db_path: &Path, target: &Path) -> Result<(), String> {
+    let source = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+    source
+        .busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to set busy timeout: {}", e))?;
+    source
+        .backup(DatabaseName::Main, target, None)
+        .map_err(|e| format!("Failed to back up {}: {}", db_path.display(), e))
+}
+
+fn verify(path: &Path) -> Result<(), String> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let problems: Vec<String> = connection
+        .prepare("PRAGMA integrity_check")
+        .and_then(|mut statement| {
+            statement
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect()
+        })
+        .map_err(|e| format!("Failed to check {}: {}", path.display(), e))?;
+    if problems == ["ok"] {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} failed the integrity check: {}",
+            path.display(),
+            problems.join("; ")
+        ))
+    }
+}
+
+fn rotate(db_path: &Path) -> Result<(), String> {
+    let db_path = db_path.to_string_lossy();
+    let backups = list(&db_path)?;
+    for reason in REASONS {
+        let old = backups
+            .iter()
+            .filter(|backup| backup.reason == reason)
+            .skip(KEEP);
+        for backup in old {
+            println!("Removing old backup {}", backup.name);
+            std::fs::remove_file(&backup.path)
+                .map_err(|e| format!("Failed to remove {}: {}", backup.path, e))?;
+        }
+    }
+    Ok(())
+}
+
+// Only files named the way `snapshot` names them
+fn info(db_path: &Path, path: &Path) -> Result<BackupInfo, String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (timestamp, reason) = name
+        .strip_prefix(&format!("{}-", stem(db_path)))
+        .and_then(|rest| rest.strip_suffix(".db"))
+        .and_then(|rest| {
+            REASONS.into_iter().find_map(|reason| {
+                rest.strip_suffix(&format!("-{}", reason.as_str()))
+                    .map(|timestamp| (timestamp, reason))
+            })
+        })
+        .ok_or_else(|| format!("{} is not a backup", name))?;
+    let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .ok_or_else(|| format!("{} is not a backup", name))?;
+    let size_bytes = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    Ok(BackupInfo {
+        name,
+        path: path.to_string_lossy().to_string(),
+        reason,
+        created_at: created_at.timestamp_millis() as f64,
+        size_bytes,
+    })
+}
+
+fn dir(db_path: &Path) -> Result<PathBuf, String> {
+    Ok(db_path
+        .parent()
+        .ok_or_else(|| "Cannot determine db parent directory".to_string())?
+        .join("backups"))
+}
+
+fn stem(db_path: &Path) -> String {
+    db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "database".to_string())
+}
@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, EventTarget};
+
+use crate::backup;
+use crate::db::repository::chain_repository::{ChainRepository, NewChainRunRecord, NewChainStep};
+use crate::executor::history::ExecutionMode;
+use crate::executor::{self, ScriptOutputEvent, SCRIPT_OUTPUT_EVENT};
+use crate::jobs::{self, JobState};
+
+/// Emitted whenever a step changes state; output of the step itself arrives as `script-output`
+/// events carrying the step's `runId`.
+pub const CHAIN_STEP_EVENT: &str = "chain-step";
+/// Emitted exactly once when every step has been run or skipped.
+pub const CHAIN_FINISHED_EVENT: &str = "chain-finished";
+
+const MAX_RETRY_COUNT: i32 = 10;
+const MAX_DELAY_MS: i32 = 60 * 60 * 1000;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStep {
+    pub script_id: i32,
+    /// Run the next step even when this one still fails after its retries.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// How many times a failing step is run again before it counts as failed.
+    #[serde(default)]
+    pub retry_count: i32,
+    /// Wait before the step starts, and before each of its retries.
+    #[serde(default)]
+    pub delay_ms: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptChain {
+    /// `None` when saving a new chain.
+    pub id: Option<i32>,
+    pub name: String,
+    pub steps: Vec<ChainStep>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StepStatus {
+    Started,
+    Retrying,
+    Succeeded,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+/// How one step of a chain run ended; stored as JSON in `historical_script_chain.step_results`.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepResult {
+    pub step_index: usize,
+    pub script_id: i32,
+    pub status: StepStatus,
+    /// The run id of every attempt, each one linking to a `historical_shell_script` row.
+    pub run_ids: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Why the step could not be started.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainStepEvent {
+    pub chain_run_id: String,
+    pub chain_id: i32,
+    pub step_index: usize,
+    pub script_id: i32,
+    /// The run id of the current attempt; `None` for skipped steps.
+    pub run_id: Option<String>,
+    pub status: StepStatus,
+    /// Zero for the first attempt.
+    pub attempt: i32,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainFinishedEvent {
+    pub chain_run_id: String,
+    pub chain_id: i32,
+    /// `SUCCEEDED`, `FAILED` or `CANCELLED`.
+    pub status: String,
+    pub duration_ms: u64,
+    pub steps: Vec<StepResult>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainRunRecord {
+    pub id: i32,
+    pub chain_id: i32,
+    pub chain_name: String,
+    pub run_id: String,
+    pub status: String,
+    pub executed_at: f64,
+    pub finished_at: f64,
+    pub steps: Vec<StepResult>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainRunPage {
+    pub items: Vec<ChainRunRecord>,
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+pub async fn list_chains() -> Result<Vec<ScriptChain>, String> {
+    let chains = ChainRepository::new()
+        .get_all_chains()
+        .await
+        .map_err(|e| format!("Failed to load script chains: {}", e))?;
+    Ok(chains.into_iter().map(to_chain).collect())
+}
+
+/// Create or update a chain; its steps are replaced by `chain.steps` in the given order.
+pub async fn save_chain(chain: ScriptChain) -> Result<ScriptChain, String> {
+    let name = chain.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Chain name must not be empty".to_string());
+    }
+    if chain.steps.is_empty() {
+        return Err("A chain needs at least one step".to_string());
+    }
+    for (index, step) in chain.steps.iter().enumerate() {
+        if !(0..=MAX_RETRY_COUNT).contains(&step.retry_count) {
+            return Err(format!(
+                "Step {}: retry count must be between 0 and {}",
+                index + 1,
+                MAX_RETRY_COUNT
+            ));
+        }
+        if !(0..=MAX_DELAY_MS).contains(&step.delay_ms) {
+            return Err(format!(
+                "Step {}: delay must be between 0 and {} ms",
+                index + 1,
+                MAX_DELAY_MS
+            ));
+        }
+    }
+
+    let steps: Vec<NewChainStep> = chain
+        .steps
+        .iter()
+        .map(|step| NewChainStep {
+            script_id: step.script_id,
+            continue_on_error: step.continue_on_error,
+            retry_count: step.retry_count,
+            delay_ms: step.delay_ms,
+        })
+        .collect();
+    let repository = ChainRepository::new();
+    let chain_id = repository
+        .save_chain(chain.id, name, &steps)
+        .await
+        .map_err(|e| format!("Failed to save script chain: {}", e))?;
+    repository
+        .get_chain(chain_id)
+        .await
+        .map_err(|e| format!("Failed to load script chain {}: {}", chain_id, e))?
+        .map(to_chain)
+        .ok_or_else(|| format!("Script chain {} not found", chain_id))
+}
+
+pub async fn delete_chain(chain_id: i32) -> Result<(), String> {
+    ChainRepository::new()
+        .delete_chain(chain_id)
+        .await
+        .map_err(|e| format!("Failed to delete script chain {}: {}", chain_id, e))
+}
+
+pub async fn chain_history(
+    chain_id: i32,
+    page: i64,
+    page_size: i64,
+) -> Result<ChainRunPage, String> {
+    let (runs, total) = ChainRepository::new()
+        .get_runs(chain_id, page, page_size)
+        .await
+        .map_err(|e| format!("Failed to load chain history: {}", e))?;
+    let items = runs
+        .into_iter()
+        .map(|run| ChainRunRecord {
+            steps: serde_json::from_str(&run.step_results).unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to parse step results of chain run {}: {}",
+                    run.run_id, e
+                );
+                vec![]
+            }),
+            id: run.id,
+            chain_id: run.script_chain_id,
+            chain_name: run.chain_name,
+            run_id: run.run_id,
+            status: run.status,
+            executed_at: run.executed_at,
+            finished_at: run.finished_at,
+        })
+        .collect();
+    Ok(ChainRunPage {
+        items,
+        page,
+        page_size,
+        total,
+    })
+}
+
+/// Start running the chain in the background, reporting progress to the webview window
+/// `label`. Returns the id of the chain run, which every step and finished event carries.
+pub async fn start_chain(
+    app: tauri::AppHandle,
+    label: String,
+    chain_id: i32,
+) -> Result<String, String> {
+    let chain = ChainRepository::new()
+        .get_chain(chain_id)
+        .await
+        .map_err(|e| format!("Failed to load script chain {}: {}", chain_id, e))?
+        .ok_or_else(|| format!("Script chain {} not found", chain_id))?;
+    let chain_name = chain.name.clone();
+    let chain = to_chain(chain);
+    if chain.steps.is_empty() {
+        return Err(format!("Script chain '{}' has no steps", chain_name));
+    }
+
+    let chain_run_id = executor::new_run_id();
+    let run = ChainRun {
+        app,
+        target: EventTarget::webview_window(label),
+        chain_id,
+        chain_run_id: chain_run_id.clone(),
+    };
+    tauri::async_runtime::spawn(async move {
+        let _database = backup::use_database().await;
+        run.execute(chain_name, chain.steps).await;
+    });
+    Ok(chain_run_id)
+}
+
+struct ChainRun {
+    app: tauri::AppHandle,
+    target: EventTarget,
+    chain_id: i32,
+    chain_run_id: String,
+}
+
+impl ChainRun {
+    async fn execute(self, chain_name: String, steps: Vec<ChainStep>) {
+        let started = Instant::now();
+        let executed_at = jobs::now_ms();
+        println!(
+            "Running script chain '{}' ({} steps) as {}",
+            chain_name,
+            steps.len(),
+            self.chain_run_id
+        );
+
+        let mut results = Vec::with_capacity(steps.len());
+        // Why the remaining steps are skipped, if they are
+        let mut stopped: Option<StepStatus> = None;
+        for (index, step) in steps.iter().enumerate() {
+            if stopped.is_some() {
+                self.emit_step(self.step_event(index, step, StepStatus::Skipped));
+                results.push(StepResult {
+                    step_index: index,
+                    script_id: step.script_id,
+                    status: StepStatus::Skipped,
+                    run_ids: vec![],
+                    exit_code: None,
+                    duration_ms: 0,
+                    error: None,
+                });
+                continue;
+            }
+
+            let result = self.run_step(index, step).await;
+            match result.status {
+                StepStatus::Cancelled => stopped = Some(StepStatus::Cancelled),
+                StepStatus::Failed if !step.continue_on_error => stopped = Some(StepStatus::Failed),
+                _ => {}
+            }
+            results.push(result);
+        }
+
+        let status = match stopped {
+            Some(StepStatus::Cancelled) => "CANCELLED",
+            Some(_) => "FAILED",
+            None => "SUCCEEDED",
+        };
+        let duration = started.elapsed();
+        println!(
+            "Script chain {} finished with status {}",
+            self.chain_run_id, status
+        );
+
+        let record = NewChainRunRecord {
+            chain_id: self.chain_id,
+            chain_name,
+            run_id: self.chain_run_id.clone(),
+            status: status.to_string(),
+            executed_at,
+            finished_at: executed_at + duration.as_millis() as f64,
+            step_results: serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string()),
+        };
+        if let Err(e) = ChainRepository::new().record_run(record).await {
+            eprintln!("Failed to record script chain history: {}", e);
+        }
+
+        let finished = ChainFinishedEvent {
+            chain_run_id: self.chain_run_id.clone(),
+            chain_id: self.chain_id,
+            status: status.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            steps: results,
+        };
+        if let Err(e) = self
+            .app
+            .emit_to(self.target.clone(), CHAIN_FINISHED_EVENT, finished)
+        {
+            eprintln!("Failed to emit {}: {}", CHAIN_FINISHED_EVENT, e);
+        }
+    }
+
+    // Run one step through the silent execution path, retrying it while it fails
+    async fn run_step(&self, index: usize, step: &ChainStep) -> StepResult {
+        let started = Instant::now();
+        let mut result = StepResult {
+            step_index: index,
+            script_id: step.script_id,
+            status: StepStatus::Failed,
+            run_ids: vec![],
+            exit_code: None,
+            duration_ms: 0,
+            error: None,
+        };
+
+        for attempt in 0..=step.retry_count.max(0) {
+            if step.delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(step.delay_ms as u64)).await;
+            }
+            let run_id = executor::new_run_id();
+            result.run_ids.push(run_id.clone());
+            self.emit_step(ChainStepEvent {
+                run_id: Some(run_id.clone()),
+                attempt,
+                ..self.step_event(index, step, StepStatus::Started)
+            });
+
+            let (status, exit_code, error) = match self.run_script(step.script_id, &run_id).await {
+                Ok(outcome) => {
+                    let cancelled =
+                        jobs::get(&run_id).is_ok_and(|info| info.state == JobState::Cancelled);
+                    let status = if cancelled {
+                        StepStatus::Cancelled
+                    } else if outcome.success {
+                        StepStatus::Succeeded
+                    } else {
+                        StepStatus::Failed
+                    };
+                    (status, outcome.exit_code, None)
+                }
+                Err(e) => {
+                    eprintln!("Chain step {} could not be started: {}", index + 1, e);
+                    (StepStatus::Failed, None, Some(e))
+                }
+            };
+            result.status = status;
+            result.exit_code = exit_code;
+            result.error = error.clone();
+
+            let retry = status == StepStatus::Failed && attempt < step.retry_count;
+            let reported = if retry { StepStatus::Retrying } else { status };
+            self.emit_step(ChainStepEvent {
+                run_id: Some(run_id),
+                attempt,
+                exit_code,
+                error,
+                ..self.step_event(index, step, reported)
+            });
+            if !retry {
+                break;
+            }
+        }
+
+        result.duration_ms = started.elapsed().as_millis() as u64;
+        result
+    }
+
+    async fn run_script(
+        &self,
+        script_id: i32,
+        run_id: &str,
+    ) -> Result<executor::ExecutionOutcome, String> {
+        executor::run_stored_script(
+            script_id,
+            run_id,
+            ExecutionMode::Chain,
+            vec![],
+            |stream, line| {
+                let event = ScriptOutputEvent {
+                    run_id: run_id.to_string(),
+                    stream,
+                    line,
+                };
+                if let Err(e) = self
+                    .app
+                    .emit_to(self.target.clone(), SCRIPT_OUTPUT_EVENT, event)
+                {
+                    eprintln!("Failed to emit {}: {}", SCRIPT_OUTPUT_EVENT, e);
+                }
+            },
+        )
+        .await
+    }
+
+    // A step event without attempt details, to be filled in by the caller
+    fn step_event(&self, index: usize, step: &ChainStep, status: StepStatus) -> ChainStepEvent {
+        ChainStepEvent {
+            chain_run_id: self.chain_run_id.clone(),
+            chain_id: self.chain_id,
+            step_index: index,
+            script_id: step.script_id,
+            run_id: None,
+            status,
+            attempt: 0,
+            exit_code: None,
+            error: None,
+        }
+    }
+
+    fn emit_step(&self, event: ChainStepEvent) {
+        if let Err(e) = self
+            .app
+            .emit_to(self.target.clone(), CHAIN_STEP_EVENT, event)
+        {
+            eprintln!("Failed to emit {}: {}", CHAIN_STEP_EVENT, e);
+        }
+    }
+}
+
+fn to_chain(chain: crate::prisma::script_chain::Data) -> ScriptChain {
+    let steps = chain
+        .script_chain_step
+        .unwrap_or_default()
+        .into_iter()
+        .map(|step| ChainStep {
+            script_id: step.shell_script_id,
+            continue_on_error: step.continue_on_error,
+            retry_count: step.retry_count,
+            delay_ms: step.delay_ms,
+        })
+        .collect();
+    ScriptChain {
+        id: Some(chain.id),
+        name: chain.name,
+        steps,
+    }
+}
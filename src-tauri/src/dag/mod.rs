@@ -0,0 +1,599 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+use tauri::{Emitter, EventTarget};
+use tokio::task::JoinSet;
+
+use crate::backup;
+use crate::db::repository::dag_repository::DagRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::executor::history::ExecutionMode;
+use crate::executor::{self, ScriptOutputEvent, SCRIPT_OUTPUT_EVENT};
+use crate::jobs::{self, JobState};
+use crate::RT_HANDLE;
+
+/// Emitted whenever a node changes state; output of the node itself arrives as `script-output`
+/// events carrying the node's `runId`.
+pub const DAG_NODE_EVENT: &str = "dag-node";
+/// Emitted exactly once when every node has either run or been cancelled.
+pub const DAG_FINISHED_EVENT: &str = "dag-finished";
+
+const DEFAULT_MAX_PARALLEL: i32 = 4;
+const MAX_PARALLEL_LIMIT: i32 = 32;
+
+/// `to_script_id` only starts once `from_script_id` has succeeded.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DagEdge {
+    pub from_script_id: i32,
+    pub to_script_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptDag {
+    /// `None` when saving a new graph.
+    pub id: Option<i32>,
+    pub name: String,
+    /// How many nodes may run at the same time.
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: i32,
+    /// Script ids; a script appears at most once per graph.
+    pub nodes: Vec<i32>,
+    pub edges: Vec<DagEdge>,
+}
+
+fn default_max_parallel() -> i32 {
+    DEFAULT_MAX_PARALLEL
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum NodeStatus {
+    Started,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeResult {
+    pub script_id: i32,
+    pub status: NodeStatus,
+    /// Links the node to its `historical_shell_script` row; `None` if it never started.
+    pub run_id: Option<String>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Why the node could not be started.
+    pub error: Option<String>,
+    /// The dependency that kept a cancelled node from running: its `from` end failed or was
+    /// cancelled itself.
+    pub blocked_by: Option<DagEdge>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DagNodeEvent {
+    pub dag_run_id: String,
+    pub dag_id: i32,
+    #[serde(flatten)]
+    pub node: NodeResult,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DagFinishedEvent {
+    pub dag_run_id: String,
+    pub dag_id: i32,
+    /// `SUCCEEDED`, `FAILED` or `CANCELLED`.
+    pub status: String,
+    pub duration_ms: u64,
+    pub nodes: Vec<NodeResult>,
+}
+
+pub async fn list_dags() -> Result<Vec<ScriptDag>, String> {
+    let dags = DagRepository::new()
+        .get_all_dags()
+        .await
+        .map_err(|e| format!("Failed to load script graphs: {}", e))?;
+    Ok(dags.into_iter().map(to_dag).collect())
+}
+
+/// Create or update a graph after checking that its edges connect its own nodes and form no
+/// cycle.
+pub async fn save_dag(dag: ScriptDag) -> Result<ScriptDag, String> {
+    let name = dag.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Graph name must not be empty".to_string());
+    }
+    if !(1..=MAX_PARALLEL_LIMIT).contains(&dag.max_parallel) {
+        return Err(format!(
+            "Parallelism must be between 1 and {}",
+            MAX_PARALLEL_LIMIT
+        ));
+    }
+    validate(&dag.nodes, &dag.edges)?;
+    if let Some(cycle) = find_cycle(&dag.nodes, &dag.edges) {
+        let mut names = Vec::with_capacity(cycle.len());
+        for script_id in cycle {
+            names.push(script_name(script_id).await);
+        }
+        return Err(format!("Dependency cycle: {}", names.join(" → ")));
+    }
+
+    let edges: Vec<(i32, i32)> = dag
+        .edges
+        .iter()
+        .map(|edge| (edge.from_script_id, edge.to_script_id))
+        .collect();
+    let repository = DagRepository::new();
+    let dag_id = repository
+        .save_dag(dag.id, name, dag.max_parallel, &dag.nodes, &edges)
+        .await
+        .map_err(|e| format!("Failed to save script graph: {}", e))?;
+    repository
+        .get_dag(dag_id)
+        .await
+        .map_err(|e| format!("Failed to load script graph {}: {}", dag_id, e))?
+        .map(to_dag)
+        .ok_or_else(|| format!("Script graph {} not found", dag_id))
+}
+
+pub async fn delete_dag(dag_id: i32) -> Result<(), String> {
+    DagRepository::new()
+        .delete_dag(dag_id)
+        .await
+        .map_err(|e| format!("Failed to delete script graph {}: {}", dag_id, e))
+}
+
+/// Start running the graph on the shared runtime, reporting progress to the webview window
+/// `label`. `max_parallel` overrides the cap stored with the graph. Returns the id of the graph
+/// run, which every node and finished event carries.
+pub async fn start_dag(
+    app: tauri::AppHandle,
+    label: String,
+    dag_id: i32,
+    max_parallel: Option<i32>,
+) -> Result<String, String> {
+    let dag = DagRepository::new()
+        .get_dag(dag_id)
+        .await
+        .map_err(|e| format!("Failed to load script graph {}: {}", dag_id, e))?
+        .ok_or_else(|| format!("Script graph {} not found", dag_id))?;
+    let dag = to_dag(dag);
+    if dag.nodes.is_empty() {
+        return Err(format!("Script graph '{}' has no nodes", dag.name));
+    }
+    let max_parallel = max_parallel
+        .unwrap_or(dag.max_parallel)
+        .clamp(1, MAX_PARALLEL_LIMIT) as usize;
+
+    let handle = RT_HANDLE
+        .get()
+        .ok_or_else(|| "Runtime not initialized".to_string())?
+        .clone();
+    let dag_run_id = executor::new_run_id();
+    let run = DagRun {
+        app,
+        target: EventTarget::webview_window(label),
+        dag_id,
+        dag_run_id: dag_run_id.clone(),
+    };
+    println!(
+        "Running script graph '{}' ({} nodes, up to {} at a time) as {}",
+        dag.name,
+        dag.nodes.len(),
+        max_parallel,
+        dag_run_id
+    );
+    handle.clone().spawn(async move {
+        let _database = backup::use_database().await;
+        run.execute(dag, max_parallel, handle).await;
+    });
+    Ok(dag_run_id)
+}
+
+#[derive(Clone)]
+struct DagRun {
+    app: tauri::AppHandle,
+    target: EventTarget,
+    dag_id: i32,
+    dag_run_id: String,
+}
+
+impl DagRun {
+    async fn execute(self, dag: ScriptDag, max_parallel: usize, handle: tokio::runtime::Handle) {
+        let started = Instant::now();
+        let mut waiting_on: HashMap<i32, usize> = dag.nodes.iter().map(|id| (*id, 0)).collect();
+        let mut dependents: HashMap<i32, Vec<i32>> = HashMap::new();
+        for edge in &dag.edges {
+            *waiting_on.entry(edge.to_script_id).or_default() += 1;
+            dependents
+                .entry(edge.from_script_id)
+                .or_default()
+                .push(edge.to_script_id);
+        }
+
+        let mut ready: VecDeque<i32> = dag
+            .nodes
+            .iter()
+            .copied()
+            .filter(|id| waiting_on[id] == 0)
+            .collect();
+        let mut results: HashMap<i32, NodeResult> = HashMap::new();
+        let mut running = JoinSet::new();
+        loop {
+            while running.len() < max_parallel {
+                let Some(script_id) = ready.pop_front() else {
+                    break;
+                };
+                let run = self.clone();
+                running.spawn_on(async move { run.run_node(script_id).await }, &handle);
+            }
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let result = match joined {
+                Ok(result) => result,
+                Err(e) => {
+                    // The node is reported as never run below
+                    eprintln!("Script graph node task failed: {}", e);
+                    continue;
+                }
+            };
+
+            let script_id = result.script_id;
+            if result.status == NodeStatus::Succeeded {
+                for dependent in dependents.get(&script_id).into_iter().flatten() {
+                    let count = waiting_on.get_mut(dependent).unwrap();
+                    *count -= 1;
+                    if *count == 0 && !results.contains_key(dependent) {
+                        ready.push_back(*dependent);
+                    }
+                }
+            } else {
+                self.cancel_downstream(script_id, &dependents, &mut results);
+            }
+            results.insert(script_id, result);
+        }
+
+        let nodes: Vec<NodeResult> = dag
+            .nodes
+            .iter()
+            .map(|script_id| {
+                results.remove(script_id).unwrap_or_else(|| NodeResult {
+                    error: Some("Node was never run".to_string()),
+                    ..not_started(*script_id, NodeStatus::Cancelled)
+                })
+            })
+            .collect();
+        let status = if nodes
+            .iter()
+            .all(|node| node.status == NodeStatus::Succeeded)
+        {
+            "SUCCEEDED"
+        } else if nodes
+            .iter()
+            .any(|node| node.status == NodeStatus::Cancelled && node.blocked_by.is_none())
+        {
+            "CANCELLED"
+        } else {
+            "FAILED"
+        };
+        let duration = started.elapsed();
+        println!(
+            "Script graph {} finished with status {}",
+            self.dag_run_id, status
+        );
+
+        let finished = DagFinishedEvent {
+            dag_run_id: self.dag_run_id.clone(),
+            dag_id: self.dag_id,
+            status: status.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            nodes,
+        };
+        if let Err(e) = self
+            .app
+            .emit_to(self.target.clone(), DAG_FINISHED_EVENT, finished)
+        {
+            eprintln!("Failed to emit {}: {}", DAG_FINISHED_EVENT, e);
+        }
+    }
+
+    async fn run_node(self, script_id: i32) -> NodeResult {
+        let started = Instant::now();
+        let run_id = executor::new_run_id();
+        self.emit_node(NodeResult {
+            run_id: Some(run_id.clone()),
+            ..not_started(script_id, NodeStatus::Started)
+        });
+
+        let outcome = executor::run_stored_script(
+            script_id,
+            &run_id,
+            ExecutionMode::Dag,
+            vec![],
+            |stream, line| {
+                let event = ScriptOutputEvent {
+                    run_id: run_id.clone(),
+                    stream,
+                    line,
+                };
+                if let Err(e) = self
+                    .app
+                    .emit_to(self.target.clone(), SCRIPT_OUTPUT_EVENT, event)
+                {
+                    eprintln!("Failed to emit {}: {}", SCRIPT_OUTPUT_EVENT, e);
+                }
+            },
+        )
+        .await;
+        let result = match outcome {
+            Ok(outcome) => {
+                let cancelled =
+                    jobs::get(&run_id).is_ok_and(|info| info.state == JobState::Cancelled);
+                let status = if cancelled {
+                    NodeStatus::Cancelled
+                } else if outcome.success {
+                    NodeStatus::Succeeded
+                } else {
+                    NodeStatus::Failed
+                };
+                NodeResult {
+                    exit_code: outcome.exit_code,
+                    ..not_started(script_id, status)
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Script graph node {} could not be started: {}",
+                    script_id, e
+                );
+                NodeResult {
+                    error: Some(e),
+                    ..not_started(script_id, NodeStatus::Failed)
+                }
+            }
+        };
+        let result = NodeResult {
+            run_id: Some(run_id),
+            duration_ms: started.elapsed().as_millis() as u64,
+            ..result
+        };
+        self.emit_node(result.clone());
+        result
+    }
+
+    // Everything that depends on `failed`, directly or not, will never run. Each node records the
+    // edge through which it was first reached.
+    fn cancel_downstream(
+        &self,
+        failed: i32,
+        dependents: &HashMap<i32, Vec<i32>>,
+        results: &mut HashMap<i32, NodeResult>,
+    ) {
+        let mut queue = VecDeque::from([failed]);
+        while let Some(from) = queue.pop_front() {
+            for to in dependents.get(&from).into_iter().flatten() {
+                if results.contains_key(to) {
+                    continue;
+                }
+                let result = NodeResult {
+                    blocked_by: Some(DagEdge {
+                        from_script_id: from,
+                        to_script_id: *to,
+                    }),
+                    ..not_started(*to, NodeStatus::Cancelled)
+                };
+                self.emit_node(result.clone());
+                results.insert(*to, result);
+                queue.push_back(*to);
+            }
+        }
+    }
+
+    fn emit_node(&self, node: NodeResult) {
+        let event = DagNodeEvent {
+            dag_run_id: self.dag_run_id.clone(),
+            dag_id: self.dag_id,
+            node,
+        };
+        if let Err(e) = self.app.emit_to(self.target.clone(), DAG_NODE_EVENT, event) {
+            eprintln!("Failed to emit {}: {}", DAG_NODE_EVENT, e);
+        }
+    }
+}
+
+fn not_started(script_id: i32, status: NodeStatus) -> NodeResult {
+    NodeResult {
+        script_id,
+        status,
+        run_id: None,
+        exit_code: None,
+        duration_ms: 0,
+        error: None,
+        blocked_by: None,
+    }
+}
+
+fn validate(nodes: &[i32], edges: &[DagEdge]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for script_id in nodes {
+        if !seen.insert(*script_id) {
+            return Err(format!(
+                "Script {} is in the graph more than once",
+                script_id
+            ));
+        }
+    }
+    let mut seen_edges = HashSet::new();
+    for edge in edges {
+        if !seen.contains(&edge.from_script_id) || !seen.contains(&edge.to_script_id) {
+            return Err(format!(
+                "Dependency {} → {} refers to a script that is not in the graph",
+                edge.from_script_id, edge.to_script_id
+            ));
+        }
+        if !seen_edges.insert(*edge) {
+            return Err(format!(
+                "Dependency {} → {} is listed more than once",
+                edge.from_script_id, edge.to_script_id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A dependency cycle in the graph, as the script ids along it with the first one repeated at
+/// the end, or `None` if the graph is acyclic.
+fn find_cycle(nodes: &[i32], edges: &[DagEdge]) -> Option<Vec<i32>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Unvisited,
+        OnPath,
+        Done,
+    }
+
+    let mut dependents: HashMap<i32, Vec<i32>> = HashMap::new();
+    for edge in edges {
+        dependents
+            .entry(edge.from_script_id)
+            .or_default()
+            .push(edge.to_script_id);
+    }
+    let mut marks: HashMap<i32, Mark> = nodes.iter().map(|id| (*id, Mark::Unvisited)).collect();
+
+    for start in nodes {
+        if marks[start] != Mark::Unvisited {
+            continue;
+        }
+        // Iterative depth-first search; `path` holds the nodes on the current branch and the
+        // index of the next dependent to visit from each
+        let mut path: Vec<(i32, usize)> = vec![(*start, 0)];
+        marks.insert(*start, Mark::OnPath);
+        while let Some((node, next)) = path.last_mut() {
+            let node = *node;
+            let children = dependents.get(&node).map(Vec::as_slice).unwrap_or(&[]);
+            if *next == children.len() {
+                marks.insert(node, Mark::Done);
+                path.pop();
+                continue;
+            }
+            let child = children[*next];
+            *next += 1;
+            match marks.get(&child).copied().unwrap_or(Mark::Done) {
+                Mark::OnPath => {
+                    let start = path.iter().position(|(id, _)| *id == child).unwrap();
+                    let mut cycle: Vec<i32> = path[start..].iter().map(|(id, _)| *id).collect();
+                    cycle.push(child);
+                    return Some(cycle);
+                }
+                Mark::Unvisited => {
+                    marks.insert(child, Mark::OnPath);
+                    path.push((child, 0));
+                }
+                Mark::Done => {}
+            }
+        }
+    }
+    None
+}
+
+async fn script_name(script_id: i32) -> String {
+    match ScriptRepository::new().get_script(script_id).await {
+        Ok(Some(script)) => script.name,
+        _ => format!("script {}", script_id),
+    }
+}
+
+fn to_dag(dag: crate::prisma::script_dag::Data) -> ScriptDag {
+    let nodes = dag.script_dag_node.unwrap_or_default();
+    let script_of: HashMap<i32, i32> = nodes
+        .iter()
+        .map(|node| (node.id, node.shell_script_id))
+        .collect();
+    let mut edges = vec![];
+    for node in &nodes {
+        for edge in node.outgoing_edges.iter().flatten() {
+            if let Some(to_script_id) = script_of.get(&edge.to_node_id) {
+                edges.push(DagEdge {
+                    from_script_id: node.shell_script_id,
+                    to_script_id: *to_script_id,
+                });
+            }
+        }
+    }
+    ScriptDag {
+        id: Some(dag.id),
+        name: dag.name,
+        max_parallel: dag.max_parallel,
+        nodes: nodes.iter().map(|node| node.shell_script_id).collect(),
+        edges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges(pairs: &[(i32, i32)]) -> Vec<DagEdge> {
+        pairs
+            .iter()
+            .map(|(from, to)| DagEdge {
+                from_script_id: *from,
+                to_script_id: *to,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accepts_an_acyclic_graph() {
+        let nodes = [1, 2, 3, 4];
+        let edges = edges(&[(1, 2), (1, 3), (2, 4), (3, 4)]);
+        assert!(validate(&nodes, &edges).is_ok());
+        assert_eq!(find_cycle(&nodes, &edges), None);
+    }
+
+    #[test]
+    fn finds_a_self_loop() {
+        let nodes = [1, 2];
+        let edges = edges(&[(1, 2), (2, 2)]);
+        assert!(validate(&nodes, &edges).is_ok());
+        assert_eq!(find_cycle(&nodes, &edges), Some(vec![2, 2]));
+    }
+
+    #[test]
+    fn reports_the_path_of_a_three_node_cycle() {
+        let nodes = [1, 2, 3, 4];
+        let edges = edges(&[(4, 1), (1, 2), (2, 3), (3, 1)]);
+        assert!(validate(&nodes, &edges).is_ok());
+        assert_eq!(find_cycle(&nodes, &edges), Some(vec![1, 2, 3, 1]));
+    }
+
+    #[test]
+    fn finds_a_cycle_reached_through_a_finished_branch() {
+        let nodes = [1, 2, 3, 4, 5];
+        let edges = edges(&[(1, 2), (1, 3), (3, 2), (3, 4), (4, 5), (5, 3)]);
+        assert_eq!(find_cycle(&nodes, &edges), Some(vec![3, 4, 5, 3]));
+    }
+
+    #[test]
+    fn rejects_a_duplicate_edge() {
+        let error = validate(&[1, 2], &edges(&[(1, 2), (1, 2)])).unwrap_err();
+        assert!(error.contains("more than once"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_an_edge_to_an_unknown_node() {
+        let error = validate(&[1, 2], &edges(&[(1, 3)])).unwrap_err();
+        assert!(error.contains("not in the graph"), "{}", error);
+        assert!(validate(&[1, 2], &edges(&[(3, 1)])).is_err());
+    }
+
+    #[test]
+    fn rejects_a_duplicate_node() {
+        assert!(validate(&[1, 2, 1], &[]).is_err());
+    }
+}
@@ -1,33 +1,31 @@
 use crate::prisma::PrismaClient;
 
 pub fn get_db() -> &'static PrismaClient {
-    crate::PRISMA_CLIENT.get().unwrap()
+    let client = *crate::PRISMA_CLIENT.read().unwrap();
+    client.expect("Prisma client not initialized")
 }
 
-pub async fn initialize_database(client: &PrismaClient) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Initializing database...");
-
-    // Apply all migrations programmatically
-    match client._migrate_deploy().await {
-        Ok(_) => {
-            println!("Database initialized successfully");
-        }
-        Err(e) => {
-            println!(
-                "Migration failed, checking if database is already initialized: {}",
-                e
-            );
-            // Check if database is already initialized
-            match client.scripts_folder().count(vec![]).exec().await {
-                Ok(_) => {
-                    println!("Database appears to be already initialized");
-                }
-                Err(_) => {
-                    return Err("Database initialization failed".into());
-                }
-            }
-        }
-    }
+/// Make `client` the one `get_db` hands out. A client it replaces is leaked rather than
+/// dropped, since repositories created earlier may still hold it.
+pub fn set_db(client: PrismaClient) {
+    let client: &'static PrismaClient = Box::leak(Box::new(client));
+    *crate::PRISMA_CLIENT.write().unwrap() = Some(client);
+}
 
+/// Switch to a new client on `DATABASE_URL`, after the database file was replaced underneath
+/// the current one.
+pub async fn reconnect() -> Result<(), String> {
+    let database_url =
+        std::env::var("DATABASE_URL").map_err(|_| "DATABASE_URL is not set".to_string())?;
+    let client = crate::prisma::new_client_with_url(&database_url)
+        .await
+        .map_err(|e| format!("Failed to create Prisma client: {}", e))?;
+    // SQLite disables foreign keys on every new connection
+    client
+        ._execute_raw(prisma_client_rust::raw!("PRAGMA foreign_keys = ON"))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+    set_db(client);
     Ok(())
 }
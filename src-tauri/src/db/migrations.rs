@@ -0,0 +1,681 @@
+use prisma_client_rust::{PrismaValue, Raw};
+use serde::{Deserialize, Serialize};
+
+use crate::backup::{self, BackupReason};
+use crate::prisma::PrismaClient;
+
+// Applied versions are recorded here. Prisma's own `_prisma_migrations` is left alone.
+const TRACKING_TABLE: &str = "_ssm_migrations";
+
+struct Migration {
+    version: &'static str,
+    sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal) => {
+        Migration {
+            version: $version,
+            sql: include_str!(concat!(
+                "../../prisma/migrations/",
+                $version,
+                "/migration.sql"
+            )),
+        }
+    };
+}
+
+/// Every directory in `prisma/migrations`, oldest first. A new migration has to be added here.
+const MIGRATIONS: [Migration; 9] = [
+    migration!("20251014224805_init"),
+    migration!("20251016224051_add_index"),
+    migration!("20251018095335_add_app_state"),
+    migration!("20251029152544_init"),
+    migration!("20251030072458_add_dark_mode"),
+    migration!("20261017120000_sync_schema"),
+    migration!("20261017130000_add_library_mirror"),
+    migration!("20261017140000_add_script_revisions"),
+    migration!("20261017150000_record_script_revisions"),
+];
+
+// Databases from before the runner were kept in sync with `db push` and already contain what
+// these describe. They are recorded as a baseline instead of being run; the migration after
+// them brings such databases up to date.
+const PUSHED_BASELINE: [&str; 5] = [
+    "20251014224805_init",
+    "20251016224051_add_index",
+    "20251018095335_add_app_state",
+    "20251029152544_init",
+    "20251030072458_add_dark_mode",
+];
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedMigration {
+    pub version: String,
+    /// UTC, `YYYY-MM-DD HH:MM:SS`.
+    pub applied_at: String,
+    /// MIGRATED when the runner ran it, BASELINE when an existing database already had it.
+    pub kind: String,
+    /// The backup taken before it was applied; `None` for a fresh database or a baseline.
+    pub backup_path: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<String>,
+    pub last_backup: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NameRow {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct VersionRow {
+    version: String,
+}
+
+/// Apply every migration the database has not seen yet, oldest first, each in its own
+/// transaction. A database that already holds data is backed up first, whatever the migrations
+/// do; if that fails nothing is migrated.
+pub async fn run(client: &PrismaClient, db_path: &str) -> Result<(), String> {
+    let tables = table_names(client).await?;
+    let tracked = tables.iter().any(|table| table == TRACKING_TABLE);
+    let fresh = tables.iter().all(|table| table.starts_with('_'));
+
+    execute(
+        client,
+        &format!(
+            "CREATE TABLE IF NOT EXISTS \"{}\" (
+    \"version\" TEXT NOT NULL PRIMARY KEY,
+    \"applied_at\" TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%S', 'now')),
+    \"kind\" TEXT NOT NULL,
+    \"backup_path\" TEXT
+)",
+            TRACKING_TABLE
+        ),
+    )
+    .await?;
+
+    if !tracked && !fresh {
+        println!("Recording the schema of an existing database as the migration baseline");
+        for version in PUSHED_BASELINE {
+            record(client, version, "BASELINE", None).await?;
+        }
+    }
+
+    let applied = applied_versions(client).await?;
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.iter().any(|version| version == migration.version))
+        .collect();
+    if pending.is_empty() {
+        println!("Database schema is up to date");
+        return Ok(());
+    }
+
+    // Only the data already in the database is at risk. Changes that look harmless can still
+    // fail halfway or rewrite data, so any pending migration gets a backup.
+    let mut backup_path = None;
+    if !fresh {
+        let mut changes = vec![];
+        for migration in &pending {
+            for change in destructive_changes(client, migration.sql).await? {
+                changes.push(format!("{} {}", migration.version, change));
+            }
+        }
+        if !changes.is_empty() {
+            println!(
+                "Pending migrations would remove data: {}",
+                changes.join("; ")
+            );
+        }
+        let backup = backup::create(db_path, BackupReason::PreMigration)
+            .await
+            .map_err(|e| {
+                if changes.is_empty() {
+                    format!("Refusing to migrate the database without a backup: {}", e)
+                } else {
+                    format!(
+                        "Refusing to migrate the database without a backup ({}): {}",
+                        changes.join("; "),
+                        e
+                    )
+                }
+            })?;
+        println!("Database backed up to {}", backup.path);
+        backup_path = Some(backup.path);
+    }
+
+    for migration in pending {
+        println!("Applying migration {}", migration.version);
+        apply(client, migration, backup_path.as_deref())
+            .await
+            .map_err(|e| format!("Migration {} failed: {}", migration.version, e))?;
+    }
+    println!("Database migrations applied successfully");
+    Ok(())
+}
+
+/// Applied and pending migrations and the most recent backup taken for one.
+pub async fn status(client: &PrismaClient) -> Result<MigrationStatus, String> {
+    let applied: Vec<AppliedMigration> = client
+        ._query_raw(Raw::new(
+            &format!(
+                "SELECT version AS \"version\", applied_at AS \"appliedAt\", kind AS \"kind\", \
+                 backup_path AS \"backupPath\" FROM \"{}\" ORDER BY version",
+                TRACKING_TABLE
+            ),
+            vec![],
+        ))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to read applied migrations: {}", e))?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.iter().any(|row| row.version == migration.version))
+        .map(|migration| migration.version.to_string())
+        .collect();
+    let last_backup = applied
+        .iter()
+        .rev()
+        .find_map(|migration| migration.backup_path.clone());
+    Ok(MigrationStatus {
+        applied,
+        pending,
+        last_backup,
+    })
+}
+
+async fn apply(
+    client: &PrismaClient,
+    migration: &Migration,
+    backup_path: Option<&str>,
+) -> Result<(), String> {
+    // Prisma's table redefinitions need foreign keys off, and SQLite ignores the switch inside a
+    // transaction
+    execute(client, "PRAGMA foreign_keys = OFF").await?;
+    execute(client, "BEGIN").await?;
+    let result = apply_statements(client, migration, backup_path).await;
+    let result = match result {
+        Ok(()) => execute(client, "COMMIT").await,
+        Err(e) => {
+            let _ = execute(client, "ROLLBACK").await;
+            Err(e)
+        }
+    };
+    execute(client, "PRAGMA foreign_keys = ON").await?;
+    result
+}
+
+async fn apply_statements(
+    client: &PrismaClient,
+    migration: &Migration,
+    backup_path: Option<&str>,
+) -> Result<(), String> {
+    for statement in statements(migration.sql) {
+        let words = words(&statement);
+        // Foreign key handling belongs to the runner, see `apply`
+        if keyword(&words, 0, "PRAGMA") {
+            continue;
+        }
+        // Databases from before the runner may already have the column
+        if let Some((table, column)) = added_column(&words) {
+            if column_names(client, &table).await?.contains(&column) {
+                continue;
+            }
+        }
+        execute(client, &statement).await?;
+    }
+
+    let violations: Vec<serde_json::Value> = client
+        ._query_raw(Raw::new("PRAGMA foreign_key_check", vec![]))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to check foreign keys: {}", e))?;
+    if !violations.is_empty() {
+        return Err(format!(
+            "{} rows would violate foreign key constraints",
+            violations.len()
+        ));
+    }
+    record(client, migration.version, "MIGRATED", backup_path).await
+}
+
+/// What in `sql` would lose data that is in the database now. Prisma redefines a table by
+/// copying it into a new one and dropping the old one; that only counts when the copy leaves
+/// columns behind.
+async fn destructive_changes(client: &PrismaClient, sql: &str) -> Result<Vec<String>, String> {
+    let statements: Vec<Vec<String>> = statements(sql).iter().map(|s| words(s)).collect();
+    let mut changes = vec![];
+    for words in &statements {
+        if let Some(table) = dropped_table(words) {
+            let columns = column_names(client, &table).await?;
+            let copied = statements
+                .iter()
+                .find_map(|words| copied_columns(words, &table))
+                .unwrap_or_default();
+            let lost: Vec<&String> = columns
+                .iter()
+                .filter(|column| !copied.contains(column))
+                .collect();
+            if lost.len() == columns.len() && !columns.is_empty() {
+                changes.push(format!("drops table {}", table));
+            } else if !lost.is_empty() {
+                changes.push(format!(
+                    "drops columns {} of {}",
+                    lost.iter()
+                        .map(|column| column.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    table
+                ));
+            }
+        } else if keyword(words, 0, "ALTER")
+            && keyword(words, 3, "DROP")
+            && keyword(words, 4, "COLUMN")
+        {
+            let (table, column) = (unquote(&words[2]), unquote(&words[5]));
+            if column_names(client, &table).await?.contains(&column) {
+                changes.push(format!("drops column {} of {}", column, table));
+            }
+        } else if keyword(words, 0, "DELETE") && keyword(words, 1, "FROM") {
+            let table = unquote(&words[2]);
+            if !column_names(client, &table).await?.is_empty() {
+                changes.push(format!("deletes rows from {}", table));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+async fn record(
+    client: &PrismaClient,
+    version: &str,
+    kind: &str,
+    backup_path: Option<&str>,
+) -> Result<(), String> {
+    client
+        ._execute_raw(Raw::new(
+            &format!(
+                "INSERT OR IGNORE INTO \"{}\" (version, kind, backup_path) VALUES ({{}}, {{}}, {{}})",
+                TRACKING_TABLE
+            ),
+            vec![
+                PrismaValue::String(version.to_string()),
+                PrismaValue::String(kind.to_string()),
+                backup_path
+                    .map(|path| PrismaValue::String(path.to_string()))
+                    .unwrap_or(PrismaValue::Null),
+            ],
+        ))
+        .exec()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to record migration {}: {}", version, e))
+}
+
+async fn execute(client: &PrismaClient, sql: &str) -> Result<(), String> {
+    client
+        ._execute_raw(Raw::new(sql, vec![]))
+        .exec()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("{} ({})", e, first_line(sql)))
+}
+
+async fn table_names(client: &PrismaClient) -> Result<Vec<String>, String> {
+    let rows: Vec<NameRow> = client
+        ._query_raw(Raw::new(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            vec![],
+        ))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to list tables: {}", e))?;
+    Ok(rows.into_iter().map(|row| row.name).collect())
+}
+
+// Empty when the table does not exist
+async fn column_names(client: &PrismaClient, table: &str) -> Result<Vec<String>, String> {
+    let rows: Vec<NameRow> = client
+        ._query_raw(Raw::new(
+            "SELECT name FROM pragma_table_info({})",
+            vec![PrismaValue::String(table.to_string())],
+        ))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to read the columns of {}: {}", table, e))?;
+    Ok(rows.into_iter().map(|row| row.name).collect())
+}
+
+async fn applied_versions(client: &PrismaClient) -> Result<Vec<String>, String> {
+    let rows: Vec<VersionRow> = client
+        ._query_raw(Raw::new(
+            &format!("SELECT version FROM \"{}\"", TRACKING_TABLE),
+            vec![],
+        ))
+        .exec()
+        .await
+        .map_err(|e| format!("Failed to read applied migrations: {}", e))?;
+    Ok(rows.into_iter().map(|row| row.version).collect())
+}
+
+// Statements end with `;` at the end of a line, the way Prisma writes migrations
+fn statements(sql: &str) -> Vec<String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    for line in sql.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("--") {
+            continue;
+        }
+        current.push_str(line);
+        current.push('\n');
+        // A trigger's body is made of statements too and only ends at its END
+        let ends = if created_trigger(&words(&current)) {
+            trimmed.eq_ignore_ascii_case("END;")
+        } else {
+            trimmed.ends_with(';')
+        };
+        if ends {
+            statements.push(current.trim().trim_end_matches(';').to_string());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+fn words(statement: &str) -> Vec<String> {
+    statement
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == ',')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+fn keyword(words: &[String], index: usize, keyword: &str) -> bool {
+    words
+        .get(index)
+        .is_some_and(|word| word.eq_ignore_ascii_case(keyword))
+}
+
+fn unquote(identifier: &str) -> String {
+    identifier
+        .trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']')
+        .to_string()
+}
+
+// `ALTER TABLE "t" ADD COLUMN "c" ...`
+fn added_column(words: &[String]) -> Option<(String, String)> {
+    if keyword(words, 0, "ALTER")
+        && keyword(words, 1, "TABLE")
+        && keyword(words, 3, "ADD")
+        && keyword(words, 4, "COLUMN")
+    {
+        Some((unquote(&words[2]), unquote(words.get(5)?)))
+    } else {
+        None
+    }
+}
+
+// `CREATE [TEMP] TRIGGER ...`
+fn created_trigger(words: &[String]) -> bool {
+    let temporary = keyword(words, 1, "TEMP") || keyword(words, 1, "TEMPORARY");
+    keyword(words, 0, "CREATE") && keyword(words, if temporary { 2 } else { 1 }, "TRIGGER")
+}
+
+// `DROP TABLE [IF EXISTS] "t"`
+fn dropped_table(words: &[String]) -> Option<String> {
+    if !keyword(words, 0, "DROP") || !keyword(words, 1, "TABLE") {
+        return None;
+    }
+    let index = if keyword(words, 2, "IF") { 4 } else { 2 };
+    words.get(index).map(|word| unquote(word))
+}
+
+// `INSERT INTO "new_t" ("a", "b") SELECT "a", "b" FROM "t"`: the columns of `t` it keeps
+fn copied_columns(words: &[String], table: &str) -> Option<Vec<String>> {
+    if !keyword(words, 0, "INSERT") || !keyword(words, 1, "INTO") {
+        return None;
+    }
+    let select = words
+        .iter()
+        .position(|word| word.eq_ignore_ascii_case("SELECT"))?;
+    let from = words
+        .iter()
+        .rposition(|word| word.eq_ignore_ascii_case("FROM"))?;
+    if words.get(from + 1).map(|word| unquote(word)).as_deref() != Some(table) {
+        return None;
+    }
+    Some(
+        words[select + 1..from]
+            .iter()
+            .map(|word| unquote(word))
+            .collect(),
+    )
+}
+
+fn first_line(sql: &str) -> &str {
+    sql.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::{params, Connection};
+
+    use super::*;
+
+    // A fresh database migrated the way `run` does it
+    fn database() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        for migration in &MIGRATIONS {
+            for statement in statements(migration.sql) {
+                let words = words(&statement);
+                if keyword(&words, 0, "PRAGMA") {
+                    continue;
+                }
+                if let Some((table, column)) = added_column(&words) {
+                    let exists: bool = db
+                        .query_row(
+                            &format!(
+                                "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?",
+                                table
+                            ),
+                            params![column],
+                            |row| row.get(0),
+                        )
+                        .unwrap();
+                    if exists {
+                        continue;
+                    }
+                }
+                db.execute_batch(&statement)
+                    .unwrap_or_else(|e| panic!("{}: {}\n{}", migration.version, e, statement));
+            }
+        }
+        db
+    }
+
+    fn add_script(db: &Connection, name: &str, command: &str) -> i64 {
+        db.execute(
+            "INSERT INTO shell_script (name, command, ordering) VALUES (?, ?, 0)",
+            params![name, command],
+        )
+        .unwrap();
+        db.last_insert_rowid()
+    }
+
+    fn set_command(db: &Connection, script_id: i64, command: &str) {
+        db.execute(
+            "UPDATE shell_script SET command = ? WHERE id = ?",
+            params![command, script_id],
+        )
+        .unwrap();
+    }
+
+    // (name, command) of each revision, oldest first
+    fn revisions(db: &Connection, script_id: i64) -> Vec<(String, String)> {
+        let mut query = db
+            .prepare(
+                "SELECT name, command FROM script_revision WHERE shell_script_id = ? ORDER BY id",
+            )
+            .unwrap();
+        query
+            .query_map(params![script_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect()
+    }
+
+    fn revision(name: &str, command: &str) -> (String, String) {
+        (name.to_string(), command.to_string())
+    }
+
+    #[test]
+    fn keeps_a_trigger_body_in_one_statement() {
+        let sql = "CREATE TABLE \"t\" (\"a\" TEXT);\n\
+                   CREATE TRIGGER \"r\" AFTER UPDATE ON \"t\"\n\
+                   BEGIN\n\
+                   -- note\n\
+                   DELETE FROM \"t\";\n\
+                   INSERT INTO \"t\" VALUES (1);\n\
+                   END;\n\
+                   DROP TABLE \"t\";";
+        let statements = statements(sql);
+        assert_eq!(statements.len(), 3);
+        assert!(statements[1].starts_with("CREATE TRIGGER"));
+        assert!(statements[1].ends_with("END"));
+        assert!(statements[1].contains("INSERT INTO"));
+        assert!(!statements[1].contains("-- note"));
+    }
+
+    #[test]
+    fn records_the_state_before_and_after_the_first_edit() {
+        let db = database();
+        let script_id = add_script(&db, "deploy", "make");
+        assert!(revisions(&db, script_id).is_empty());
+
+        set_command(&db, script_id, "make deploy");
+        db.execute(
+            "UPDATE shell_script SET name = 'ship' WHERE id = ?",
+            params![script_id],
+        )
+        .unwrap();
+
+        assert_eq!(
+            revisions(&db, script_id),
+            vec![
+                revision("deploy", "make"),
+                revision("deploy", "make deploy"),
+                revision("ship", "make deploy"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_updates_that_keep_the_name_and_command() {
+        let db = database();
+        let script_id = add_script(&db, "deploy", "make");
+        // Full-row updates like the Spring backend's rewrite unchanged columns too
+        db.execute(
+            "UPDATE shell_script SET name = name, command = command, ordering = 3 WHERE id = ?",
+            params![script_id],
+        )
+        .unwrap();
+        db.execute(
+            "UPDATE shell_script SET locked = false WHERE id = ?",
+            params![script_id],
+        )
+        .unwrap();
+
+        assert!(revisions(&db, script_id).is_empty());
+    }
+
+    #[test]
+    fn restoring_a_revision_keeps_the_state_it_replaces() {
+        let db = database();
+        let script_id = add_script(&db, "deploy", "make");
+        set_command(&db, script_id, "make deploy");
+
+        // What `RevisionRepository::restore` does with the oldest revision
+        db.execute(
+            "UPDATE shell_script SET name = ?, command = ? WHERE id = ?",
+            params!["deploy", "make", script_id],
+        )
+        .unwrap();
+
+        assert_eq!(
+            revisions(&db, script_id),
+            vec![
+                revision("deploy", "make"),
+                revision("deploy", "make deploy"),
+                revision("deploy", "make"),
+            ]
+        );
+    }
+
+    #[test]
+    fn prunes_each_script_to_the_retention() {
+        let db = database();
+        db.execute(
+            "INSERT INTO application_state (dark_mode, revisions_kept) VALUES (false, 2)",
+            [],
+        )
+        .unwrap();
+        let script_id = add_script(&db, "deploy", "v0");
+        let other_id = add_script(&db, "other", "v0");
+        set_command(&db, other_id, "v1");
+        for version in 1..=4 {
+            set_command(&db, script_id, &format!("v{}", version));
+        }
+
+        assert_eq!(
+            revisions(&db, script_id),
+            vec![revision("deploy", "v3"), revision("deploy", "v4")]
+        );
+        assert_eq!(
+            revisions(&db, other_id),
+            vec![revision("other", "v0"), revision("other", "v1")]
+        );
+    }
+
+    #[test]
+    fn keeps_every_revision_without_a_retention() {
+        let db = database();
+        db.execute(
+            "INSERT INTO application_state (dark_mode, revisions_kept) VALUES (false, NULL)",
+            [],
+        )
+        .unwrap();
+        let script_id = add_script(&db, "deploy", "v0");
+        for version in 1..=120 {
+            set_command(&db, script_id, &format!("v{}", version));
+        }
+
+        assert_eq!(revisions(&db, script_id).len(), 121);
+    }
+
+    #[test]
+    fn keeps_the_default_number_of_revisions_before_the_state_is_saved() {
+        let db = database();
+        let script_id = add_script(&db, "deploy", "v0");
+        for version in 1..=120 {
+            set_command(&db, script_id, &format!("v{}", version));
+        }
+
+        let kept = revisions(&db, script_id);
+        assert_eq!(
+            kept.len(),
+            crate::db::repository::revision_repository::DEFAULT_REVISIONS_KEPT as usize
+        );
+        assert_eq!(kept.last(), Some(&revision("deploy", "v120")));
+    }
+}
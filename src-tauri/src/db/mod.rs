@@ -1,2 +1,3 @@
 pub mod get_db;
+pub mod migrations;
 pub mod repository;
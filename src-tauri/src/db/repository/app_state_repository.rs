@@ -1,5 +1,5 @@
+use crate::prisma::application_state::{self, Data, SetParam};
 use crate::prisma::PrismaClient;
-use crate::PRISMA_CLIENT;
 
 pub struct AppStateRepository {}
 
@@ -9,93 +9,91 @@ impl AppStateRepository {
     }
 
     fn get_client(&self) -> &'static PrismaClient {
-        PRISMA_CLIENT
-            .get()
-            .expect("Prisma client not initialized")
+        crate::db::get_db::get_db()
     }
 
-    pub async fn get_app_state(&self) -> Result<Option<crate::prisma::application_state::Data>, Box<dyn std::error::Error>> {
+    pub async fn get_app_state(&self) -> Result<Option<Data>, Box<dyn std::error::Error>> {
         let client = self.get_client();
-        
-        let state = client
-            .application_state()
-            .find_first(vec![])
-            .exec()
-            .await?;
-        
+
+        let state = client.application_state().find_first(vec![]).exec().await?;
+
         Ok(state)
     }
 
-    pub async fn set_last_opened_folder(&self, folder_id: Option<i32>) -> Result<crate::prisma::application_state::Data, Box<dyn std::error::Error>> {
-        let client = self.get_client();
-        
-        // Check if state exists
-        let existing = self.get_app_state().await?;
-        
-        if let Some(state) = existing {
-            // Update existing state
-            let updated = client
-                .application_state()
-                .update(
-                    crate::prisma::application_state::id::equals(state.id),
-                    vec![
-                        crate::prisma::application_state::last_opened_folder_id::set(folder_id),
-                    ],
-                )
-                .exec()
-                .await?;
-            
-            Ok(updated)
-        } else {
-            // Create new state
-            let created = client
-                .application_state()
-                .create(
-                    vec![
-                        crate::prisma::application_state::last_opened_folder_id::set(folder_id),
-                    ],
-                )
-                .exec()
-                .await?;
-            
-            Ok(created)
-        }
+    pub async fn set_last_opened_folder(
+        &self,
+        folder_id: Option<i32>,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::last_opened_folder_id::set(
+            folder_id,
+        )])
+        .await
+    }
+
+    pub async fn set_dark_mode(&self, enabled: bool) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::dark_mode::set(enabled)])
+            .await
+    }
+
+    pub async fn set_watchers_paused(
+        &self,
+        paused: bool,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::watchers_paused::set(paused)])
+            .await
+    }
+
+    pub async fn set_terminal_preference(
+        &self,
+        emulator: Option<String>,
+        template: Option<String>,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![
+            application_state::terminal_emulator::set(emulator),
+            application_state::terminal_template::set(template),
+        ])
+        .await
     }
 
-    pub async fn set_dark_mode(&self, enabled: bool) -> Result<crate::prisma::application_state::Data, Box<dyn std::error::Error>> {
+    pub async fn set_shell_multiplexer(
+        &self,
+        multiplexer: Option<String>,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::shell_multiplexer::set(multiplexer)])
+            .await
+    }
+
+    pub async fn set_mirror_dir(
+        &self,
+        mirror_dir: Option<String>,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::mirror_dir::set(mirror_dir)])
+            .await
+    }
+
+    pub async fn set_revisions_kept(
+        &self,
+        revisions_kept: Option<i32>,
+    ) -> Result<Data, Box<dyn std::error::Error>> {
+        self.save(vec![application_state::revisions_kept::set(revisions_kept)])
+            .await
+    }
+
+    // Update the single state row, creating it on first use
+    async fn save(&self, params: Vec<SetParam>) -> Result<Data, Box<dyn std::error::Error>> {
         let client = self.get_client();
-        
-        // Check if state exists
-        let existing = self.get_app_state().await?;
-        
-        if let Some(state) = existing {
-            // Update existing state
-            let updated = client
-                .application_state()
-                .update(
-                    crate::prisma::application_state::id::equals(state.id),
-                    vec![
-                        crate::prisma::application_state::dark_mode::set(enabled),
-                    ],
-                )
-                .exec()
-                .await?;
-            
-            Ok(updated)
-        } else {
-            // Create new state with dark mode
-            let created = client
-                .application_state()
-                .create(
-                    vec![
-                        crate::prisma::application_state::dark_mode::set(enabled),
-                    ],
-                )
-                .exec()
-                .await?;
-            
-            Ok(created)
-        }
+
+        let saved = match self.get_app_state().await? {
+            Some(state) => {
+                client
+                    .application_state()
+                    .update(application_state::id::equals(state.id), params)
+                    .exec()
+                    .await?
+            }
+            None => client.application_state().create(params).exec().await?,
+        };
+
+        Ok(saved)
     }
 }
-
@@ -0,0 +1,171 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::historical_script_chain;
+use crate::prisma::script_chain;
+use crate::prisma::script_chain::Data;
+use crate::prisma::script_chain_step;
+use crate::prisma::PrismaClient;
+
+pub struct NewChainStep {
+    pub script_id: i32,
+    pub continue_on_error: bool,
+    pub retry_count: i32,
+    pub delay_ms: i32,
+}
+
+/// One chain run to be written into `historical_script_chain`.
+pub struct NewChainRunRecord {
+    pub chain_id: i32,
+    pub chain_name: String,
+    pub run_id: String,
+    pub status: String,
+    pub executed_at: f64,
+    pub finished_at: f64,
+    pub step_results: String,
+}
+
+pub struct ChainRepository {
+    db: &'static PrismaClient,
+}
+
+impl ChainRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// Every chain with its steps in order.
+    pub async fn get_all_chains(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .script_chain()
+            .find_many(vec![])
+            .order_by(script_chain::ordering::order(Direction::Asc))
+            .with(
+                script_chain::script_chain_step::fetch(vec![])
+                    .order_by(script_chain_step::ordering::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn get_chain(&self, chain_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .script_chain()
+            .find_unique(script_chain::id::equals(chain_id))
+            .with(
+                script_chain::script_chain_step::fetch(vec![])
+                    .order_by(script_chain_step::ordering::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    /// Create a chain, or rename an existing one, and replace its steps.
+    pub async fn save_chain(
+        &self,
+        chain_id: Option<i32>,
+        name: String,
+        steps: &[NewChainStep],
+    ) -> prisma_client_rust::Result<i32> {
+        let chain_id = match chain_id {
+            Some(chain_id) => {
+                self.db
+                    .script_chain()
+                    .update(
+                        script_chain::id::equals(chain_id),
+                        vec![script_chain::name::set(name)],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+            None => {
+                let count = self.db.script_chain().count(vec![]).exec().await?;
+                self.db
+                    .script_chain()
+                    .create(name, vec![script_chain::ordering::set(count as i32)])
+                    .exec()
+                    .await?
+                    .id
+            }
+        };
+
+        self.db
+            .script_chain_step()
+            .delete_many(vec![script_chain_step::script_chain_id::equals(chain_id)])
+            .exec()
+            .await?;
+        for (index, step) in steps.iter().enumerate() {
+            self.db
+                .script_chain_step()
+                .create(
+                    index as i32,
+                    script_chain::id::equals(chain_id),
+                    crate::prisma::shell_script::id::equals(step.script_id),
+                    vec![
+                        script_chain_step::continue_on_error::set(step.continue_on_error),
+                        script_chain_step::retry_count::set(step.retry_count),
+                        script_chain_step::delay_ms::set(step.delay_ms),
+                    ],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(chain_id)
+    }
+
+    pub async fn delete_chain(&self, chain_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .script_chain()
+            .delete_many(vec![script_chain::id::equals(chain_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn record_run(
+        &self,
+        record: NewChainRunRecord,
+    ) -> prisma_client_rust::Result<historical_script_chain::Data> {
+        self.db
+            .historical_script_chain()
+            .create(
+                record.chain_id,
+                record.chain_name,
+                record.run_id,
+                record.status,
+                record.executed_at,
+                record.finished_at,
+                record.step_results,
+                vec![],
+            )
+            .exec()
+            .await
+    }
+
+    /// Runs of one chain, newest first, and how many there are in total. `page` is zero-based.
+    pub async fn get_runs(
+        &self,
+        chain_id: i32,
+        page: i64,
+        page_size: i64,
+    ) -> prisma_client_rust::Result<(Vec<historical_script_chain::Data>, i64)> {
+        let filter = vec![historical_script_chain::script_chain_id::equals(chain_id)];
+        let total = self
+            .db
+            .historical_script_chain()
+            .count(filter.clone())
+            .exec()
+            .await?;
+        let runs = self
+            .db
+            .historical_script_chain()
+            .find_many(filter)
+            .order_by(historical_script_chain::executed_at::order(Direction::Desc))
+            .skip(page * page_size)
+            .take(page_size)
+            .exec()
+            .await?;
+        Ok((runs, total))
+    }
+}
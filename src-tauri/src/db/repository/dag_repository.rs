@@ -0,0 +1,132 @@
+use prisma_client_rust::Direction;
+use std::collections::HashMap;
+
+use crate::prisma::script_dag;
+use crate::prisma::script_dag::Data;
+use crate::prisma::script_dag_node;
+use crate::prisma::PrismaClient;
+
+pub struct DagRepository {
+    db: &'static PrismaClient,
+}
+
+impl DagRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// Every graph with its nodes and their outgoing edges.
+    pub async fn get_all_dags(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .script_dag()
+            .find_many(vec![])
+            .order_by(script_dag::ordering::order(Direction::Asc))
+            .with(
+                script_dag::script_dag_node::fetch(vec![])
+                    .with(script_dag_node::outgoing_edges::fetch(vec![])),
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn get_dag(&self, dag_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .script_dag()
+            .find_unique(script_dag::id::equals(dag_id))
+            .with(
+                script_dag::script_dag_node::fetch(vec![])
+                    .with(script_dag_node::outgoing_edges::fetch(vec![])),
+            )
+            .exec()
+            .await
+    }
+
+    /// Create a graph, or update an existing one, and replace its nodes and edges. Edges are
+    /// given as `(from, to)` script ids, each of which must be one of `script_ids`.
+    pub async fn save_dag(
+        &self,
+        dag_id: Option<i32>,
+        name: String,
+        max_parallel: i32,
+        script_ids: &[i32],
+        edges: &[(i32, i32)],
+    ) -> prisma_client_rust::Result<i32> {
+        let dag_id = match dag_id {
+            Some(dag_id) => {
+                self.db
+                    .script_dag()
+                    .update(
+                        script_dag::id::equals(dag_id),
+                        vec![
+                            script_dag::name::set(name),
+                            script_dag::max_parallel::set(max_parallel),
+                        ],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+            None => {
+                let count = self.db.script_dag().count(vec![]).exec().await?;
+                self.db
+                    .script_dag()
+                    .create(
+                        name,
+                        vec![
+                            script_dag::ordering::set(count as i32),
+                            script_dag::max_parallel::set(max_parallel),
+                        ],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+        };
+
+        // Edges go with their nodes through the cascade
+        self.db
+            .script_dag_node()
+            .delete_many(vec![script_dag_node::script_dag_id::equals(dag_id)])
+            .exec()
+            .await?;
+        let mut node_ids = HashMap::new();
+        for script_id in script_ids {
+            let node = self
+                .db
+                .script_dag_node()
+                .create(
+                    script_dag::id::equals(dag_id),
+                    crate::prisma::shell_script::id::equals(*script_id),
+                    vec![],
+                )
+                .exec()
+                .await?;
+            node_ids.insert(*script_id, node.id);
+        }
+        for (from, to) in edges {
+            let (Some(from_node), Some(to_node)) = (node_ids.get(from), node_ids.get(to)) else {
+                continue;
+            };
+            self.db
+                .script_dag_edge()
+                .create(
+                    script_dag_node::id::equals(*from_node),
+                    script_dag_node::id::equals(*to_node),
+                    vec![],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(dag_id)
+    }
+
+    pub async fn delete_dag(&self, dag_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .script_dag()
+            .delete_many(vec![script_dag::id::equals(dag_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
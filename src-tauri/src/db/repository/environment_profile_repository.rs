@@ -0,0 +1,249 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::environment_profile;
+use crate::prisma::environment_profile::Data;
+use crate::prisma::environment_profile_var;
+use crate::prisma::rel_workspace_envprofile;
+use crate::prisma::workspace_env_var;
+use crate::prisma::PrismaClient;
+
+pub struct EnvironmentProfileRepository {
+    db: &'static PrismaClient,
+}
+
+impl EnvironmentProfileRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// Every profile with its variables loaded.
+    pub async fn get_all_profiles(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .environment_profile()
+            .find_many(vec![])
+            .order_by(environment_profile::ordering::order(Direction::Asc))
+            .with(
+                environment_profile::environment_profile_var::fetch(vec![])
+                    .order_by(environment_profile_var::ordering::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn get_profile(&self, profile_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .environment_profile()
+            .find_unique(environment_profile::id::equals(profile_id))
+            .with(
+                environment_profile::environment_profile_var::fetch(vec![])
+                    .order_by(environment_profile_var::ordering::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    /// Create a profile, or rename an existing one, and replace its variables.
+    pub async fn save_profile(
+        &self,
+        profile_id: Option<i32>,
+        name: String,
+        vars: &[(String, String)],
+    ) -> prisma_client_rust::Result<i32> {
+        let profile_id = match profile_id {
+            Some(profile_id) => {
+                self.db
+                    .environment_profile()
+                    .update(
+                        environment_profile::id::equals(profile_id),
+                        vec![environment_profile::name::set(name)],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+            None => {
+                let count = self.db.environment_profile().count(vec![]).exec().await?;
+                self.db
+                    .environment_profile()
+                    .create(name, vec![environment_profile::ordering::set(count as i32)])
+                    .exec()
+                    .await?
+                    .id
+            }
+        };
+
+        self.db
+            .environment_profile_var()
+            .delete_many(vec![
+                environment_profile_var::environment_profile_id::equals(profile_id),
+            ])
+            .exec()
+            .await?;
+        for (index, (name, value)) in vars.iter().enumerate() {
+            self.db
+                .environment_profile_var()
+                .create(
+                    name.clone(),
+                    value.clone(),
+                    environment_profile::id::equals(profile_id),
+                    vec![environment_profile_var::ordering::set(index as i32)],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(profile_id)
+    }
+
+    pub async fn delete_profile(&self, profile_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .environment_profile()
+            .delete_many(vec![environment_profile::id::equals(profile_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    /// Profiles attached to a workspace, including which one is active.
+    pub async fn get_workspace_profiles(
+        &self,
+        workspace_id: i32,
+    ) -> prisma_client_rust::Result<Vec<rel_workspace_envprofile::Data>> {
+        self.db
+            .rel_workspace_envprofile()
+            .find_many(vec![rel_workspace_envprofile::workspace_id::equals(
+                workspace_id,
+            )])
+            .exec()
+            .await
+    }
+
+    pub async fn attach_profile(
+        &self,
+        workspace_id: i32,
+        profile_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_workspace_envprofile()
+            .upsert(
+                rel_workspace_envprofile::workspace_id_environment_profile_id(
+                    workspace_id,
+                    profile_id,
+                ),
+                rel_workspace_envprofile::create(
+                    crate::prisma::workspace::id::equals(workspace_id),
+                    environment_profile::id::equals(profile_id),
+                    vec![],
+                ),
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn detach_profile(
+        &self,
+        workspace_id: i32,
+        profile_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_workspace_envprofile()
+            .delete_many(vec![
+                rel_workspace_envprofile::workspace_id::equals(workspace_id),
+                rel_workspace_envprofile::environment_profile_id::equals(profile_id),
+            ])
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    /// Make `profile_id` the only active profile of the workspace, or deactivate all of them
+    /// when it is `None`. The profile is attached first if needed.
+    pub async fn set_active_profile(
+        &self,
+        workspace_id: i32,
+        profile_id: Option<i32>,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_workspace_envprofile()
+            .update_many(
+                vec![rel_workspace_envprofile::workspace_id::equals(workspace_id)],
+                vec![rel_workspace_envprofile::is_active::set(false)],
+            )
+            .exec()
+            .await?;
+
+        if let Some(profile_id) = profile_id {
+            self.attach_profile(workspace_id, profile_id).await?;
+            self.db
+                .rel_workspace_envprofile()
+                .update(
+                    rel_workspace_envprofile::workspace_id_environment_profile_id(
+                        workspace_id,
+                        profile_id,
+                    ),
+                    vec![rel_workspace_envprofile::is_active::set(true)],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_active_profile(
+        &self,
+        workspace_id: i32,
+    ) -> prisma_client_rust::Result<Option<Data>> {
+        let active = self
+            .db
+            .rel_workspace_envprofile()
+            .find_first(vec![
+                rel_workspace_envprofile::workspace_id::equals(workspace_id),
+                rel_workspace_envprofile::is_active::equals(true),
+            ])
+            .exec()
+            .await?;
+        match active {
+            Some(active) => self.get_profile(active.environment_profile_id).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_workspace_env_vars(
+        &self,
+        workspace_id: i32,
+    ) -> prisma_client_rust::Result<Vec<workspace_env_var::Data>> {
+        self.db
+            .workspace_env_var()
+            .find_many(vec![workspace_env_var::workspace_id::equals(workspace_id)])
+            .order_by(workspace_env_var::ordering::order(Direction::Asc))
+            .exec()
+            .await
+    }
+
+    pub async fn set_workspace_env_vars(
+        &self,
+        workspace_id: i32,
+        vars: &[(String, String)],
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .workspace_env_var()
+            .delete_many(vec![workspace_env_var::workspace_id::equals(workspace_id)])
+            .exec()
+            .await?;
+        for (index, (name, value)) in vars.iter().enumerate() {
+            self.db
+                .workspace_env_var()
+                .create(
+                    name.clone(),
+                    value.clone(),
+                    crate::prisma::workspace::id::equals(workspace_id),
+                    vec![workspace_env_var::ordering::set(index as i32)],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use crate::executor::limits::ExecutionLimits;
+use crate::prisma::shell_script_execution_limit;
+use crate::prisma::shell_script_execution_limit::Data;
+use crate::prisma::PrismaClient;
+
+pub struct ExecutionLimitRepository {
+    db: &'static PrismaClient,
+}
+
+impl ExecutionLimitRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_limits(&self, script_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .shell_script_execution_limit()
+            .find_unique(shell_script_execution_limit::shell_script_id::equals(
+                script_id,
+            ))
+            .exec()
+            .await
+    }
+
+    pub async fn upsert_limits(
+        &self,
+        script_id: i32,
+        limits: &ExecutionLimits,
+    ) -> prisma_client_rust::Result<Data> {
+        let to_column = |value: Option<u64>| value.map(|v| v.min(i32::MAX as u64) as i32);
+        let params = vec![
+            shell_script_execution_limit::timeout_seconds::set(to_column(limits.timeout_seconds)),
+            shell_script_execution_limit::max_output_bytes::set(to_column(limits.max_output_bytes)),
+            shell_script_execution_limit::cpu_seconds::set(to_column(limits.cpu_seconds)),
+            shell_script_execution_limit::address_space_mb::set(to_column(limits.address_space_mb)),
+            shell_script_execution_limit::open_files::set(to_column(limits.open_files)),
+        ];
+
+        self.db
+            .shell_script_execution_limit()
+            .upsert(
+                shell_script_execution_limit::shell_script_id::equals(script_id),
+                shell_script_execution_limit::create(
+                    crate::prisma::shell_script::id::equals(script_id),
+                    params.clone(),
+                ),
+                params,
+            )
+            .exec()
+            .await
+    }
+}
+
+impl From<&Data> for ExecutionLimits {
+    fn from(data: &Data) -> Self {
+        let from_column = |value: Option<i32>| value.filter(|v| *v > 0).map(|v| v as u64);
+        Self {
+            timeout_seconds: from_column(data.timeout_seconds),
+            max_output_bytes: from_column(data.max_output_bytes),
+            cpu_seconds: from_column(data.cpu_seconds),
+            address_space_mb: from_column(data.address_space_mb),
+            open_files: from_column(data.open_files),
+        }
+    }
+}
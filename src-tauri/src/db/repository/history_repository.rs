@@ -0,0 +1,187 @@
+use prisma_client_rust::Direction;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::prisma::historical_shell_script;
+use crate::prisma::historical_shell_script::Data;
+use crate::prisma::shell_script;
+use crate::prisma::PrismaClient;
+
+/// One execution to be written into `historical_shell_script`.
+pub struct NewHistoryRecord {
+    pub script_id: i32,
+    pub run_id: String,
+    pub execution_mode: String,
+    pub command: String,
+    pub working_dir: Option<String>,
+    pub executed_at: f64,
+    pub finished_at: Option<f64>,
+    pub exit_code: Option<i32>,
+    pub env_profile_id: Option<i32>,
+    pub env_profile_name: Option<String>,
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub output_truncated: bool,
+    pub remote_host: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    pub id: i32,
+    pub script_id: i32,
+    /// `None` when the script has been deleted since it ran.
+    pub script_name: Option<String>,
+    pub run_id: Option<String>,
+    pub execution_mode: Option<String>,
+    pub command: Option<String>,
+    pub working_dir: Option<String>,
+    pub executed_at: f64,
+    pub finished_at: Option<f64>,
+    pub exit_code: Option<i32>,
+    /// Name of the environment profile that was active for the run.
+    pub env_profile_name: Option<String>,
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub output_truncated: bool,
+    /// `user@host:port` when the run was executed over SSH.
+    pub remote_host: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub items: Vec<HistoryRecord>,
+    pub page: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+pub struct HistoryRepository {
+    db: &'static PrismaClient,
+}
+
+impl HistoryRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn record_execution(
+        &self,
+        record: NewHistoryRecord,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .historical_shell_script()
+            .create(
+                record.script_id,
+                record.executed_at,
+                vec![
+                    historical_shell_script::run_id::set(Some(record.run_id)),
+                    historical_shell_script::execution_mode::set(Some(record.execution_mode)),
+                    historical_shell_script::command::set(Some(record.command)),
+                    historical_shell_script::working_dir::set(record.working_dir),
+                    historical_shell_script::finished_at::set(record.finished_at),
+                    historical_shell_script::exit_code::set(record.exit_code),
+                    historical_shell_script::env_profile_id::set(record.env_profile_id),
+                    historical_shell_script::env_profile_name::set(record.env_profile_name),
+                    historical_shell_script::stdout::set(record.stdout),
+                    historical_shell_script::stderr::set(record.stderr),
+                    historical_shell_script::output_truncated::set(record.output_truncated),
+                    historical_shell_script::remote_host::set(record.remote_host),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    /// Executions of one script, newest first. `page` is zero-based.
+    pub async fn get_script_history(
+        &self,
+        script_id: i32,
+        page: i64,
+        page_size: i64,
+    ) -> prisma_client_rust::Result<HistoryPage> {
+        self.get_page(
+            vec![historical_shell_script::shell_script_id::equals(script_id)],
+            page,
+            page_size,
+        )
+        .await
+    }
+
+    /// Executions of every script, newest first. `page` is zero-based.
+    pub async fn get_timeline(
+        &self,
+        page: i64,
+        page_size: i64,
+    ) -> prisma_client_rust::Result<HistoryPage> {
+        self.get_page(vec![], page, page_size).await
+    }
+
+    async fn get_page(
+        &self,
+        filter: Vec<historical_shell_script::WhereParam>,
+        page: i64,
+        page_size: i64,
+    ) -> prisma_client_rust::Result<HistoryPage> {
+        let total = self
+            .db
+            .historical_shell_script()
+            .count(filter.clone())
+            .exec()
+            .await?;
+
+        let rows = self
+            .db
+            .historical_shell_script()
+            .find_many(filter)
+            .order_by(historical_shell_script::executed_at::order(Direction::Desc))
+            .skip(page * page_size)
+            .take(page_size)
+            .exec()
+            .await?;
+
+        // historical_shell_script has no relation to shell_script, so look the names up in one go
+        let mut script_ids: Vec<i32> = rows.iter().map(|row| row.shell_script_id).collect();
+        script_ids.sort_unstable();
+        script_ids.dedup();
+        let script_names: HashMap<i32, String> = self
+            .db
+            .shell_script()
+            .find_many(vec![shell_script::id::in_vec(script_ids)])
+            .exec()
+            .await?
+            .into_iter()
+            .map(|script| (script.id, script.name))
+            .collect();
+
+        let items = rows
+            .into_iter()
+            .map(|row| HistoryRecord {
+                id: row.id,
+                script_id: row.shell_script_id,
+                script_name: script_names.get(&row.shell_script_id).cloned(),
+                run_id: row.run_id,
+                execution_mode: row.execution_mode,
+                command: row.command,
+                working_dir: row.working_dir,
+                executed_at: row.executed_at,
+                finished_at: row.finished_at,
+                exit_code: row.exit_code,
+                env_profile_name: row.env_profile_name,
+                stdout: row.stdout,
+                stderr: row.stderr,
+                output_truncated: row.output_truncated,
+                remote_host: row.remote_host,
+            })
+            .collect();
+
+        Ok(HistoryPage {
+            items,
+            page,
+            page_size,
+            total,
+        })
+    }
+}
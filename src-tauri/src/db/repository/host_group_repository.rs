@@ -0,0 +1,114 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::ssh_host_group;
+use crate::prisma::ssh_host_group::Data;
+use crate::prisma::ssh_host_group_member;
+use crate::prisma::PrismaClient;
+
+pub struct HostGroupRepository {
+    db: &'static PrismaClient,
+}
+
+impl HostGroupRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// Every group with its members in the order they were added.
+    pub async fn get_all_groups(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .ssh_host_group()
+            .find_many(vec![])
+            .order_by(ssh_host_group::name::order(Direction::Asc))
+            .with(
+                ssh_host_group::members::fetch(vec![])
+                    .order_by(ssh_host_group_member::id::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn get_group(&self, group_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .ssh_host_group()
+            .find_unique(ssh_host_group::id::equals(group_id))
+            .with(
+                ssh_host_group::members::fetch(vec![])
+                    .order_by(ssh_host_group_member::id::order(Direction::Asc)),
+            )
+            .exec()
+            .await
+    }
+
+    /// Create a group, or update an existing one, and replace its members.
+    pub async fn save_group(
+        &self,
+        group_id: Option<i32>,
+        name: String,
+        max_parallel: i32,
+        timeout_seconds: Option<i32>,
+        target_ids: &[i32],
+    ) -> prisma_client_rust::Result<i32> {
+        let group_id = match group_id {
+            Some(group_id) => {
+                self.db
+                    .ssh_host_group()
+                    .update(
+                        ssh_host_group::id::equals(group_id),
+                        vec![
+                            ssh_host_group::name::set(name),
+                            ssh_host_group::max_parallel::set(max_parallel),
+                            ssh_host_group::timeout_seconds::set(timeout_seconds),
+                        ],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+            None => {
+                self.db
+                    .ssh_host_group()
+                    .create(
+                        name,
+                        vec![
+                            ssh_host_group::max_parallel::set(max_parallel),
+                            ssh_host_group::timeout_seconds::set(timeout_seconds),
+                        ],
+                    )
+                    .exec()
+                    .await?
+                    .id
+            }
+        };
+
+        self.db
+            .ssh_host_group_member()
+            .delete_many(vec![ssh_host_group_member::ssh_host_group_id::equals(
+                group_id,
+            )])
+            .exec()
+            .await?;
+        for target_id in target_ids {
+            self.db
+                .ssh_host_group_member()
+                .create(
+                    ssh_host_group::id::equals(group_id),
+                    crate::prisma::ssh_target::id::equals(*target_id),
+                    vec![],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(group_id)
+    }
+
+    pub async fn delete_group(&self, group_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .ssh_host_group()
+            .delete_many(vec![ssh_host_group::id::equals(group_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::prisma::PrismaClient;
+use crate::prisma::{
+    rel_folder_folder, rel_scriptsfolder_shellscript, rel_workspace_folder, scripts_folder,
+    shell_script, workspace,
+};
+
+/// Every row of the tables that make up the script library.
+pub struct Library {
+    pub workspaces: Vec<workspace::Data>,
+    pub folders: Vec<scripts_folder::Data>,
+    pub scripts: Vec<shell_script::Data>,
+    pub workspace_folders: Vec<rel_workspace_folder::Data>,
+    pub folder_folders: Vec<rel_folder_folder::Data>,
+    pub folder_scripts: Vec<rel_scriptsfolder_shellscript::Data>,
+}
+
+/// The parts of a script that travel with it between libraries.
+pub struct ScriptContent {
+    pub name: String,
+    pub command: String,
+    pub is_markdown: bool,
+    pub locked: bool,
+    pub show_shell: bool,
+    pub interpreter: Option<String>,
+}
+
+pub struct LibraryRepository<'a> {
+    db: &'a PrismaClient,
+}
+
+impl LibraryRepository<'static> {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+}
+
+impl<'a> LibraryRepository<'a> {
+    /// A repository on `db`, e.g. the client of a transaction.
+    pub fn with_client(db: &'a PrismaClient) -> Self {
+        Self { db }
+    }
+
+    pub async fn get_library(&self) -> prisma_client_rust::Result<Library> {
+        Ok(Library {
+            workspaces: self.db.workspace().find_many(vec![]).exec().await?,
+            folders: self.db.scripts_folder().find_many(vec![]).exec().await?,
+            scripts: self.db.shell_script().find_many(vec![]).exec().await?,
+            workspace_folders: self
+                .db
+                .rel_workspace_folder()
+                .find_many(vec![])
+                .exec()
+                .await?,
+            folder_folders: self.db.rel_folder_folder().find_many(vec![]).exec().await?,
+            folder_scripts: self
+                .db
+                .rel_scriptsfolder_shellscript()
+                .find_many(vec![])
+                .exec()
+                .await?,
+        })
+    }
+
+    /// Delete every workspace, script and user folder. System folders such as Drafts stay, empty.
+    /// Everything attached to a script (run configs, schedules, chain steps, ...) goes with it.
+    pub async fn clear_library(&self) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_workspace_folder()
+            .delete_many(vec![])
+            .exec()
+            .await?;
+        self.db
+            .rel_folder_folder()
+            .delete_many(vec![])
+            .exec()
+            .await?;
+        self.db
+            .rel_scriptsfolder_shellscript()
+            .delete_many(vec![])
+            .exec()
+            .await?;
+        self.db.shell_script().delete_many(vec![]).exec().await?;
+        self.db
+            .scripts_folder()
+            .delete_many(vec![scripts_folder::system_level::not(
+                "SYSTEM".to_string(),
+            )])
+            .exec()
+            .await?;
+        self.db.workspace().delete_many(vec![]).exec().await?;
+        Ok(())
+    }
+
+    pub async fn create_workspace(
+        &self,
+        name: String,
+        ordering: i32,
+    ) -> prisma_client_rust::Result<i32> {
+        Ok(self
+            .db
+            .workspace()
+            .create(name, ordering, vec![])
+            .exec()
+            .await?
+            .id)
+    }
+
+    pub async fn create_folder(
+        &self,
+        name: String,
+        ordering: i32,
+        system_level: String,
+    ) -> prisma_client_rust::Result<i32> {
+        Ok(self
+            .db
+            .scripts_folder()
+            .create(
+                name,
+                ordering,
+                vec![scripts_folder::system_level::set(system_level)],
+            )
+            .exec()
+            .await?
+            .id)
+    }
+
+    pub async fn create_script(
+        &self,
+        content: ScriptContent,
+        ordering: i32,
+    ) -> prisma_client_rust::Result<i32> {
+        Ok(self
+            .db
+            .shell_script()
+            .create(
+                content.name,
+                content.command,
+                ordering,
+                vec![
+                    shell_script::is_markdown::set(content.is_markdown),
+                    shell_script::locked::set(content.locked),
+                    shell_script::show_shell::set(content.show_shell),
+                    shell_script::interpreter::set(content.interpreter),
+                ],
+            )
+            .exec()
+            .await?
+            .id)
+    }
+
+    /// Overwrite a script with imported content. Its name stays, since that is what matched it.
+    pub async fn update_script_content(
+        &self,
+        script_id: i32,
+        content: ScriptContent,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .shell_script()
+            .update(
+                shell_script::id::equals(script_id),
+                vec![
+                    shell_script::command::set(content.command),
+                    shell_script::is_markdown::set(content.is_markdown),
+                    shell_script::locked::set(content.locked),
+                    shell_script::show_shell::set(content.show_shell),
+                    shell_script::interpreter::set(content.interpreter),
+                ],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn link_workspace_folder(
+        &self,
+        workspace_id: i32,
+        folder_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_workspace_folder()
+            .create(
+                workspace::id::equals(workspace_id),
+                scripts_folder::id::equals(folder_id),
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn link_folder_folder(
+        &self,
+        parent_folder_id: i32,
+        child_folder_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_folder_folder()
+            .create(
+                scripts_folder::id::equals(parent_folder_id),
+                scripts_folder::id::equals(child_folder_id),
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn link_folder_script(
+        &self,
+        folder_id: i32,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_scriptsfolder_shellscript()
+            .create(
+                shell_script::id::equals(script_id),
+                scripts_folder::id::equals(folder_id),
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unlink_folder_script(
+        &self,
+        folder_id: i32,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .rel_scriptsfolder_shellscript()
+            .delete_many(vec![
+                rel_scriptsfolder_shellscript::scripts_folder_id::equals(folder_id),
+                rel_scriptsfolder_shellscript::shell_script_id::equals(script_id),
+            ])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::prisma::library_mirror_entry;
+use crate::prisma::library_mirror_entry::Data;
+use crate::prisma::PrismaClient;
+
+pub struct MirrorRepository {
+    db: &'static PrismaClient,
+}
+
+impl MirrorRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_all(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .library_mirror_entry()
+            .find_many(vec![])
+            .exec()
+            .await
+    }
+
+    /// Record what the mirror directory holds for a script after a sync.
+    pub async fn upsert(
+        &self,
+        script_id: i32,
+        path: &str,
+        synced_hash: &str,
+        synced_at: f64,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .library_mirror_entry()
+            .upsert(
+                library_mirror_entry::shell_script_id::equals(script_id),
+                library_mirror_entry::create(
+                    script_id,
+                    path.to_string(),
+                    synced_hash.to_string(),
+                    synced_at,
+                    vec![],
+                ),
+                vec![
+                    library_mirror_entry::path::set(path.to_string()),
+                    library_mirror_entry::synced_hash::set(synced_hash.to_string()),
+                    library_mirror_entry::synced_at::set(synced_at),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn delete(&self, script_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .library_mirror_entry()
+            .delete_many(vec![library_mirror_entry::shell_script_id::equals(
+                script_id,
+            )])
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    /// Forget every entry, e.g. when the library is mirrored to another directory.
+    pub async fn clear(&self) -> prisma_client_rust::Result<()> {
+        self.db
+            .library_mirror_entry()
+            .delete_many(vec![])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
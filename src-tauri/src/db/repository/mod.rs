@@ -1,3 +1,18 @@
 pub mod app_state_repository;
+pub mod chain_repository;
+pub mod dag_repository;
+pub mod environment_profile_repository;
+pub mod execution_limit_repository;
 pub mod folder_repository;
+pub mod history_repository;
+pub mod host_group_repository;
+pub mod library_repository;
+pub mod mirror_repository;
+pub mod parameter_value_repository;
+pub mod revision_repository;
+pub mod run_config_repository;
+pub mod schedule_repository;
 pub mod script_repository;
+pub mod secret_repository;
+pub mod ssh_target_repository;
+pub mod watch_trigger_repository;
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::prisma::shell_script_parameter_value;
+use crate::prisma::PrismaClient;
+
+pub struct ParameterValueRepository {
+    db: &'static PrismaClient,
+}
+
+impl ParameterValueRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// Last-used placeholder values of a script, keyed by parameter name.
+    pub async fn get_last_values(
+        &self,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<HashMap<String, String>> {
+        let rows = self
+            .db
+            .shell_script_parameter_value()
+            .find_many(vec![shell_script_parameter_value::shell_script_id::equals(
+                script_id,
+            )])
+            .exec()
+            .await?;
+        Ok(rows.into_iter().map(|row| (row.name, row.value)).collect())
+    }
+
+    pub async fn save_last_values(
+        &self,
+        script_id: i32,
+        values: &HashMap<String, String>,
+    ) -> prisma_client_rust::Result<()> {
+        for (name, value) in values {
+            self.db
+                .shell_script_parameter_value()
+                .upsert(
+                    shell_script_parameter_value::shell_script_id_name(script_id, name.clone()),
+                    shell_script_parameter_value::create(
+                        name.clone(),
+                        value.clone(),
+                        crate::prisma::shell_script::id::equals(script_id),
+                        vec![],
+                    ),
+                    vec![shell_script_parameter_value::value::set(value.clone())],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use prisma_client_rust::Direction;
+
+use crate::prisma::script_revision;
+use crate::prisma::script_revision::Data;
+use crate::prisma::{shell_script, PrismaClient};
+
+/// Revisions kept per script until a retention is chosen; matches the schema default and the
+/// trigger that records revisions.
+pub const DEFAULT_REVISIONS_KEPT: i32 = 100;
+
+// Revisions are recorded and pruned by the `shell_script_record_revision` trigger on every change
+// to a script's name or command, whichever backend makes it.
+
+pub struct RevisionRepository {
+    db: &'static PrismaClient,
+}
+
+impl RevisionRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    /// A script's revisions, newest first.
+    pub async fn get_by_script(&self, script_id: i32) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .script_revision()
+            .find_many(vec![script_revision::shell_script_id::equals(script_id)])
+            .order_by(script_revision::id::order(Direction::Desc))
+            .exec()
+            .await
+    }
+
+    pub async fn get(&self, revision_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .script_revision()
+            .find_unique(script_revision::id::equals(revision_id))
+            .exec()
+            .await
+    }
+
+    /// Put a revision's name and command back on its script as a single recorded edit.
+    pub async fn restore(&self, revision: &Data) -> prisma_client_rust::Result<()> {
+        self.db
+            .shell_script()
+            .update_many(
+                vec![shell_script::id::equals(revision.shell_script_id)],
+                vec![
+                    shell_script::name::set(revision.name.clone()),
+                    shell_script::command::set(revision.command.clone()),
+                ],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    /// How many revisions are kept per script; `None` keeps them all.
+    pub async fn get_revisions_kept(&self) -> prisma_client_rust::Result<Option<i32>> {
+        let state = self
+            .db
+            .application_state()
+            .find_first(vec![])
+            .exec()
+            .await?;
+        Ok(match state {
+            Some(state) => state.revisions_kept,
+            None => Some(DEFAULT_REVISIONS_KEPT),
+        })
+    }
+
+    /// Delete all but the newest `keep` revisions of one script, or of every script when
+    /// `script_id` is `None`.
+    pub async fn prune(
+        &self,
+        script_id: Option<i32>,
+        keep: Option<i32>,
+    ) -> prisma_client_rust::Result<()> {
+        let Some(keep) = keep else {
+            return Ok(());
+        };
+        let filter = script_id
+            .map(script_revision::shell_script_id::equals)
+            .into_iter()
+            .collect();
+        let revisions = self
+            .db
+            .script_revision()
+            .find_many(filter)
+            .order_by(script_revision::id::order(Direction::Desc))
+            .exec()
+            .await?;
+
+        let excess = excess(
+            revisions
+                .iter()
+                .map(|revision| (revision.id, revision.shell_script_id)),
+            keep,
+        );
+        if excess.is_empty() {
+            return Ok(());
+        }
+
+        self.db
+            .script_revision()
+            .delete_many(vec![script_revision::id::in_vec(excess)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
+
+// Ids of the revisions beyond the newest `keep` of their script, given (id, script id) pairs
+// newest first
+fn excess(revisions: impl IntoIterator<Item = (i32, i32)>, keep: i32) -> Vec<i32> {
+    let mut seen: HashMap<i32, i32> = HashMap::new();
+    revisions
+        .into_iter()
+        .filter(|(_, script_id)| {
+            let count = seen.entry(*script_id).or_insert(0);
+            *count += 1;
+            *count > keep
+        })
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_newest_revisions_of_each_script() {
+        // Newest first, two scripts interleaved
+        let revisions = [
+            (9, 1),
+            (8, 2),
+            (7, 1),
+            (6, 1),
+            (5, 2),
+            (4, 1),
+            (3, 2),
+            (2, 2),
+        ];
+        assert_eq!(excess(revisions, 2), vec![6, 4, 3, 2]);
+        assert_eq!(excess(revisions, 1), vec![7, 6, 5, 4, 3, 2]);
+    }
+
+    #[test]
+    fn keeps_everything_within_the_retention() {
+        assert!(excess([(3, 1), (2, 1), (1, 2)], 2).is_empty());
+        assert!(excess([], 1).is_empty());
+    }
+}
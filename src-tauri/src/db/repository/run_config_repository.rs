@@ -0,0 +1,88 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::shell_script_env_var;
+use crate::prisma::shell_script_run_config;
+use crate::prisma::PrismaClient;
+
+pub struct RunConfigRepository {
+    db: &'static PrismaClient,
+}
+
+impl RunConfigRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_working_dir(
+        &self,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<Option<String>> {
+        let config = self
+            .db
+            .shell_script_run_config()
+            .find_unique(shell_script_run_config::shell_script_id::equals(script_id))
+            .exec()
+            .await?;
+        Ok(config.and_then(|config| config.working_dir))
+    }
+
+    pub async fn get_env_vars(
+        &self,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<Vec<shell_script_env_var::Data>> {
+        self.db
+            .shell_script_env_var()
+            .find_many(vec![shell_script_env_var::shell_script_id::equals(
+                script_id,
+            )])
+            .order_by(shell_script_env_var::ordering::order(Direction::Asc))
+            .exec()
+            .await
+    }
+
+    /// Replace the working directory and the whole set of env var overrides of a script.
+    pub async fn save(
+        &self,
+        script_id: i32,
+        working_dir: Option<String>,
+        env_vars: &[(String, String)],
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .shell_script_run_config()
+            .upsert(
+                shell_script_run_config::shell_script_id::equals(script_id),
+                shell_script_run_config::create(
+                    crate::prisma::shell_script::id::equals(script_id),
+                    vec![shell_script_run_config::working_dir::set(
+                        working_dir.clone(),
+                    )],
+                ),
+                vec![shell_script_run_config::working_dir::set(working_dir)],
+            )
+            .exec()
+            .await?;
+
+        self.db
+            .shell_script_env_var()
+            .delete_many(vec![shell_script_env_var::shell_script_id::equals(
+                script_id,
+            )])
+            .exec()
+            .await?;
+
+        for (index, (name, value)) in env_vars.iter().enumerate() {
+            self.db
+                .shell_script_env_var()
+                .create(
+                    name.clone(),
+                    value.clone(),
+                    crate::prisma::shell_script::id::equals(script_id),
+                    vec![shell_script_env_var::ordering::set(index as i32)],
+                )
+                .exec()
+                .await?;
+        }
+        Ok(())
+    }
+}
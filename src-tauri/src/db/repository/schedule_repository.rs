@@ -0,0 +1,117 @@
+use crate::prisma::script_schedule;
+use crate::prisma::script_schedule::Data;
+use crate::prisma::PrismaClient;
+
+/// The editable part of a schedule.
+pub struct ScheduleFields {
+    pub cron_expression: String,
+    pub timezone: String,
+    pub misfire_policy: String,
+    pub enabled: bool,
+}
+
+pub struct ScheduleRepository {
+    db: &'static PrismaClient,
+}
+
+impl ScheduleRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_all(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db.script_schedule().find_many(vec![]).exec().await
+    }
+
+    pub async fn get_enabled(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .script_schedule()
+            .find_many(vec![script_schedule::enabled::equals(true)])
+            .exec()
+            .await
+    }
+
+    pub async fn get(&self, schedule_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .script_schedule()
+            .find_unique(script_schedule::id::equals(schedule_id))
+            .exec()
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        script_id: i32,
+        fields: ScheduleFields,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .script_schedule()
+            .create(
+                fields.cron_expression,
+                crate::prisma::shell_script::id::equals(script_id),
+                vec![
+                    script_schedule::timezone::set(fields.timezone),
+                    script_schedule::misfire_policy::set(fields.misfire_policy),
+                    script_schedule::enabled::set(fields.enabled),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    /// Changing the expression or time zone, or enabling the schedule again, starts it afresh:
+    /// fire times of the old definition, or from while it was disabled, are not caught up on.
+    pub async fn update(
+        &self,
+        schedule_id: i32,
+        fields: ScheduleFields,
+        now: f64,
+    ) -> prisma_client_rust::Result<Data> {
+        let current = self.get(schedule_id).await?;
+        let mut updates = vec![];
+        if current.is_some_and(|current| {
+            current.cron_expression != fields.cron_expression
+                || current.timezone != fields.timezone
+                || (!current.enabled && fields.enabled)
+        }) {
+            updates.push(script_schedule::last_fired_at::set(Some(now)));
+        }
+        updates.extend([
+            script_schedule::cron_expression::set(fields.cron_expression),
+            script_schedule::timezone::set(fields.timezone),
+            script_schedule::misfire_policy::set(fields.misfire_policy),
+            script_schedule::enabled::set(fields.enabled),
+        ]);
+        self.db
+            .script_schedule()
+            .update(script_schedule::id::equals(schedule_id), updates)
+            .exec()
+            .await
+    }
+
+    pub async fn set_last_fired_at(
+        &self,
+        schedule_id: i32,
+        fired_at: f64,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .script_schedule()
+            .update(
+                script_schedule::id::equals(schedule_id),
+                vec![script_schedule::last_fired_at::set(Some(fired_at))],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, schedule_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .script_schedule()
+            .delete_many(vec![script_schedule::id::equals(schedule_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
@@ -41,6 +41,65 @@ impl ScriptRepository {
         Ok(())
     }
 
+    pub async fn get_script(&self, script_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .shell_script()
+            .find_unique(crate::prisma::shell_script::id::equals(script_id))
+            .exec()
+            .await
+    }
+
+    /// The workspace a script lives in, found by walking up its folder's parents until a
+    /// folder attached to a workspace is reached.
+    pub async fn get_workspace_id(
+        &self,
+        script_id: i32,
+    ) -> prisma_client_rust::Result<Option<i32>> {
+        let relation = self
+            .db
+            .rel_scriptsfolder_shellscript()
+            .find_first(vec![
+                crate::prisma::rel_scriptsfolder_shellscript::shell_script_id::equals(script_id),
+            ])
+            .exec()
+            .await?;
+        let Some(relation) = relation else {
+            return Ok(None);
+        };
+
+        let mut folder_id = relation.scripts_folder_id;
+        let mut visited = vec![];
+        while !visited.contains(&folder_id) {
+            visited.push(folder_id);
+
+            let workspace = self
+                .db
+                .rel_workspace_folder()
+                .find_first(vec![
+                    crate::prisma::rel_workspace_folder::folder_id::equals(folder_id),
+                ])
+                .exec()
+                .await?;
+            if let Some(workspace) = workspace {
+                return Ok(Some(workspace.workspace_id));
+            }
+
+            let parent = self
+                .db
+                .rel_folder_folder()
+                .find_first(vec![
+                    crate::prisma::rel_folder_folder::child_folder_id::equals(folder_id),
+                ])
+                .exec()
+                .await?;
+            match parent {
+                Some(parent) => folder_id = parent.parent_folder_id,
+                None => break,
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn update_script_command(
         &self,
         script_id: i32,
@@ -73,6 +132,38 @@ impl ScriptRepository {
         Ok(())
     }
 
+    pub async fn update_script_interpreter(
+        &self,
+        script_id: i32,
+        interpreter: Option<String>,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .shell_script()
+            .update_many(
+                vec![crate::prisma::shell_script::id::equals(script_id)],
+                vec![crate::prisma::shell_script::interpreter::set(interpreter)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_script_ordering(
+        &self,
+        script_id: i32,
+        ordering: i32,
+    ) -> prisma_client_rust::Result<()> {
+        self.db
+            .shell_script()
+            .update_many(
+                vec![crate::prisma::shell_script::id::equals(script_id)],
+                vec![crate::prisma::shell_script::ordering::set(ordering)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
     pub async fn get_scripts_by_folder(
         &self,
         folder_id: i32,
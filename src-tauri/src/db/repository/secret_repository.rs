@@ -0,0 +1,55 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::secret_reference;
+use crate::prisma::secret_reference::Data;
+use crate::prisma::PrismaClient;
+
+pub struct SecretRepository {
+    db: &'static PrismaClient,
+}
+
+impl SecretRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_all(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .secret_reference()
+            .find_many(vec![])
+            .order_by(secret_reference::name::order(Direction::Asc))
+            .exec()
+            .await
+    }
+
+    /// Remember that `name` exists and which backend holds its value.
+    pub async fn upsert(
+        &self,
+        name: &str,
+        backend: &str,
+        updated_at: f64,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .secret_reference()
+            .upsert(
+                secret_reference::name::equals(name.to_string()),
+                secret_reference::create(name.to_string(), backend.to_string(), updated_at, vec![]),
+                vec![
+                    secret_reference::backend::set(backend.to_string()),
+                    secret_reference::updated_at::set(updated_at),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn delete(&self, name: &str) -> prisma_client_rust::Result<()> {
+        self.db
+            .secret_reference()
+            .delete_many(vec![secret_reference::name::equals(name.to_string())])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use prisma_client_rust::Direction;
+
+use crate::prisma::ssh_target;
+use crate::prisma::ssh_target::Data;
+use crate::prisma::PrismaClient;
+
+/// The editable part of a target, already validated.
+pub struct SshTargetFields {
+    pub name: String,
+    pub host: String,
+    pub port: i32,
+    pub username: String,
+    pub auth_method: String,
+    pub key_path: Option<String>,
+    pub known_hosts_path: Option<String>,
+    pub remote_dir: Option<String>,
+}
+
+pub struct SshTargetRepository {
+    db: &'static PrismaClient,
+}
+
+impl SshTargetRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_all(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .ssh_target()
+            .find_many(vec![])
+            .order_by(ssh_target::name::order(Direction::Asc))
+            .exec()
+            .await
+    }
+
+    pub async fn get(&self, target_id: i32) -> prisma_client_rust::Result<Option<Data>> {
+        self.db
+            .ssh_target()
+            .find_unique(ssh_target::id::equals(target_id))
+            .exec()
+            .await
+    }
+
+    pub async fn create(&self, fields: SshTargetFields) -> prisma_client_rust::Result<Data> {
+        self.db
+            .ssh_target()
+            .create(
+                fields.name,
+                fields.host,
+                fields.username,
+                vec![
+                    ssh_target::port::set(fields.port),
+                    ssh_target::auth_method::set(fields.auth_method),
+                    ssh_target::key_path::set(fields.key_path),
+                    ssh_target::known_hosts_path::set(fields.known_hosts_path),
+                    ssh_target::remote_dir::set(fields.remote_dir),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        target_id: i32,
+        fields: SshTargetFields,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .ssh_target()
+            .update(
+                ssh_target::id::equals(target_id),
+                vec![
+                    ssh_target::name::set(fields.name),
+                    ssh_target::host::set(fields.host),
+                    ssh_target::port::set(fields.port),
+                    ssh_target::username::set(fields.username),
+                    ssh_target::auth_method::set(fields.auth_method),
+                    ssh_target::key_path::set(fields.key_path),
+                    ssh_target::known_hosts_path::set(fields.known_hosts_path),
+                    ssh_target::remote_dir::set(fields.remote_dir),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn delete(&self, target_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .ssh_target()
+            .delete_many(vec![ssh_target::id::equals(target_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
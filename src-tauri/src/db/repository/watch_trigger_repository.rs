@@ -0,0 +1,79 @@
+use crate::prisma::script_watch_trigger;
+use crate::prisma::script_watch_trigger::Data;
+use crate::prisma::PrismaClient;
+
+/// The editable part of a trigger. `patterns` is the JSON array stored as is.
+pub struct WatchTriggerFields {
+    pub base_dir: String,
+    pub patterns: String,
+    pub debounce_ms: i32,
+    pub enabled: bool,
+}
+
+pub struct WatchTriggerRepository {
+    db: &'static PrismaClient,
+}
+
+impl WatchTriggerRepository {
+    pub fn new() -> Self {
+        let db = crate::db::get_db::get_db();
+        Self { db }
+    }
+
+    pub async fn get_all(&self) -> prisma_client_rust::Result<Vec<Data>> {
+        self.db
+            .script_watch_trigger()
+            .find_many(vec![])
+            .exec()
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        script_id: i32,
+        fields: WatchTriggerFields,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .script_watch_trigger()
+            .create(
+                fields.base_dir,
+                fields.patterns,
+                crate::prisma::shell_script::id::equals(script_id),
+                vec![
+                    script_watch_trigger::debounce_ms::set(fields.debounce_ms),
+                    script_watch_trigger::enabled::set(fields.enabled),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        trigger_id: i32,
+        fields: WatchTriggerFields,
+    ) -> prisma_client_rust::Result<Data> {
+        self.db
+            .script_watch_trigger()
+            .update(
+                script_watch_trigger::id::equals(trigger_id),
+                vec![
+                    script_watch_trigger::base_dir::set(fields.base_dir),
+                    script_watch_trigger::patterns::set(fields.patterns),
+                    script_watch_trigger::debounce_ms::set(fields.debounce_ms),
+                    script_watch_trigger::enabled::set(fields.enabled),
+                ],
+            )
+            .exec()
+            .await
+    }
+
+    pub async fn delete(&self, trigger_id: i32) -> prisma_client_rust::Result<()> {
+        self.db
+            .script_watch_trigger()
+            .delete_many(vec![script_watch_trigger::id::equals(trigger_id)])
+            .exec()
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::db::repository::environment_profile_repository::EnvironmentProfileRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::executor::run_config::{validate_env_vars, EnvVar};
+
+/// Broadcast to every window when the active profile of a workspace changes.
+pub const ENVIRONMENT_PROFILE_CHANGED_EVENT: &str = "environment-profile-changed";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentProfile {
+    /// `None` when creating a new profile.
+    pub id: Option<i32>,
+    pub name: String,
+    pub vars: Vec<EnvVar>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEnvironment {
+    pub workspace_id: i32,
+    pub profile_ids: Vec<i32>,
+    pub active_profile_id: Option<i32>,
+    pub vars: Vec<EnvVar>,
+}
+
+/// The profile a run was started under, recorded in its history entry.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveProfile {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentProfileChangedEvent {
+    pub workspace_id: i32,
+    pub profile: Option<ActiveProfile>,
+}
+
+/// The variables a script inherits from its workspace: the active profile's, overridden by the
+/// workspace's own.
+pub struct InheritedEnvironment {
+    pub profile: Option<ActiveProfile>,
+    pub vars: Vec<EnvVar>,
+}
+
+pub async fn list_profiles() -> Result<Vec<EnvironmentProfile>, String> {
+    let profiles = EnvironmentProfileRepository::new()
+        .get_all_profiles()
+        .await
+        .map_err(|e| format!("Failed to load environment profiles: {}", e))?;
+    Ok(profiles.into_iter().map(to_profile).collect())
+}
+
+pub async fn save_profile(profile: EnvironmentProfile) -> Result<EnvironmentProfile, String> {
+    let name = profile.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Environment profile name cannot be empty".to_string());
+    }
+    validate_env_vars(&profile.vars)?;
+
+    let repository = EnvironmentProfileRepository::new();
+    let profile_id = repository
+        .save_profile(profile.id, name, &to_pairs(&profile.vars))
+        .await
+        .map_err(|e| format!("Failed to save environment profile: {}", e))?;
+    repository
+        .get_profile(profile_id)
+        .await
+        .map_err(|e| format!("Failed to load environment profile: {}", e))?
+        .map(to_profile)
+        .ok_or_else(|| format!("Environment profile {} not found", profile_id))
+}
+
+pub async fn delete_profile(profile_id: i32) -> Result<(), String> {
+    EnvironmentProfileRepository::new()
+        .delete_profile(profile_id)
+        .await
+        .map_err(|e| format!("Failed to delete environment profile: {}", e))
+}
+
+pub async fn get_workspace_environment(workspace_id: i32) -> Result<WorkspaceEnvironment, String> {
+    let repository = EnvironmentProfileRepository::new();
+    let attached = repository
+        .get_workspace_profiles(workspace_id)
+        .await
+        .map_err(|e| format!("Failed to load workspace profiles: {}", e))?;
+    let vars = repository
+        .get_workspace_env_vars(workspace_id)
+        .await
+        .map_err(|e| format!("Failed to load workspace variables: {}", e))?;
+
+    Ok(WorkspaceEnvironment {
+        workspace_id,
+        profile_ids: attached
+            .iter()
+            .map(|rel| rel.environment_profile_id)
+            .collect(),
+        active_profile_id: attached
+            .iter()
+            .find(|rel| rel.is_active)
+            .map(|rel| rel.environment_profile_id),
+        vars: vars
+            .into_iter()
+            .map(|var| EnvVar {
+                name: var.name,
+                value: var.value,
+            })
+            .collect(),
+    })
+}
+
+pub async fn set_workspace_env_vars(workspace_id: i32, vars: Vec<EnvVar>) -> Result<(), String> {
+    validate_env_vars(&vars)?;
+    EnvironmentProfileRepository::new()
+        .set_workspace_env_vars(workspace_id, &to_pairs(&vars))
+        .await
+        .map_err(|e| format!("Failed to save workspace variables: {}", e))
+}
+
+pub async fn attach_profile(workspace_id: i32, profile_id: i32) -> Result<(), String> {
+    EnvironmentProfileRepository::new()
+        .attach_profile(workspace_id, profile_id)
+        .await
+        .map_err(|e| format!("Failed to attach environment profile: {}", e))
+}
+
+pub async fn detach_profile(workspace_id: i32, profile_id: i32) -> Result<(), String> {
+    EnvironmentProfileRepository::new()
+        .detach_profile(workspace_id, profile_id)
+        .await
+        .map_err(|e| format!("Failed to detach environment profile: {}", e))
+}
+
+/// Make `profile_id` the active profile of the workspace (`None` deactivates profiles) and tell
+/// every window about it.
+pub async fn switch_profile(
+    app: &tauri::AppHandle,
+    workspace_id: i32,
+    profile_id: Option<i32>,
+) -> Result<Option<ActiveProfile>, String> {
+    let repository = EnvironmentProfileRepository::new();
+    repository
+        .set_active_profile(workspace_id, profile_id)
+        .await
+        .map_err(|e| format!("Failed to switch environment profile: {}", e))?;
+    let profile = repository
+        .get_active_profile(workspace_id)
+        .await
+        .map_err(|e| format!("Failed to load environment profile: {}", e))?
+        .map(|profile| ActiveProfile {
+            id: profile.id,
+            name: profile.name,
+        });
+
+    println!(
+        "Workspace {} switched to environment profile {:?}",
+        workspace_id,
+        profile.as_ref().map(|p| &p.name)
+    );
+    let event = EnvironmentProfileChangedEvent {
+        workspace_id,
+        profile: profile.clone(),
+    };
+    if let Err(e) = app.emit(ENVIRONMENT_PROFILE_CHANGED_EVENT, event) {
+        eprintln!(
+            "Failed to emit {}: {}",
+            ENVIRONMENT_PROFILE_CHANGED_EVENT, e
+        );
+    }
+    Ok(profile)
+}
+
+/// What a script inherits from the workspace it lives in. Scripts outside any workspace
+/// inherit nothing.
+pub async fn inherited_environment(script_id: i32) -> Result<InheritedEnvironment, String> {
+    let workspace_id = ScriptRepository::new()
+        .get_workspace_id(script_id)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to find the workspace of script {}: {}",
+                script_id, e
+            )
+        })?;
+    let Some(workspace_id) = workspace_id else {
+        return Ok(InheritedEnvironment {
+            profile: None,
+            vars: vec![],
+        });
+    };
+
+    let repository = EnvironmentProfileRepository::new();
+    let active = repository
+        .get_active_profile(workspace_id)
+        .await
+        .map_err(|e| format!("Failed to load environment profile: {}", e))?;
+    let workspace_vars = repository
+        .get_workspace_env_vars(workspace_id)
+        .await
+        .map_err(|e| format!("Failed to load workspace variables: {}", e))?;
+
+    let mut vars = vec![];
+    let mut profile = None;
+    if let Some(active) = active {
+        profile = Some(ActiveProfile {
+            id: active.id,
+            name: active.name.clone(),
+        });
+        vars = to_profile(active).vars;
+    }
+    let workspace_vars = workspace_vars
+        .into_iter()
+        .map(|var| EnvVar {
+            name: var.name,
+            value: var.value,
+        })
+        .collect();
+    merge_env_vars(&mut vars, workspace_vars);
+
+    Ok(InheritedEnvironment { profile, vars })
+}
+
+/// Add `overrides` to `base`; a variable set in both takes the value from `overrides`.
+pub fn merge_env_vars(base: &mut Vec<EnvVar>, overrides: Vec<EnvVar>) {
+    for var in overrides {
+        base.retain(|existing| existing.name != var.name);
+        base.push(var);
+    }
+}
+
+fn to_profile(data: crate::prisma::environment_profile::Data) -> EnvironmentProfile {
+    EnvironmentProfile {
+        id: Some(data.id),
+        name: data.name,
+        vars: data
+            .environment_profile_var
+            .unwrap_or_default()
+            .into_iter()
+            .map(|var| EnvVar {
+                name: var.name,
+                value: var.value,
+            })
+            .collect(),
+    }
+}
+
+fn to_pairs(vars: &[EnvVar]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|var| (var.name.clone(), var.value.clone()))
+        .collect()
+}
@@ -0,0 +1,147 @@
+use crate::db::repository::history_repository::{HistoryRepository, NewHistoryRecord};
+use crate::environment::ActiveProfile;
+use crate::jobs;
+
+use crate::ssh::SshTarget;
+
+use super::run_config::ResolvedRunConfig;
+use super::ExecutionOutcome;
+
+/// Only the last bytes of each stream are stored, the end of a log being where errors show up.
+const HISTORY_OUTPUT_LIMIT: usize = 32 * 1024;
+
+#[derive(Clone, Copy)]
+pub enum ExecutionMode {
+    Silent,
+    Streaming,
+    Terminal,
+    Pty,
+    /// A step of a script chain.
+    Chain,
+    /// A node of a script dependency graph.
+    Dag,
+    /// Fired by the scheduler.
+    Scheduled,
+    /// Started by a file-watch trigger.
+    Watch,
+    /// One host of a run across a host group.
+    FanOut,
+}
+
+impl ExecutionMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionMode::Silent => "SILENT",
+            ExecutionMode::Streaming => "STREAMING",
+            ExecutionMode::Terminal => "TERMINAL",
+            ExecutionMode::Pty => "PTY",
+            ExecutionMode::Chain => "CHAIN",
+            ExecutionMode::Dag => "DAG",
+            ExecutionMode::Scheduled => "SCHEDULED",
+            ExecutionMode::Watch => "WATCH",
+            ExecutionMode::FanOut => "FAN_OUT",
+        }
+    }
+}
+
+/// What is known about a run when it starts; turned into a `historical_shell_script` row once
+/// the outcome is known.
+pub struct HistoryEntry {
+    script_id: i32,
+    run_id: String,
+    mode: ExecutionMode,
+    command: String,
+    working_dir: Option<String>,
+    profile: Option<ActiveProfile>,
+    started_at: f64,
+    remote_host: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Ad-hoc commands that do not belong to a script are not recorded.
+    pub fn start(
+        script_id: Option<i32>,
+        run_id: &str,
+        mode: ExecutionMode,
+        command: &str,
+        run_config: &ResolvedRunConfig,
+    ) -> Option<Self> {
+        // Terminals open in the home directory unless the script says otherwise
+        let working_dir = match mode {
+            ExecutionMode::Terminal | ExecutionMode::Pty => Some(
+                run_config
+                    .working_dir
+                    .clone()
+                    .unwrap_or_else(super::resolve_home),
+            ),
+            ExecutionMode::Silent
+            | ExecutionMode::Streaming
+            | ExecutionMode::Chain
+            | ExecutionMode::Dag
+            | ExecutionMode::Scheduled
+            | ExecutionMode::Watch
+            | ExecutionMode::FanOut => run_config.effective_working_dir(),
+        };
+        Some(Self {
+            script_id: script_id?,
+            run_id: run_id.to_string(),
+            mode,
+            command: command.to_string(),
+            working_dir,
+            profile: run_config.profile.clone(),
+            started_at: jobs::now_ms(),
+            remote_host: None,
+        })
+    }
+
+    /// Record the run as executed on `target`, in the target's directory rather than a local one.
+    pub fn set_remote(&mut self, target: &SshTarget) {
+        self.remote_host = Some(target.address());
+        self.working_dir = target.remote_dir.clone();
+    }
+
+    /// Write the row. Runs handed to a terminal window have no outcome. Failures are only
+    /// logged: losing a history row must never fail the run itself.
+    pub async fn record(self, outcome: Option<&ExecutionOutcome>) {
+        let (stdout, stdout_cut) = outcome
+            .map(|o| tail(&o.stdout))
+            .map_or((None, false), |(text, cut)| (Some(text), cut));
+        let (stderr, stderr_cut) = outcome
+            .map(|o| tail(&o.stderr))
+            .map_or((None, false), |(text, cut)| (Some(text), cut));
+
+        let record = NewHistoryRecord {
+            script_id: self.script_id,
+            run_id: self.run_id,
+            execution_mode: self.mode.as_str().to_string(),
+            command: self.command,
+            working_dir: self.working_dir,
+            executed_at: self.started_at,
+            finished_at: outcome.map(|o| self.started_at + o.duration.as_millis() as f64),
+            exit_code: outcome.and_then(|o| o.exit_code),
+            env_profile_id: self.profile.as_ref().map(|profile| profile.id),
+            env_profile_name: self.profile.map(|profile| profile.name),
+            stdout,
+            stderr,
+            output_truncated: stdout_cut
+                || stderr_cut
+                || outcome.is_some_and(|o| o.output_truncated),
+            remote_host: self.remote_host,
+        };
+
+        if let Err(e) = HistoryRepository::new().record_execution(record).await {
+            eprintln!("Failed to record script execution history: {}", e);
+        }
+    }
+}
+
+fn tail(text: &str) -> (String, bool) {
+    if text.len() <= HISTORY_OUTPUT_LIMIT {
+        return (text.to_string(), false);
+    }
+    let mut start = text.len() - HISTORY_OUTPUT_LIMIT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    (text[start..].to_string(), true)
+}
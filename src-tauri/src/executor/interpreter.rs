@@ -0,0 +1,475 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::jobs;
+use crate::terminal;
+
+use super::placeholders::shell_quote;
+use super::run_config::ResolvedRunConfig;
+use super::{resolve_home, wrap_with_shell_config};
+
+/// The program a script's body is handed to. Scripts without one use the interpreter named on
+/// their shebang line, or the user's login shell.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpreter {
+    Bash,
+    Zsh,
+    Fish,
+    Sh,
+    Python,
+    Node,
+    Deno,
+}
+
+const ALL: [Interpreter; 7] = [
+    Interpreter::Bash,
+    Interpreter::Zsh,
+    Interpreter::Fish,
+    Interpreter::Sh,
+    Interpreter::Python,
+    Interpreter::Node,
+    Interpreter::Deno,
+];
+
+// Where package managers usually install interpreters. Apps started from a desktop launcher
+// often get a PATH without them.
+const EXTRA_DIRS: [&str; 5] = [
+    "/opt/homebrew/bin",
+    "/usr/local/bin",
+    "~/.local/bin",
+    "~/.deno/bin",
+    "~/.volta/bin",
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterpreterInfo {
+    pub interpreter: Interpreter,
+    /// Where it was found; `None` when it is not installed.
+    pub path: Option<String>,
+}
+
+impl Interpreter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interpreter::Bash => "bash",
+            Interpreter::Zsh => "zsh",
+            Interpreter::Fish => "fish",
+            Interpreter::Sh => "sh",
+            Interpreter::Python => "python",
+            Interpreter::Node => "node",
+            Interpreter::Deno => "deno",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ALL.into_iter()
+            .find(|interpreter| interpreter.as_str() == value)
+    }
+
+    // The executables to look for, preferred first
+    fn binaries(&self) -> &'static [&'static str] {
+        match self {
+            Interpreter::Bash => &["bash"],
+            Interpreter::Zsh => &["zsh"],
+            Interpreter::Fish => &["fish"],
+            Interpreter::Sh => &["sh"],
+            Interpreter::Python => &["python3", "python"],
+            Interpreter::Node => &["node", "nodejs"],
+            Interpreter::Deno => &["deno"],
+        }
+    }
+
+    /// The executable, searched on `PATH` and then in the usual install locations.
+    pub fn locate(&self) -> Result<PathBuf, String> {
+        let path = search_path();
+        self.binaries()
+            .iter()
+            .find_map(|binary| {
+                std::env::split_paths(&path)
+                    .map(|dir| dir.join(binary))
+                    .find(|candidate| terminal::is_executable(candidate))
+            })
+            .ok_or_else(|| {
+                format!(
+                    "{} is not installed: none of {} was found on PATH or in {}",
+                    self.as_str(),
+                    self.binaries().join(", "),
+                    EXTRA_DIRS.join(", ")
+                )
+            })
+    }
+
+    /// Whether bodies are shell code, into which values can be spliced as quoted words.
+    pub fn is_shell(&self) -> bool {
+        matches!(
+            self,
+            Interpreter::Bash | Interpreter::Zsh | Interpreter::Fish | Interpreter::Sh
+        )
+    }
+
+    /// How a body run by this interpreter reads the environment variable `name`: an expansion
+    /// in shells, an expression that evaluates to the value as a string otherwise.
+    pub fn env_reference(&self, name: &str) -> String {
+        match self {
+            Interpreter::Bash | Interpreter::Zsh | Interpreter::Sh => format!("${{{}}}", name),
+            // fish has no `${VAR}`
+            Interpreter::Fish => format!("${}", name),
+            Interpreter::Python => format!("__import__(\"os\").environ[\"{}\"]", name),
+            Interpreter::Node => format!("process.env.{}", name),
+            Interpreter::Deno => format!("Deno.env.get(\"{}\")", name),
+        }
+    }
+
+    /// The line a remote login shell runs for `command`: this interpreter as found on the remote
+    /// `PATH`, replacing the shell.
+    pub fn remote_invocation(&self, command: &str) -> String {
+        format!("exec {}", self.invocation(self.binaries()[0], command))
+    }
+
+    // A POSIX shell line that runs `command` with `program` as this interpreter
+    fn invocation(&self, program: &str, command: &str) -> String {
+        let mut line = shell_quote(program);
+        for arg in self.code_args(command) {
+            line.push(' ');
+            line.push_str(&shell_quote(&arg));
+        }
+        line
+    }
+
+    // Arguments that make the interpreter run `body` passed as text
+    fn code_args(&self, body: &str) -> Vec<String> {
+        match self {
+            Interpreter::Bash | Interpreter::Zsh => vec![
+                "-l".to_string(),
+                "-c".to_string(),
+                wrap_with_shell_config(self.as_str(), body),
+            ],
+            Interpreter::Fish => vec!["-l".to_string(), "-c".to_string(), body.to_string()],
+            Interpreter::Sh | Interpreter::Python => vec!["-c".to_string(), body.to_string()],
+            // Both reject a shebang line in code passed as text
+            Interpreter::Node => vec!["-e".to_string(), without_shebang(body)],
+            Interpreter::Deno => vec!["eval".to_string(), without_shebang(body)],
+        }
+    }
+}
+
+/// Every interpreter and where it is installed.
+pub fn list_interpreters() -> Vec<InterpreterInfo> {
+    ALL.into_iter()
+        .map(|interpreter| InterpreterInfo {
+            interpreter,
+            path: interpreter
+                .locate()
+                .ok()
+                .map(|path| path.to_string_lossy().to_string()),
+        })
+        .collect()
+}
+
+/// The interpreter stored on the script, if any.
+pub async fn configured(script_id: Option<i32>) -> Result<Option<Interpreter>, String> {
+    let Some(script_id) = script_id else {
+        return Ok(None);
+    };
+    let script = ScriptRepository::new()
+        .get_script(script_id)
+        .await
+        .map_err(|e| format!("Failed to load script {}: {}", script_id, e))?
+        .ok_or_else(|| format!("Script {} not found", script_id))?;
+    match script.interpreter.as_deref() {
+        None => Ok(None),
+        Some(value) => Interpreter::parse(value).map(Some).ok_or_else(|| {
+            format!(
+                "Script {} has an unknown interpreter '{}'",
+                script_id, value
+            )
+        }),
+    }
+}
+
+pub async fn set_interpreter(
+    script_id: i32,
+    interpreter: Option<Interpreter>,
+) -> Result<(), String> {
+    ScriptRepository::new()
+        .update_script_interpreter(
+            script_id,
+            interpreter.map(|interpreter| interpreter.as_str().to_string()),
+        )
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to save the interpreter of script {}: {}",
+                script_id, e
+            )
+        })
+}
+
+/// [`Interpreter::env_reference`] for a run with `interpreter`, where `None` is the login shell.
+pub fn env_reference(interpreter: Option<Interpreter>, name: &str) -> String {
+    match interpreter {
+        Some(interpreter) => interpreter.env_reference(name),
+        None if cfg!(target_os = "windows") => format!("%{}%", name),
+        None => format!("${{{}}}", name),
+    }
+}
+
+/// The interpreter a run of `command` uses: the configured one, else the one its shebang line
+/// names. `None` means the user's login shell.
+pub fn resolve(
+    configured: Option<Interpreter>,
+    command: &str,
+) -> Result<Option<Interpreter>, String> {
+    match configured {
+        Some(interpreter) => Ok(Some(interpreter)),
+        None => from_shebang(command),
+    }
+}
+
+/// The interpreter named by a `#!` first line, directly or through `env`.
+pub fn from_shebang(command: &str) -> Result<Option<Interpreter>, String> {
+    let Some(line) = command
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("#!"))
+    else {
+        return Ok(None);
+    };
+    let mut words = line.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(None);
+    };
+    let mut name = file_name(program);
+    if name == "env" {
+        // e.g. `#!/usr/bin/env -S python3 -u`
+        name = words
+            .find(|word| !word.starts_with('-'))
+            .map(file_name)
+            .unwrap_or_default();
+    }
+    by_binary_name(name).map(Some).ok_or_else(|| {
+        format!(
+            "Unsupported interpreter '{}' on the shebang line. Supported: {}",
+            name,
+            ALL.map(|interpreter| interpreter.as_str()).join(", ")
+        )
+    })
+}
+
+/// A process that runs `command` with `interpreter`, set up like
+/// [`super::build_shell_command`] does for the login shell.
+pub fn build_command(
+    interpreter: Interpreter,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) -> Result<tokio::process::Command, String> {
+    let program = interpreter.locate()?;
+
+    #[cfg(debug_assertions)]
+    println!("Using {} for command: {}", program.display(), command);
+
+    let mut cmd = tokio::process::Command::new(&program);
+    cmd.args(interpreter.code_args(command))
+        .env("HOME", resolve_home())
+        .env(
+            "USER",
+            std::env::var("USER").unwrap_or_else(|_| whoami::username()),
+        )
+        .env("PATH", search_path());
+    run_config.apply(&mut cmd);
+    jobs::isolate_process_group(&mut cmd);
+    Ok(cmd)
+}
+
+/// The line a terminal window's bash runs for `command`: the command itself when bash or the
+/// login shell runs it, otherwise the interpreter invoked on it.
+pub fn terminal_command(configured: Option<Interpreter>, command: &str) -> Result<String, String> {
+    match resolve(configured, command)? {
+        Some(Interpreter::Bash) => Ok(command.to_string()),
+        _ => shell_line(configured, command),
+    }
+}
+
+/// The line the login shell runs for `command`: the command itself when no interpreter applies,
+/// otherwise the interpreter invoked on it.
+pub fn shell_line(configured: Option<Interpreter>, command: &str) -> Result<String, String> {
+    match resolve(configured, command)? {
+        None => Ok(command.to_string()),
+        Some(interpreter) => {
+            let program = interpreter.locate()?;
+            Ok(interpreter.invocation(&program.to_string_lossy(), command))
+        }
+    }
+}
+
+fn by_binary_name(name: &str) -> Option<Interpreter> {
+    match name {
+        "bash" => Some(Interpreter::Bash),
+        "zsh" => Some(Interpreter::Zsh),
+        "fish" => Some(Interpreter::Fish),
+        "sh" | "dash" => Some(Interpreter::Sh),
+        "node" | "nodejs" => Some(Interpreter::Node),
+        "deno" => Some(Interpreter::Deno),
+        // python, python3, python3.12
+        name if name
+            .strip_prefix("python")
+            .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit() || c == '.')) =>
+        {
+            Some(Interpreter::Python)
+        }
+        _ => None,
+    }
+}
+
+fn file_name(program: &str) -> &str {
+    program.rsplit('/').next().unwrap_or(program)
+}
+
+// Keep the line count so error messages point at the right lines
+fn without_shebang(body: &str) -> String {
+    if body.starts_with("#!") {
+        match body.find('\n') {
+            Some(end) => body[end..].to_string(),
+            None => String::new(),
+        }
+    } else {
+        body.to_string()
+    }
+}
+
+// The app's PATH followed by the install locations that exist and are missing from it
+fn search_path() -> std::ffi::OsString {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let home = resolve_home();
+    for dir in EXTRA_DIRS {
+        let dir = match dir.strip_prefix("~/") {
+            Some(rest) => PathBuf::from(&home).join(rest),
+            None => PathBuf::from(dir),
+        };
+        if dir.is_dir() && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    std::env::join_paths(dirs).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::placeholders::Template;
+    use std::collections::HashMap;
+
+    #[test]
+    fn references_environment_variables_in_each_language() {
+        let reference = |interpreter| env_reference(Some(interpreter), "SSM_SECRET_TOKEN");
+        assert_eq!(reference(Interpreter::Bash), "${SSM_SECRET_TOKEN}");
+        assert_eq!(reference(Interpreter::Zsh), "${SSM_SECRET_TOKEN}");
+        assert_eq!(reference(Interpreter::Sh), "${SSM_SECRET_TOKEN}");
+        assert_eq!(reference(Interpreter::Fish), "$SSM_SECRET_TOKEN");
+        assert_eq!(
+            reference(Interpreter::Python),
+            r#"__import__("os").environ["SSM_SECRET_TOKEN"]"#
+        );
+        assert_eq!(reference(Interpreter::Node), "process.env.SSM_SECRET_TOKEN");
+        assert_eq!(
+            reference(Interpreter::Deno),
+            r#"Deno.env.get("SSM_SECRET_TOKEN")"#
+        );
+    }
+
+    #[test]
+    fn runs_remotely_with_the_interpreter_on_the_remote_path() {
+        assert_eq!(
+            Interpreter::Python.remote_invocation("print('hi')"),
+            r#"exec python3 -c 'print('\''hi'\'')'"#
+        );
+        assert_eq!(
+            Interpreter::Node.remote_invocation("#!/usr/bin/env node\nconsole.log(1)"),
+            "exec node -e '\nconsole.log(1)'"
+        );
+    }
+
+    // Runs a body that prints two text parameters, an int, a bool and a secret with every
+    // installed interpreter; the values would break the body if they were spliced in as text
+    #[test]
+    fn bodies_read_parameters_and_secrets_in_their_own_syntax() {
+        let text = r#"it's "$HOME" `x` ${y}"#;
+        let path = "/tmp/a b";
+        let secret = "s3cr3t'\"";
+        let cases = [
+            (
+                Interpreter::Sh,
+                "printf '%s|%s|%s|%s|%s' {{param:text}} {{param:dir:path}} {{param:n:int}} \
+                 {{param:flag:bool}} \"$SECRET\"",
+                "true",
+            ),
+            (
+                Interpreter::Fish,
+                "printf '%s|%s|%s|%s|%s' {{param:text}} {{param:dir:path}} {{param:n:int}} \
+                 {{param:flag:bool}} \"$SECRET\"",
+                "true",
+            ),
+            (
+                Interpreter::Python,
+                "print('|'.join(map(str, [{{param:text}}, {{param:dir:path}}, {{param:n:int}}, \
+                 {{param:flag:bool}}, $SECRET])), end='')",
+                "True",
+            ),
+            (
+                Interpreter::Node,
+                "process.stdout.write([{{param:text}}, {{param:dir:path}}, {{param:n:int}}, \
+                 {{param:flag:bool}}, $SECRET].join('|'))",
+                "true",
+            ),
+            (
+                Interpreter::Deno,
+                "Deno.stdout.writeSync(new TextEncoder().encode([{{param:text}}, \
+                 {{param:dir:path}}, {{param:n:int}}, {{param:flag:bool}}, $SECRET].join('|')))",
+                "true",
+            ),
+        ];
+        let provided = HashMap::from([
+            ("text".to_string(), text.to_string()),
+            ("dir".to_string(), path.to_string()),
+            ("n".to_string(), " 42".to_string()),
+            ("flag".to_string(), "yes".to_string()),
+        ]);
+
+        for (interpreter, body, shown_true) in cases {
+            let Ok(program) = interpreter.locate() else {
+                continue;
+            };
+            let body = body.replace("$SECRET", &env_reference(Some(interpreter), "SSM_SECRET_S"));
+            let rendered = Template::parse(&body)
+                .unwrap()
+                .render(&provided, &HashMap::new(), Some(interpreter))
+                .unwrap();
+            let (command, env) = (rendered.command, rendered.env);
+            assert_eq!(env.is_empty(), interpreter.is_shell(), "{}", command);
+
+            let output = std::process::Command::new(&program)
+                .args(interpreter.code_args(&command))
+                .envs(env.iter().map(|var| (&var.name, &var.value)))
+                .env("SSM_SECRET_S", secret)
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                interpreter.as_str(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                format!("{}|{}|42|{}|{}", text, path, shown_true, secret),
+                "{}",
+                interpreter.as_str()
+            );
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Optional per-script execution limits. `None` means unlimited.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionLimits {
+    /// Wall-clock timeout after which the whole process group is killed.
+    pub timeout_seconds: Option<u64>,
+    /// Maximum number of stdout + stderr bytes captured before the run is killed.
+    pub max_output_bytes: Option<u64>,
+    /// RLIMIT_CPU for every process in the run (Unix only).
+    pub cpu_seconds: Option<u64>,
+    /// RLIMIT_AS in megabytes for every process in the run (Unix only).
+    pub address_space_mb: Option<u64>,
+    /// RLIMIT_NOFILE for every process in the run (Unix only).
+    pub open_files: Option<u64>,
+}
+
+/// The limit that caused a run to be stopped.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "limit", rename_all = "camelCase")]
+pub enum LimitExceeded {
+    Timeout { seconds: u64 },
+    OutputBytes { bytes: u64 },
+    CpuSeconds { seconds: u64 },
+    AddressSpace { megabytes: u64 },
+    OpenFiles { count: u64 },
+}
+
+impl LimitExceeded {
+    /// Whether the limit is only inferred from a crash or an error message rather than known to
+    /// have stopped the run.
+    pub fn is_probable(&self) -> bool {
+        matches!(
+            self,
+            LimitExceeded::AddressSpace { .. } | LimitExceeded::OpenFiles { .. }
+        )
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Timeout { seconds } => write!(f, "timeout of {}s", seconds),
+            LimitExceeded::OutputBytes { bytes } => write!(f, "output limit of {} bytes", bytes),
+            LimitExceeded::CpuSeconds { seconds } => write!(f, "CPU time limit of {}s", seconds),
+            LimitExceeded::AddressSpace { megabytes } => {
+                write!(f, "address space limit of {} MB", megabytes)
+            }
+            LimitExceeded::OpenFiles { count } => write!(f, "open files limit of {}", count),
+        }
+    }
+}
+
+impl ExecutionLimits {
+    /// Install the rlimits in the child right before it execs. The limits are inherited by
+    /// everything the login shell starts.
+    pub fn apply_rlimits(&self, cmd: &mut tokio::process::Command) {
+        #[cfg(unix)]
+        {
+            let cpu_seconds = self.cpu_seconds;
+            let address_space = self
+                .address_space_mb
+                .map(|mb| mb.saturating_mul(1024 * 1024));
+            let open_files = self.open_files;
+            if cpu_seconds.is_none() && address_space.is_none() && open_files.is_none() {
+                return;
+            }
+
+            // SAFETY: the closure only calls async-signal-safe setrlimit(2).
+            unsafe {
+                cmd.pre_exec(move || {
+                    if let Some(seconds) = cpu_seconds {
+                        // The soft limit delivers SIGXCPU, the hard limit one second later SIGKILL.
+                        set_rlimit(libc::RLIMIT_CPU, seconds, seconds.saturating_add(1))?;
+                    }
+                    if let Some(bytes) = address_space {
+                        set_rlimit(libc::RLIMIT_AS, bytes, bytes)?;
+                    }
+                    if let Some(count) = open_files {
+                        set_rlimit(libc::RLIMIT_NOFILE, count, count)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = cmd;
+    }
+
+    /// The same limits without the rlimits, for runs where they cannot be installed such as
+    /// remote ones; how those runs end says nothing about a limit.
+    pub fn without_rlimits(&self) -> Self {
+        Self {
+            cpu_seconds: None,
+            address_space_mb: None,
+            open_files: None,
+            ..self.clone()
+        }
+    }
+
+    /// Work out which rlimit (if any) explains how the run ended. The kernel does not say why a
+    /// process died, so this looks at the signal that killed the process and at the error text
+    /// the usual tools print. An exit code above 128 is not taken as a signal: a shell reports a
+    /// crashed child that way, but a script can just as well `exit 139`. Only limits set on the
+    /// run are considered; any other crash is reported through [`exit_signal`].
+    pub fn classify_exit(
+        &self,
+        status: &std::process::ExitStatus,
+        stderr: &str,
+    ) -> Option<LimitExceeded> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            let signal = status.signal();
+            let lowered = stderr.to_lowercase();
+
+            if let Some(seconds) = self.cpu_seconds {
+                if signal == Some(libc::SIGXCPU) {
+                    return Some(LimitExceeded::CpuSeconds { seconds });
+                }
+            }
+            if let Some(megabytes) = self.address_space_mb {
+                // A failed allocation usually ends in an abort or a crash, but so does a plain bug
+                let crashed = matches!(signal, Some(s) if s == libc::SIGSEGV || s == libc::SIGABRT || s == libc::SIGBUS);
+                if crashed
+                    || lowered.contains("cannot allocate memory")
+                    || lowered.contains("out of memory")
+                    || lowered.contains("memoryerror")
+                {
+                    return Some(LimitExceeded::AddressSpace { megabytes });
+                }
+            }
+            if let Some(count) = self.open_files {
+                if lowered.contains("too many open files") {
+                    return Some(LimitExceeded::OpenFiles { count });
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (status, stderr);
+        None
+    }
+}
+
+/// The signal that terminated the process, `None` when it exited normally.
+pub fn exit_signal(status: &std::process::ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+#[cfg(unix)]
+fn set_rlimit(resource: RlimitResource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// glibc types the resource argument as its own enum-like integer, other libcs as c_int.
+#[cfg(all(unix, target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn exit_of(script: &str) -> std::process::ExitStatus {
+        Command::new("sh").arg("-c").arg(script).status().unwrap()
+    }
+
+    fn all_limits() -> ExecutionLimits {
+        ExecutionLimits {
+            cpu_seconds: Some(5),
+            address_space_mb: Some(256),
+            open_files: Some(64),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn takes_exit_codes_above_128_at_face_value() {
+        let limits = all_limits();
+        for code in [139, 134, 128 + libc::SIGXCPU] {
+            let status = exit_of(&format!("exit {}", code));
+            assert_eq!(exit_signal(&status), None);
+            assert!(
+                limits.classify_exit(&status, "").is_none(),
+                "exit {} was blamed on a limit",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn reports_a_crash_under_a_memory_limit_as_probable() {
+        let status = exit_of("kill -SEGV $$");
+        assert_eq!(exit_signal(&status), Some(libc::SIGSEGV));
+
+        let limit = all_limits().classify_exit(&status, "").unwrap();
+        assert!(matches!(
+            limit,
+            LimitExceeded::AddressSpace { megabytes: 256 }
+        ));
+        assert!(limit.is_probable());
+        assert!(ExecutionLimits::default()
+            .classify_exit(&status, "")
+            .is_none());
+    }
+
+    #[test]
+    fn knows_the_cpu_limit_from_its_signal() {
+        let limit = all_limits()
+            .classify_exit(&exit_of("kill -XCPU $$"), "")
+            .unwrap();
+        assert!(matches!(limit, LimitExceeded::CpuSeconds { seconds: 5 }));
+        assert!(!limit.is_probable());
+    }
+}
@@ -0,0 +1,649 @@
+pub mod history;
+pub mod interpreter;
+pub mod limits;
+pub mod placeholders;
+pub mod run_config;
+
+use serde::Serialize;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, EventTarget};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+
+use crate::db::repository::execution_limit_repository::ExecutionLimitRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::environment::merge_env_vars;
+use crate::jobs;
+use crate::secrets::SecretMask;
+use crate::ssh::{self, SshTarget};
+use history::{ExecutionMode, HistoryEntry};
+use limits::{exit_signal, ExecutionLimits, LimitExceeded};
+use run_config::{EnvVar, ResolvedRunConfig, RunConfig};
+
+/// Emitted once per line of stdout/stderr while a streaming run is in progress.
+pub const SCRIPT_OUTPUT_EVENT: &str = "script-output";
+/// Emitted exactly once when a streaming run has finished (or failed to be awaited).
+pub const SCRIPT_EXITED_EVENT: &str = "script-exited";
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptOutputEvent {
+    pub run_id: String,
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptExitedEvent {
+    pub run_id: String,
+    /// `None` when the process was terminated by a signal.
+    pub exit_code: Option<i32>,
+    /// The signal that terminated the process, if any.
+    pub signal: Option<i32>,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub output_truncated: bool,
+    /// Set when the run was stopped because it hit one of the script's execution limits.
+    pub limit_exceeded: Option<LimitExceeded>,
+}
+
+/// Everything known about a finished run.
+pub struct ExecutionOutcome {
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub success: bool,
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+    pub output_truncated: bool,
+    pub limit_exceeded: Option<LimitExceeded>,
+}
+
+impl ExecutionOutcome {
+    /// The message returned to the frontend when the run did not succeed.
+    pub fn error_message(&self) -> String {
+        if let Some(limit) = &self.limit_exceeded {
+            if limit.is_probable() {
+                return format!("Execution limit probably exceeded: {}", limit);
+            }
+            return format!("Execution limit exceeded: {}", limit);
+        }
+        if !self.stderr.is_empty() {
+            self.stderr.clone()
+        } else if !self.stdout.is_empty() {
+            self.stdout.clone()
+        } else if let Some(signal) = self.signal {
+            format!("Command terminated by signal {}", signal)
+        } else {
+            format!("Command failed with exit code: {:?}", self.exit_code)
+        }
+    }
+}
+
+/// A command to run through [`run_silent`].
+pub struct SilentRun {
+    pub run_id: String,
+    /// Placeholders must already be resolved.
+    pub command: String,
+    pub script_id: Option<i32>,
+    pub mode: ExecutionMode,
+    /// Written to the process and then closed, see [`jobs::feed_stdin`].
+    pub stdin: Option<String>,
+    /// Keep stdin open for [`jobs::write_stdin`], after `stdin` if given. Otherwise the process
+    /// reads EOF once it has consumed `stdin`, or right away without it.
+    pub interactive_stdin: bool,
+    /// Set on top of the script's own variables, e.g. what triggered the run.
+    pub env: Vec<EnvVar>,
+    /// Run on this SSH target instead of locally.
+    pub ssh_target_id: Option<i32>,
+    /// Stop the run after this long even if the script's own timeout is longer.
+    pub timeout_seconds: Option<u64>,
+}
+
+#[derive(Default)]
+struct CapturedOutput {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    total_bytes: u64,
+    truncated: bool,
+}
+
+// Get the user's home directory
+pub fn resolve_home() -> String {
+    std::env::var("HOME").unwrap_or_else(|_| {
+        dirs::home_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/Users".to_string())
+    })
+}
+
+// Detect the user's shell from /etc/passwd or use zsh as default
+pub fn resolve_shell(home: &str) -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| {
+        std::fs::read_to_string("/etc/passwd")
+            .ok()
+            .and_then(|content| {
+                content.lines().find_map(|line| {
+                    if line.contains(home) {
+                        line.split(':').next_back().map(|s| s.to_string())
+                    } else {
+                        None
+                    }
+                })
+            })
+            .unwrap_or_else(|| "/bin/zsh".to_string())
+    })
+}
+
+// Build the command that sources the shell config files before running
+pub fn wrap_with_shell_config(shell: &str, command: &str) -> String {
+    if shell.contains("zsh") {
+        format!(
+            "source ~/.zshrc 2>/dev/null; source ~/.zprofile 2>/dev/null; {}",
+            command
+        )
+    } else if shell.contains("bash") {
+        format!(
+            "source ~/.bash_profile 2>/dev/null; source ~/.bashrc 2>/dev/null; {}",
+            command
+        )
+    } else {
+        command.to_string()
+    }
+}
+
+/// Build a login-shell invocation of `command` with the user's rc files sourced,
+/// the same way for every silent execution path. The script's env var overrides win over
+/// HOME/USER.
+pub fn build_shell_command(
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) -> tokio::process::Command {
+    let home = resolve_home();
+    let shell = resolve_shell(&home);
+
+    #[cfg(debug_assertions)]
+    println!("Using shell: {} for command: {}", shell, command);
+
+    let wrapped_command = wrap_with_shell_config(&shell, command);
+
+    let mut cmd = tokio::process::Command::new(&shell);
+    cmd.arg("-l") // Login shell
+        .arg("-c")
+        .arg(&wrapped_command)
+        .env("HOME", &home) // Ensure HOME is set
+        .env(
+            "USER",
+            std::env::var("USER").unwrap_or_else(|_| whoami::username()),
+        );
+    run_config.apply(&mut cmd);
+    jobs::isolate_process_group(&mut cmd);
+    cmd
+}
+
+/// Build the process that runs `command` with the script's interpreter, the one its shebang
+/// line names, or else the login shell via [`build_shell_command`].
+pub fn build_command(
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) -> Result<tokio::process::Command, String> {
+    match interpreter::resolve(run_config.interpreter, command)? {
+        Some(interpreter) => interpreter::build_command(interpreter, command, run_config),
+        None => Ok(build_shell_command(command, run_config)),
+    }
+}
+
+/// Spawn `cmd` and register it in the job registry under `run_id` so it can be listed,
+/// cancelled and awaited. A piped stdin is kept in the registry for [`jobs::write_stdin`].
+/// `cmd` must set its stdin: an unset one is inherited from the app.
+/// Callers must report completion through [`jobs::finish`].
+pub fn spawn_job(
+    run_id: &str,
+    command: &str,
+    cmd: &mut tokio::process::Command,
+) -> Result<Child, String> {
+    let child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+    register_job(run_id, command, child)
+}
+
+/// The registration half of [`spawn_job`], for children that need some setup between being
+/// spawned and becoming visible as a job.
+pub fn register_job(run_id: &str, command: &str, mut child: Child) -> Result<Child, String> {
+    if let Err(e) = jobs::register(run_id, command, child.id()) {
+        let _ = child.start_kill();
+        return Err(e);
+    }
+    if let Some(stdin) = child.stdin.take() {
+        if let Err(e) = jobs::attach_stdin(run_id, stdin) {
+            let _ = child.start_kill();
+            return Err(e);
+        }
+    }
+    Ok(child)
+}
+
+/// Start `command` as a registered job with piped output, locally with [`build_command`] or on
+/// `target` over SSH. `command` must already have its secrets injected into `run_config`.
+/// Stdin is only piped with `pipe_stdin`; otherwise it is null so reads see EOF instead of
+/// waiting forever. CPU and memory limits only apply to local runs.
+pub async fn spawn_run(
+    run_id: &str,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+    limits: &ExecutionLimits,
+    target: Option<&SshTarget>,
+    pipe_stdin: bool,
+) -> Result<Child, String> {
+    if let Some(target) = target {
+        return ssh::spawn_job(
+            run_id,
+            command,
+            run_config,
+            target,
+            pipe_stdin,
+            limits.timeout_seconds,
+        )
+        .await;
+    }
+    let stdin = if pipe_stdin {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    let mut cmd = build_command(command, run_config)?;
+    cmd.stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    limits.apply_rlimits(&mut cmd);
+    spawn_job(run_id, command, &mut cmd)
+}
+
+/// Load the execution limits stored for `script_id`; ad-hoc commands run unlimited.
+pub async fn load_limits(script_id: Option<i32>) -> Result<ExecutionLimits, String> {
+    let Some(script_id) = script_id else {
+        return Ok(ExecutionLimits::default());
+    };
+    let limits = ExecutionLimitRepository::new()
+        .get_limits(script_id)
+        .await
+        .map_err(|e| format!("Failed to load execution limits: {}", e))?;
+    Ok(limits
+        .as_ref()
+        .map(ExecutionLimits::from)
+        .unwrap_or_default())
+}
+
+/// Drive a registered job to completion: read stdout/stderr line by line (handing each line
+/// to `on_line`), enforce `limits`, wait for the exit status and report it to the registry.
+/// Secret values are masked in the lines and in the captured output.
+///
+/// Exceeding the timeout or the output cap kills the whole process group; rlimit violations
+/// are detected from how the process ended.
+pub async fn run_to_completion<F>(
+    run_id: &str,
+    mut child: Child,
+    limits: &ExecutionLimits,
+    mask: &SecretMask,
+    started: Instant,
+    on_line: F,
+) -> ExecutionOutcome
+where
+    F: Fn(OutputStream, String) + Sync,
+{
+    let pid = child.id();
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let captured = Mutex::new(CapturedOutput::default());
+    let exceeded: Mutex<Option<LimitExceeded>> = Mutex::new(None);
+    let on_line = |stream: OutputStream, line: String| on_line(stream, mask.apply(&line));
+
+    let on_limit = |limit: LimitExceeded| {
+        let mut exceeded = exceeded.lock().unwrap();
+        if exceeded.is_none() {
+            println!("Run {} exceeded its {}, killing it", run_id, limit);
+            *exceeded = Some(limit);
+            if let Some(pid) = pid {
+                if let Err(e) = jobs::kill_process_group(pid) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+    };
+
+    let io = async {
+        tokio::join!(
+            pump_output(
+                stdout,
+                OutputStream::Stdout,
+                limits,
+                &captured,
+                &on_limit,
+                &on_line
+            ),
+            pump_output(
+                stderr,
+                OutputStream::Stderr,
+                limits,
+                &captured,
+                &on_limit,
+                &on_line
+            ),
+        );
+        child.wait().await
+    };
+
+    let status = match limits.timeout_seconds {
+        Some(seconds) => match tokio::time::timeout(Duration::from_secs(seconds), io).await {
+            Ok(status) => status,
+            Err(_) => {
+                on_limit(LimitExceeded::Timeout { seconds });
+                child.wait().await
+            }
+        },
+        None => io.await,
+    };
+
+    let captured = captured.into_inner().unwrap();
+    let stdout = mask.apply(&String::from_utf8_lossy(&captured.stdout));
+    let stderr = mask.apply(&String::from_utf8_lossy(&captured.stderr));
+
+    let (exit_code, signal, mut success, mut limit_exceeded) = match &status {
+        Ok(status) => (
+            status.code(),
+            exit_signal(status),
+            status.success(),
+            limits.classify_exit(status, &stderr),
+        ),
+        Err(e) => {
+            eprintln!("Failed to wait for run {}: {}", run_id, e);
+            (None, None, false, None)
+        }
+    };
+    if let Some(limit) = exceeded.into_inner().unwrap() {
+        limit_exceeded = Some(limit);
+    }
+    if limit_exceeded.is_some() {
+        success = false;
+    }
+
+    let duration = started.elapsed();
+    jobs::finish(run_id, exit_code, success, duration);
+
+    ExecutionOutcome {
+        exit_code,
+        signal,
+        success,
+        duration,
+        stdout,
+        stderr,
+        output_truncated: captured.truncated,
+        limit_exceeded,
+    }
+}
+
+/// The silent execution path shared by every caller that waits for the result: load the
+/// script's limits and run configuration, inject its secrets, run the command as a registered
+/// job and record it in the history. Fails only when the run cannot be started; a failing
+/// command is reported through the outcome.
+pub async fn run_silent<F>(run: SilentRun, on_line: F) -> Result<ExecutionOutcome, String>
+where
+    F: Fn(OutputStream, String) + Sync,
+{
+    let mut limits = load_limits(run.script_id).await?;
+    if let Some(seconds) = run.timeout_seconds {
+        limits.timeout_seconds = Some(
+            limits
+                .timeout_seconds
+                .map_or(seconds, |own| own.min(seconds)),
+        );
+    }
+    let target = match run.ssh_target_id {
+        Some(target_id) => Some(ssh::load_target(target_id).await?),
+        None => None,
+    };
+    let mut run_config = match target {
+        Some(_) => {
+            limits = limits.without_rlimits();
+            RunConfig::load_for_remote_run(run.script_id).await?
+        }
+        None => RunConfig::load_for_run(run.script_id).await?,
+    };
+    let started = Instant::now();
+    let mut history = HistoryEntry::start(
+        run.script_id,
+        &run.run_id,
+        run.mode,
+        &run.command,
+        &run_config,
+    );
+    if let (Some(history), Some(target)) = (history.as_mut(), &target) {
+        history.set_remote(target);
+    }
+    let shell_command = run_config.inject_secrets(&run.command).await?;
+    // Added after the secrets are resolved: these values come from outside the script and must
+    // not be able to reference secrets
+    merge_env_vars(&mut run_config.env, run.env);
+
+    let child = spawn_run(
+        &run.run_id,
+        &shell_command,
+        &run_config,
+        &limits,
+        target.as_ref(),
+        run.stdin.is_some() || run.interactive_stdin,
+    )
+    .await?;
+    if let Some(input) = run.stdin {
+        jobs::feed_stdin(&run.run_id, input.into_bytes(), !run.interactive_stdin);
+    }
+    let outcome = run_to_completion(
+        &run.run_id,
+        child,
+        &limits,
+        &run_config.secrets.mask,
+        started,
+        on_line,
+    )
+    .await;
+    if let Some(history) = history {
+        history.record(Some(&outcome)).await;
+    }
+    Ok(outcome)
+}
+
+/// Run a stored script unattended through [`run_silent`], as the steps of chains and graphs do.
+/// Nobody is asked for parameters, so placeholders take their last used or default values.
+pub async fn run_stored_script<F>(
+    script_id: i32,
+    run_id: &str,
+    mode: ExecutionMode,
+    env: Vec<EnvVar>,
+    on_line: F,
+) -> Result<ExecutionOutcome, String>
+where
+    F: Fn(OutputStream, String) + Sync,
+{
+    let (command, mut parameter_env) = stored_command(script_id).await?;
+    merge_env_vars(&mut parameter_env, env);
+    let run = SilentRun {
+        run_id: run_id.to_string(),
+        command,
+        script_id: Some(script_id),
+        mode,
+        stdin: None,
+        interactive_stdin: false,
+        env: parameter_env,
+        ssh_target_id: None,
+        timeout_seconds: None,
+    };
+    run_silent(run, on_line).await
+}
+
+/// The command of a stored script as an unattended run executes it, and the variables that carry
+/// its parameters: placeholders take their last used or default values.
+pub async fn stored_command(script_id: i32) -> Result<(String, Vec<EnvVar>), String> {
+    let script = ScriptRepository::new()
+        .get_script(script_id)
+        .await
+        .map_err(|e| format!("Failed to load script {}: {}", script_id, e))?
+        .ok_or_else(|| format!("Script {} not found", script_id))?;
+    placeholders::resolve_command(&script.command, Some(script_id), None).await
+}
+
+/// Generate an id used to correlate streamed events with the run that produced them.
+pub fn new_run_id() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("run-{}-{:08x}", ts, rand::random::<u32>())
+}
+
+/// Forward every line of a job started with [`spawn_run`] to the webview window `label` as a
+/// `script-output` event, finishing with a single `script-exited` event. The run is written to
+/// the script's history once it has finished.
+///
+/// The child is spawned by the caller so that a failure to start is reported there; reading and
+/// waiting happen on a background task.
+pub fn spawn_streaming(
+    app: tauri::AppHandle,
+    label: String,
+    run_id: String,
+    child: Child,
+    limits: ExecutionLimits,
+    mask: SecretMask,
+    history: Option<HistoryEntry>,
+) {
+    let started = Instant::now();
+
+    tauri::async_runtime::spawn(async move {
+        let _database = crate::backup::use_database().await;
+        let target = EventTarget::webview_window(label);
+
+        let emit_line = |stream: OutputStream, line: String| {
+            let event = ScriptOutputEvent {
+                run_id: run_id.clone(),
+                stream,
+                line,
+            };
+            if let Err(e) = app.emit_to(target.clone(), SCRIPT_OUTPUT_EVENT, event) {
+                eprintln!("Failed to emit {}: {}", SCRIPT_OUTPUT_EVENT, e);
+            }
+        };
+
+        let outcome = run_to_completion(&run_id, child, &limits, &mask, started, emit_line).await;
+
+        #[cfg(debug_assertions)]
+        println!(
+            "Streaming run {} finished with exit code {:?}",
+            run_id, outcome.exit_code
+        );
+
+        let exited = ScriptExitedEvent {
+            run_id: run_id.clone(),
+            exit_code: outcome.exit_code,
+            signal: outcome.signal,
+            success: outcome.success,
+            duration_ms: outcome.duration.as_millis() as u64,
+            output_truncated: outcome.output_truncated,
+            limit_exceeded: outcome.limit_exceeded.clone(),
+        };
+        if let Err(e) = app.emit_to(target, SCRIPT_EXITED_EVENT, exited) {
+            eprintln!("Failed to emit {}: {}", SCRIPT_EXITED_EVENT, e);
+        }
+
+        if let Some(history) = history {
+            history.record(Some(&outcome)).await;
+        }
+    });
+}
+
+// Read a child pipe line by line, tolerating invalid UTF-8, until EOF. Lines past the output
+// cap are neither captured nor forwarded.
+async fn pump_output<R, L, F>(
+    reader: Option<R>,
+    stream: OutputStream,
+    limits: &ExecutionLimits,
+    captured: &Mutex<CapturedOutput>,
+    on_limit: &L,
+    on_line: &F,
+) where
+    R: AsyncRead + Unpin,
+    L: Fn(LimitExceeded),
+    F: Fn(OutputStream, String),
+{
+    let Some(reader) = reader else {
+        return;
+    };
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let over_limit = {
+                    let mut captured = captured.lock().unwrap();
+                    if captured.truncated {
+                        continue;
+                    }
+                    captured.total_bytes += buf.len() as u64;
+                    match limits.max_output_bytes {
+                        Some(max) if captured.total_bytes > max => {
+                            // Keep whatever still fits so the tail of the log is not lost entirely
+                            let room =
+                                (buf.len() as u64).saturating_sub(captured.total_bytes - max);
+                            let target = match stream {
+                                OutputStream::Stdout => &mut captured.stdout,
+                                OutputStream::Stderr => &mut captured.stderr,
+                            };
+                            target.extend_from_slice(&buf[..room as usize]);
+                            captured.truncated = true;
+                            Some(max)
+                        }
+                        _ => {
+                            let target = match stream {
+                                OutputStream::Stdout => &mut captured.stdout,
+                                OutputStream::Stderr => &mut captured.stderr,
+                            };
+                            target.extend_from_slice(&buf);
+                            None
+                        }
+                    }
+                };
+
+                if let Some(bytes) = over_limit {
+                    on_limit(LimitExceeded::OutputBytes { bytes });
+                    continue;
+                }
+
+                let mut line = buf.as_slice();
+                while let Some((last, rest)) = line.split_last() {
+                    if *last == b'\n' || *last == b'\r' {
+                        line = rest;
+                    } else {
+                        break;
+                    }
+                }
+                on_line(stream, String::from_utf8_lossy(line).to_string());
+            }
+            Err(e) => {
+                eprintln!("Failed to read command output: {}", e);
+                break;
+            }
+        }
+    }
+}
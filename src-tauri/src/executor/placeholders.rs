@@ -0,0 +1,668 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::repository::parameter_value_repository::ParameterValueRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+
+use super::interpreter::{self, Interpreter};
+use super::run_config::EnvVar;
+
+// Marks a placeholder. Other `{{...}}` text (Go, Helm or Mustache templates) is left alone.
+const PREFIX: &str = "{{param:";
+const SUFFIX: &str = "}}";
+const TYPE_NAMES: &str = "string, int, bool, path or enum(a|b)";
+// Text values reach Python, Node and Deno bodies through these variables, since they cannot be
+// spliced into the source as shell words
+const PARAM_VAR_PREFIX: &str = "SSM_PARAM_";
+
+/// The type of a `{{param:name:type=default}}` placeholder.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ParameterType {
+    String,
+    Int,
+    Bool,
+    Path,
+    Enum { choices: Vec<String> },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterSpec {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ParameterType,
+    pub default: Option<String>,
+}
+
+/// A command with its placeholders substituted.
+pub struct Rendered {
+    pub command: String,
+    /// The raw value used for each parameter.
+    pub used: HashMap<String, String>,
+    /// Variables the run must set, read by placeholders rendered as environment references.
+    pub env: Vec<EnvVar>,
+}
+
+/// A parsed command: literal text interleaved with placeholder references.
+pub struct Template {
+    parts: Vec<Part>,
+    parameters: Vec<ParameterSpec>,
+}
+
+enum Part {
+    Literal(String),
+    Placeholder(String),
+}
+
+impl Template {
+    /// Parse `{{param:name}}`, `{{param:name:type}}` and `{{param:name:type=default}}`
+    /// placeholders. Enum choices are written as `{{param:env:enum(dev|staging|prod)=dev}}`;
+    /// they may contain `=` and balanced parentheses, but not `|` or `}}`.
+    /// The same name may appear several times as long as every occurrence agrees on type and
+    /// default (later ones may omit them). Braces without the `param:` prefix are plain text.
+    pub fn parse(command: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut parameters: Vec<ParameterSpec> = Vec::new();
+        let mut rest = command;
+
+        while let Some(start) = rest.find(PREFIX) {
+            let body_start = start + PREFIX.len();
+            let Some(len) = rest[body_start..].find(SUFFIX) else {
+                return Err(format!(
+                    "Unterminated placeholder '{}'",
+                    rest[start..].lines().next().unwrap_or_default()
+                ));
+            };
+            let body = &rest[body_start..body_start + len];
+            let spec = parse_placeholder(body)?;
+
+            parts.push(Part::Literal(rest[..start].to_string()));
+            parts.push(Part::Placeholder(spec.name.clone()));
+            merge_spec(&mut parameters, spec, body)?;
+            rest = &rest[body_start + len + SUFFIX.len()..];
+        }
+        parts.push(Part::Literal(rest.to_string()));
+
+        Ok(Self { parts, parameters })
+    }
+
+    pub fn parameters(&self) -> &[ParameterSpec] {
+        &self.parameters
+    }
+
+    /// Substitute every placeholder for a run with `interpreter` (`None` is the login shell).
+    /// For each parameter the value comes from `provided`, then `remembered`, then the default;
+    /// a parameter without any of them is an error. Values are validated against the parameter
+    /// type. Shell bodies get them shell-escaped. Python, Node and Deno bodies get ints and
+    /// bools as literals and text as an expression reading an environment variable, so there a
+    /// placeholder stands for a value and is written without quotes.
+    ///
+    pub fn render(
+        &self,
+        provided: &HashMap<String, String>,
+        remembered: &HashMap<String, String>,
+        interpreter: Option<Interpreter>,
+    ) -> Result<Rendered, String> {
+        let mut used = HashMap::new();
+        let mut escaped = HashMap::new();
+        let mut env = Vec::new();
+        for spec in &self.parameters {
+            let value = provided
+                .get(&spec.name)
+                .or_else(|| remembered.get(&spec.name))
+                .or(spec.default.as_ref())
+                .ok_or_else(|| format!("Missing value for parameter '{}'", spec.name))?;
+            let text = match interpreter {
+                Some(interpreter) if !interpreter.is_shell() => {
+                    let value = normalize(spec, value)?;
+                    match spec.kind {
+                        ParameterType::Int => value,
+                        ParameterType::Bool => match (interpreter, value.as_str()) {
+                            (Interpreter::Python, "true") => "True".to_string(),
+                            (Interpreter::Python, _) => "False".to_string(),
+                            _ => value,
+                        },
+                        _ => {
+                            let name = param_var_name(&spec.name);
+                            let reference = interpreter.env_reference(&name);
+                            env.push(EnvVar { name, value });
+                            reference
+                        }
+                    }
+                }
+                _ => validate(spec, value)?,
+            };
+            escaped.insert(spec.name.clone(), text);
+            used.insert(spec.name.clone(), value.clone());
+        }
+
+        let mut command = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => command.push_str(text),
+                Part::Placeholder(name) => command.push_str(&escaped[name]),
+            }
+        }
+        Ok(Rendered { command, used, env })
+    }
+}
+
+/// A parameter as shown to the user before running a script.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptParameter {
+    #[serde(flatten)]
+    pub spec: ParameterSpec,
+    pub last_value: Option<String>,
+}
+
+/// The parameter schema of a stored script, with the values used on its last run.
+pub async fn script_parameters(script_id: i32) -> Result<Vec<ScriptParameter>, String> {
+    let script = ScriptRepository::new()
+        .get_script(script_id)
+        .await
+        .map_err(|e| format!("Failed to load script {}: {}", script_id, e))?
+        .ok_or_else(|| format!("Script {} not found", script_id))?;
+    let template = Template::parse(&script.command)?;
+    if template.parameters().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut last_values = ParameterValueRepository::new()
+        .get_last_values(script_id)
+        .await
+        .map_err(|e| format!("Failed to load last parameter values: {}", e))?;
+    Ok(template
+        .parameters()
+        .iter()
+        .map(|spec| ScriptParameter {
+            last_value: last_values.remove(&spec.name),
+            spec: spec.clone(),
+        })
+        .collect())
+}
+
+/// Resolve the placeholders of `command` right before it runs, in the syntax of the
+/// interpreter that will run it, and remember the values used for the script. Returns the
+/// command and the variables to set on the run. Commands without placeholders are returned
+/// unchanged.
+pub async fn resolve_command(
+    command: &str,
+    script_id: Option<i32>,
+    provided: Option<HashMap<String, String>>,
+) -> Result<(String, Vec<EnvVar>), String> {
+    let template = Template::parse(command)?;
+    if template.parameters().is_empty() {
+        return Ok((command.to_string(), vec![]));
+    }
+    let interpreter = interpreter::resolve(interpreter::configured(script_id).await?, command)?;
+
+    let repository = ParameterValueRepository::new();
+    let remembered = match script_id {
+        Some(script_id) => repository
+            .get_last_values(script_id)
+            .await
+            .map_err(|e| format!("Failed to load last parameter values: {}", e))?,
+        None => HashMap::new(),
+    };
+    let rendered = template.render(&provided.unwrap_or_default(), &remembered, interpreter)?;
+
+    if let Some(script_id) = script_id {
+        if let Err(e) = repository.save_last_values(script_id, &rendered.used).await {
+            eprintln!("Failed to remember parameter values: {}", e);
+        }
+    }
+    Ok((rendered.command, rendered.env))
+}
+
+/// Quote `value` for a POSIX shell so it is passed through as exactly one word.
+pub fn shell_quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+// `body` is what follows `{{param:`
+// The index of the ')' that closes the first '(' in `text`
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_placeholder(body: &str) -> Result<ParameterSpec, String> {
+    let (name, type_and_default) = match body.split_once(':') {
+        Some((name, rest)) => (name.trim(), Some(rest)),
+        None => (body.trim(), None),
+    };
+    if !is_valid_name(name) {
+        return Err(format!(
+            "Invalid parameter name '{}' in placeholder '{}{}{}'",
+            name, PREFIX, body, SUFFIX
+        ));
+    }
+
+    let Some(type_and_default) = type_and_default else {
+        return Ok(ParameterSpec {
+            name: name.to_string(),
+            kind: ParameterType::String,
+            default: None,
+        });
+    };
+
+    // The enum choice list may itself contain '=' and parentheses, so split the default off
+    // after the ')' that closes it
+    let type_end = if type_and_default.trim_start().starts_with("enum(") {
+        closing_paren(type_and_default)
+            .map(|i| i + 1)
+            .ok_or_else(|| {
+                format!(
+                    "Unterminated choice list in placeholder '{}{}{}'",
+                    PREFIX, body, SUFFIX
+                )
+            })?
+    } else {
+        type_and_default.find('=').unwrap_or(type_and_default.len())
+    };
+    let type_name = type_and_default[..type_end].trim();
+    let default = match type_and_default[type_end..].strip_prefix('=') {
+        Some(default) => Some(default.to_string()),
+        None if type_and_default[type_end..].trim().is_empty() => None,
+        None => {
+            return Err(format!(
+                "Unexpected text after the type in placeholder '{}{}{}'",
+                PREFIX, body, SUFFIX
+            ))
+        }
+    };
+
+    let kind = match type_name {
+        "" | "string" => ParameterType::String,
+        "int" => ParameterType::Int,
+        "bool" => ParameterType::Bool,
+        "path" => ParameterType::Path,
+        enum_type if enum_type.starts_with("enum(") => {
+            let choices: Vec<String> = enum_type["enum(".len()..enum_type.len() - 1]
+                .split('|')
+                .map(|choice| choice.trim().to_string())
+                .filter(|choice| !choice.is_empty())
+                .collect();
+            if choices.is_empty() {
+                return Err(format!("Parameter '{}' has an empty choice list", name));
+            }
+            ParameterType::Enum { choices }
+        }
+        other => {
+            return Err(format!(
+                "Unknown type '{}' for parameter '{}' (expected {})",
+                other, name, TYPE_NAMES
+            ))
+        }
+    };
+
+    let spec = ParameterSpec {
+        name: name.to_string(),
+        kind,
+        default,
+    };
+    if let Some(default) = &spec.default {
+        validate(&spec, default)
+            .map_err(|e| format!("Invalid default for parameter '{}': {}", name, e))?;
+    }
+    Ok(spec)
+}
+
+fn merge_spec(
+    parameters: &mut Vec<ParameterSpec>,
+    spec: ParameterSpec,
+    body: &str,
+) -> Result<(), String> {
+    let Some(existing) = parameters.iter_mut().find(|p| p.name == spec.name) else {
+        parameters.push(spec);
+        return Ok(());
+    };
+    // A bare `{{param:name}}` refers to the occurrence that declares the type, wherever that is
+    if !body.contains(':') {
+        return Ok(());
+    }
+    if existing.kind == ParameterType::String && existing.default.is_none() {
+        *existing = spec;
+        return Ok(());
+    }
+    if existing.kind != spec.kind || existing.default != spec.default {
+        return Err(format!(
+            "Parameter '{}' is declared more than once with different types or defaults",
+            spec.name
+        ));
+    }
+    Ok(())
+}
+
+// Parameter names may contain '-', which environment variable names may not
+fn param_var_name(name: &str) -> String {
+    format!("{}{}", PARAM_VAR_PREFIX, name.replace('-', "_"))
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Check `value` against the parameter type and return the text to splice into a shell command
+fn validate(spec: &ParameterSpec, value: &str) -> Result<String, String> {
+    let value = normalize(spec, value)?;
+    match spec.kind {
+        ParameterType::Int | ParameterType::Bool => Ok(value),
+        _ => Ok(shell_quote(&value)),
+    }
+}
+
+// Check `value` against the parameter type and return it in canonical form
+fn normalize(spec: &ParameterSpec, value: &str) -> Result<String, String> {
+    match &spec.kind {
+        ParameterType::String => Ok(value.to_string()),
+        ParameterType::Int => value
+            .trim()
+            .parse::<i64>()
+            .map(|n| n.to_string())
+            .map_err(|_| format!("'{}' is not an integer (parameter '{}')", value, spec.name)),
+        ParameterType::Bool => match value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" | "on" => Ok("true".to_string()),
+            "false" | "no" | "0" | "off" => Ok("false".to_string()),
+            _ => Err(format!(
+                "'{}' is not a boolean (parameter '{}')",
+                value, spec.name
+            )),
+        },
+        ParameterType::Path => {
+            if value.trim().is_empty() {
+                return Err(format!("Parameter '{}' needs a path", spec.name));
+            }
+            // Quoting stops the shell from expanding `~`, so do it here
+            Ok(match value.strip_prefix("~/") {
+                Some(rest) => format!("{}/{}", super::resolve_home(), rest),
+                None if value == "~" => super::resolve_home(),
+                None => value.to_string(),
+            })
+        }
+        ParameterType::Enum { choices } => {
+            if choices.iter().any(|choice| choice == value) {
+                Ok(value.to_string())
+            } else {
+                Err(format!(
+                    "'{}' is not one of {} (parameter '{}')",
+                    value,
+                    choices.join(", "),
+                    spec.name
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(command: &str, provided: &[(&str, &str)]) -> Result<String, String> {
+        let provided = provided
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let rendered = Template::parse(command)?.render(&provided, &HashMap::new(), None)?;
+        Ok(rendered.command)
+    }
+
+    #[test]
+    fn keeps_other_templates_verbatim() {
+        let command =
+            "kubectl get pods -o go-template='{{range .items}}{{.metadata.name}}{{end}}' \
+                       && echo '{{ else }}' '{{name}}' '{{ .Values.image }}' '{{'";
+        assert!(Template::parse(command).unwrap().parameters().is_empty());
+        assert_eq!(render(command, &[]).unwrap(), command);
+    }
+
+    #[test]
+    fn mixes_placeholders_with_literal_braces() {
+        let command = "echo '{{end}}' {{param:who}} '}}'";
+        assert_eq!(
+            render(command, &[("who", "world")]).unwrap(),
+            "echo '{{end}}' world '}}'"
+        );
+    }
+
+    #[test]
+    fn parses_types_and_defaults() {
+        let template = Template::parse(
+            "{{param:a}} {{param:b:int=3}} {{param:c:bool}} {{param:d:path=/tmp}} {{param:e:string=x y}}",
+        )
+        .unwrap();
+        let specs: Vec<_> = template
+            .parameters()
+            .iter()
+            .map(|spec| {
+                (
+                    spec.name.as_str(),
+                    spec.kind.clone(),
+                    spec.default.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            specs,
+            vec![
+                ("a", ParameterType::String, None),
+                ("b", ParameterType::Int, Some("3")),
+                ("c", ParameterType::Bool, None),
+                ("d", ParameterType::Path, Some("/tmp")),
+                ("e", ParameterType::String, Some("x y")),
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_choices_and_default_may_contain_equals() {
+        let command = "run {{param:opt:enum(a=1|b=2)=b=2}}";
+        let template = Template::parse(command).unwrap();
+        let spec = &template.parameters()[0];
+        assert_eq!(
+            spec.kind,
+            ParameterType::Enum {
+                choices: vec!["a=1".to_string(), "b=2".to_string()]
+            }
+        );
+        assert_eq!(spec.default.as_deref(), Some("b=2"));
+        assert_eq!(render(command, &[]).unwrap(), "run b=2");
+        assert_eq!(render(command, &[("opt", "a=1")]).unwrap(), "run a=1");
+        assert!(render(command, &[("opt", "c")]).is_err());
+    }
+
+    #[test]
+    fn enum_choices_may_contain_parentheses() {
+        let template = Template::parse("run {{param:opt:enum(f(x)|(none)|a (b))=(none)}}").unwrap();
+        let spec = &template.parameters()[0];
+        assert_eq!(
+            spec.kind,
+            ParameterType::Enum {
+                choices: vec![
+                    "f(x)".to_string(),
+                    "(none)".to_string(),
+                    "a (b)".to_string()
+                ]
+            }
+        );
+        assert_eq!(spec.default.as_deref(), Some("(none)"));
+    }
+
+    #[test]
+    fn rejects_malformed_placeholders() {
+        assert!(Template::parse("{{param:env:enum(a|b}}").is_err());
+        assert!(Template::parse("{{param:env:enum(a|(b)}}").is_err());
+        assert!(Template::parse("{{param:env:enum(a)|b)}}").is_err());
+        assert!(Template::parse("{{param:env:enum()}}").is_err());
+        assert!(Template::parse("{{param:env:enum(a|b) x}}").is_err());
+        assert!(Template::parse("{{param:n:float}}").is_err());
+        assert!(Template::parse("{{param:1n}}").is_err());
+        assert!(Template::parse("{{param:}}").is_err());
+        assert!(Template::parse("echo {{param:n").is_err());
+    }
+
+    #[test]
+    fn repeated_names_share_one_parameter() {
+        let command = "{{param:n}} {{param:n:int=1}} {{param:n}}";
+        let template = Template::parse(command).unwrap();
+        assert_eq!(template.parameters().len(), 1);
+        assert_eq!(template.parameters()[0].kind, ParameterType::Int);
+        assert_eq!(render(command, &[]).unwrap(), "1 1 1");
+        assert_eq!(render(command, &[("n", "7")]).unwrap(), "7 7 7");
+        assert!(Template::parse("{{param:n:int=1}} {{param:n:int=1}}").is_ok());
+    }
+
+    #[test]
+    fn rejects_conflicting_declarations() {
+        assert!(Template::parse("{{param:n:int=1}} {{param:n:int=2}}").is_err());
+        assert!(Template::parse("{{param:n:int}} {{param:n:bool}}").is_err());
+        assert!(Template::parse("{{param:n:int=1}} {{param:n:int}}").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_defaults() {
+        assert!(Template::parse("{{param:n:int=many}}").is_err());
+        assert!(Template::parse("{{param:n:bool=maybe}}").is_err());
+        assert!(Template::parse("{{param:n:enum(a|b)=c}}").is_err());
+        assert!(Template::parse("{{param:n:path=}}").is_err());
+    }
+
+    #[test]
+    fn takes_values_in_order_of_precedence() {
+        let template = Template::parse("{{param:n:string=default}}").unwrap();
+        let provided = HashMap::from([("n".to_string(), "provided".to_string())]);
+        let remembered = HashMap::from([("n".to_string(), "remembered".to_string())]);
+        let none = HashMap::new();
+        assert_eq!(
+            template
+                .render(&provided, &remembered, None)
+                .unwrap()
+                .command,
+            "provided"
+        );
+        assert_eq!(
+            template.render(&none, &remembered, None).unwrap().command,
+            "remembered"
+        );
+        assert_eq!(
+            template.render(&none, &none, None).unwrap().command,
+            "default"
+        );
+
+        let used = template.render(&none, &remembered, None).unwrap().used;
+        assert_eq!(used.get("n").map(String::as_str), Some("remembered"));
+    }
+
+    #[test]
+    fn requires_a_value_without_default() {
+        let error = render("echo {{param:n}}", &[]).unwrap_err();
+        assert!(
+            error.contains("Missing value for parameter 'n'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn validates_and_normalises_values() {
+        let spec = |kind| ParameterSpec {
+            name: "n".to_string(),
+            kind,
+            default: None,
+        };
+        assert_eq!(validate(&spec(ParameterType::Int), " 42 ").unwrap(), "42");
+        assert_eq!(validate(&spec(ParameterType::Int), "-3").unwrap(), "-3");
+        assert!(validate(&spec(ParameterType::Int), "4.2").is_err());
+        assert!(validate(&spec(ParameterType::Int), "1; rm -rf /").is_err());
+        assert_eq!(validate(&spec(ParameterType::Bool), "Yes").unwrap(), "true");
+        assert_eq!(
+            validate(&spec(ParameterType::Bool), "off").unwrap(),
+            "false"
+        );
+        assert!(validate(&spec(ParameterType::Bool), "maybe").is_err());
+        assert_eq!(
+            validate(&spec(ParameterType::String), "a b").unwrap(),
+            "'a b'"
+        );
+        assert_eq!(
+            validate(&spec(ParameterType::Path), "~/notes.txt").unwrap(),
+            shell_quote(&format!("{}/notes.txt", crate::executor::resolve_home()))
+        );
+        assert!(validate(&spec(ParameterType::Path), " ").is_err());
+    }
+
+    #[test]
+    fn passes_text_to_other_interpreters_through_the_environment() {
+        let template = Template::parse(
+            "f({{param:who-am-i}}, {{param:n:int=3}}, {{param:on:bool=yes}}, {{param:e:enum(a|b)=b}})",
+        )
+        .unwrap();
+        let provided = HashMap::from([("who-am-i".to_string(), "it's \"me\"".to_string())]);
+        let render = |interpreter| {
+            let rendered = template
+                .render(&provided, &HashMap::new(), Some(interpreter))
+                .unwrap();
+            let env: Vec<_> = rendered
+                .env
+                .into_iter()
+                .map(|var| (var.name, var.value))
+                .collect();
+            (rendered.command, env)
+        };
+        let env = vec![
+            ("SSM_PARAM_who_am_i".to_string(), "it's \"me\"".to_string()),
+            ("SSM_PARAM_e".to_string(), "b".to_string()),
+        ];
+
+        assert_eq!(
+            render(Interpreter::Python),
+            (
+                r#"f(__import__("os").environ["SSM_PARAM_who_am_i"], 3, True, __import__("os").environ["SSM_PARAM_e"])"#
+                    .to_string(),
+                env.clone()
+            )
+        );
+        assert_eq!(
+            render(Interpreter::Node),
+            (
+                "f(process.env.SSM_PARAM_who_am_i, 3, true, process.env.SSM_PARAM_e)".to_string(),
+                env
+            )
+        );
+        assert_eq!(
+            render(Interpreter::Fish),
+            (r#"f('it'\''s "me"', 3, true, b)"#.to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn quotes_for_the_shell() {
+        assert_eq!(shell_quote("plain-word_1.txt"), "plain-word_1.txt");
+        assert_eq!(shell_quote("key=value"), "key=value");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+        assert_eq!(shell_quote("a\nb"), "'a\nb'");
+    }
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::repository::run_config_repository::RunConfigRepository;
+use crate::environment::{self, ActiveProfile};
+use crate::secrets::{self, InjectedSecrets};
+
+use super::interpreter::{self, Interpreter};
+use super::placeholders::shell_quote;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+/// Where a script runs and which environment variables it gets on top of the login shell's.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunConfig {
+    /// May contain `~` and `$VAR` / `${VAR}`, expanded right before each run.
+    pub working_dir: Option<String>,
+    pub env: Vec<EnvVar>,
+}
+
+impl RunConfig {
+    /// Load the stored configuration of `script_id`; ad-hoc commands get the defaults.
+    pub async fn load(script_id: Option<i32>) -> Result<Self, String> {
+        let Some(script_id) = script_id else {
+            return Ok(Self::default());
+        };
+        let repository = RunConfigRepository::new();
+        let working_dir = repository
+            .get_working_dir(script_id)
+            .await
+            .map_err(|e| format!("Failed to load working directory: {}", e))?;
+        let env = repository
+            .get_env_vars(script_id)
+            .await
+            .map_err(|e| format!("Failed to load environment variables: {}", e))?
+            .into_iter()
+            .map(|var| EnvVar {
+                name: var.name,
+                value: var.value,
+            })
+            .collect();
+        Ok(Self { working_dir, env })
+    }
+
+    /// The configuration a run of `script_id` actually uses: the active environment profile's
+    /// variables, then the workspace's, then the script's own, each overriding the previous.
+    /// Also carries the script's interpreter.
+    pub async fn load_for_run(script_id: Option<i32>) -> Result<ResolvedRunConfig, String> {
+        let (config, profile) = Self::load_merged(script_id).await?;
+        let mut resolved = config.resolve(profile)?;
+        resolved.interpreter = interpreter::configured(script_id).await?;
+        Ok(resolved)
+    }
+
+    /// Like [`Self::load_for_run`] for a run on another machine: the variables and the
+    /// interpreter are the same, the local working directory does not apply there.
+    pub async fn load_for_remote_run(script_id: Option<i32>) -> Result<ResolvedRunConfig, String> {
+        let (mut config, profile) = Self::load_merged(script_id).await?;
+        config.working_dir = None;
+        let mut resolved = config.resolve(profile)?;
+        resolved.interpreter = interpreter::configured(script_id).await?;
+        Ok(resolved)
+    }
+
+    async fn load_merged(script_id: Option<i32>) -> Result<(Self, Option<ActiveProfile>), String> {
+        let script_config = Self::load(script_id).await?;
+        let Some(script_id) = script_id else {
+            return Ok((script_config, None));
+        };
+
+        let inherited = environment::inherited_environment(script_id).await?;
+        let mut env = inherited.vars;
+        environment::merge_env_vars(&mut env, script_config.env);
+        let merged = Self {
+            working_dir: script_config.working_dir,
+            env,
+        };
+        Ok((merged, inherited.profile))
+    }
+
+    pub async fn save(&self, script_id: i32) -> Result<(), String> {
+        validate_env_vars(&self.env)?;
+        let working_dir = self
+            .working_dir
+            .as_ref()
+            .map(|dir| dir.trim().to_string())
+            .filter(|dir| !dir.is_empty());
+        let env_vars: Vec<(String, String)> = self
+            .env
+            .iter()
+            .map(|var| (var.name.clone(), var.value.clone()))
+            .collect();
+        RunConfigRepository::new()
+            .save(script_id, working_dir, &env_vars)
+            .await
+            .map_err(|e| format!("Failed to save run configuration: {}", e))
+    }
+
+    /// Expand `~` and variables in the working directory and check that it exists. Done right
+    /// before each run so the directory reflects the environment at that moment.
+    fn resolve(&self, profile: Option<ActiveProfile>) -> Result<ResolvedRunConfig, String> {
+        let working_dir = match &self.working_dir {
+            Some(working_dir) => {
+                let overrides: HashMap<&str, &str> = self
+                    .env
+                    .iter()
+                    .map(|var| (var.name.as_str(), var.value.as_str()))
+                    .collect();
+                let expanded = expand_path(working_dir, |name| {
+                    overrides
+                        .get(name)
+                        .map(|value| value.to_string())
+                        .or_else(|| std::env::var(name).ok())
+                })?;
+                if !std::path::Path::new(&expanded).is_dir() {
+                    return Err(format!("Working directory '{}' does not exist", expanded));
+                }
+                Some(expanded)
+            }
+            None => None,
+        };
+        Ok(ResolvedRunConfig {
+            working_dir,
+            env: self.env.clone(),
+            secrets: InjectedSecrets::default(),
+            profile,
+            interpreter: None,
+        })
+    }
+}
+
+/// A [`RunConfig`] whose working directory has been expanded and checked.
+#[derive(Clone, Debug, Default)]
+pub struct ResolvedRunConfig {
+    pub working_dir: Option<String>,
+    pub env: Vec<EnvVar>,
+    /// Filled by [`Self::inject_secrets`] right before the run starts.
+    pub secrets: InjectedSecrets,
+    /// The environment profile the variables came from, if any.
+    pub profile: Option<ActiveProfile>,
+    /// The interpreter set on the script; `None` follows the shebang line or uses the login
+    /// shell.
+    pub interpreter: Option<Interpreter>,
+}
+
+impl ResolvedRunConfig {
+    /// Apply the working directory and env var overrides to a process we spawn ourselves.
+    pub fn apply(&self, cmd: &mut tokio::process::Command) {
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(dir);
+        }
+        for var in self.env.iter().chain(&self.secrets.env) {
+            cmd.env(&var.name, &var.value);
+        }
+    }
+
+    /// Resolve the `${secret:NAME}` references of `command` and of the env vars, see
+    /// [`secrets::inject`]. References in the command read the injected variables in the syntax
+    /// of the interpreter that runs it. Returns the command to hand to the shell.
+    pub async fn inject_secrets(&mut self, command: &str) -> Result<String, String> {
+        let interpreter = interpreter::resolve(self.interpreter, command)?;
+        let (command, secrets) = secrets::inject(command, &mut self.env, |name| {
+            interpreter::env_reference(interpreter, name)
+        })
+        .await?;
+        self.secrets = secrets;
+        Ok(command)
+    }
+
+    /// The directory a silent run executes in, recorded alongside it in the history.
+    pub fn effective_working_dir(&self) -> Option<String> {
+        self.working_dir.clone().or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|p| p.to_string_lossy().to_string())
+        })
+    }
+
+    /// Shell lines that recreate this configuration inside a terminal window, where the command
+    /// runs in a shell we do not spawn directly. Secrets are only included once staged with
+    /// [`InjectedSecrets::stage_env_file`].
+    pub fn shell_prelude(&self) -> String {
+        let mut prelude = String::new();
+        if let Some(dir) = &self.working_dir {
+            prelude.push_str(&format!("cd {} || exit 1\n", shell_quote(dir)));
+        }
+        for var in &self.env {
+            prelude.push_str(&format!(
+                "export {}={}\n",
+                var.name,
+                shell_quote(&var.value)
+            ));
+        }
+        prelude.push_str(&self.secrets.shell_prelude());
+        prelude
+    }
+}
+
+/// Expand a leading `~` and every `$VAR` / `${VAR}` in `value`. Unset variables are an error
+/// rather than silently becoming empty, which would turn `$PROJECTS/app` into `/app`.
+pub fn expand_path<F>(value: &str, lookup: F) -> Result<String, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let value = value.trim();
+    let mut expanded = String::new();
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&super::resolve_home());
+        rest = &rest[1..];
+    }
+
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let (name, braced) = if rest[i + 1..].starts_with('{') {
+            let end = rest[i + 2..]
+                .find('}')
+                .ok_or_else(|| format!("Unterminated '${{' in '{}'", value))?;
+            (&rest[i + 2..i + 2 + end], true)
+        } else {
+            let len = rest[i + 1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len() - i - 1);
+            (&rest[i + 1..i + 1 + len], false)
+        };
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let resolved = lookup(name).ok_or_else(|| {
+            format!(
+                "Environment variable '{}' used in '{}' is not set",
+                name, value
+            )
+        })?;
+        expanded.push_str(&resolved);
+
+        let skip = name.chars().count() + if braced { 2 } else { 0 };
+        for _ in 0..skip {
+            chars.next();
+        }
+    }
+    Ok(expanded)
+}
+
+pub fn validate_env_vars(vars: &[EnvVar]) -> Result<(), String> {
+    for var in vars {
+        if !is_valid_env_name(&var.name) {
+            return Err(format!(
+                "'{}' is not a valid environment variable name",
+                var.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Instant;
+use tauri::{Emitter, EventTarget};
+use tokio::task::JoinSet;
+
+use crate::backup;
+use crate::db::repository::host_group_repository::HostGroupRepository;
+use crate::executor::history::ExecutionMode;
+use crate::executor::limits::LimitExceeded;
+use crate::executor::run_config::EnvVar;
+use crate::executor::{self, ScriptOutputEvent, SilentRun, SCRIPT_OUTPUT_EVENT};
+use crate::jobs::{self, JobState};
+use crate::ssh::{self, SshTarget};
+use crate::RT_HANDLE;
+
+/// Emitted whenever a host changes state; output of the host itself arrives as `script-output`
+/// events carrying the host's `runId`.
+pub const FAN_OUT_HOST_EVENT: &str = "fan-out-host";
+/// Emitted exactly once when every host has finished.
+pub const FAN_OUT_FINISHED_EVENT: &str = "fan-out-finished";
+
+const DEFAULT_MAX_PARALLEL: i32 = 8;
+const MAX_PARALLEL_LIMIT: i32 = 64;
+const MAX_TIMEOUT_SECONDS: i32 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostGroup {
+    /// `None` when saving a new group.
+    pub id: Option<i32>,
+    pub name: String,
+    /// How many hosts run at the same time.
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: i32,
+    /// How long each host may take; only the script's own timeout applies when not set.
+    pub timeout_seconds: Option<i32>,
+    /// SSH target ids, in the order results are listed.
+    pub target_ids: Vec<i32>,
+}
+
+fn default_max_parallel() -> i32 {
+    DEFAULT_MAX_PARALLEL
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HostStatus {
+    Started,
+    Succeeded,
+    Failed,
+    TimedOut,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostResult {
+    pub target_id: i32,
+    pub target_name: String,
+    pub status: HostStatus,
+    /// Links the host to its `historical_shell_script` row.
+    pub run_id: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    /// Why the run could not be started, that it was cancelled, or that it timed out.
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub timed_out: usize,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutHostEvent {
+    pub fan_out_run_id: String,
+    pub group_id: i32,
+    pub script_id: i32,
+    #[serde(flatten)]
+    pub host: HostResult,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutFinishedEvent {
+    pub fan_out_run_id: String,
+    pub group_id: i32,
+    pub script_id: i32,
+    pub duration_ms: u64,
+    pub summary: FanOutSummary,
+    /// One entry per host of the group, in the group's order.
+    pub hosts: Vec<HostResult>,
+}
+
+pub async fn list_groups() -> Result<Vec<HostGroup>, String> {
+    let groups = HostGroupRepository::new()
+        .get_all_groups()
+        .await
+        .map_err(|e| format!("Failed to load host groups: {}", e))?;
+    Ok(groups.into_iter().map(to_group).collect())
+}
+
+pub async fn save_group(group: HostGroup) -> Result<HostGroup, String> {
+    let name = group.name.trim().to_string();
+    if name.is_empty() {
+        return Err("Host group name must not be empty".to_string());
+    }
+    if !(1..=MAX_PARALLEL_LIMIT).contains(&group.max_parallel) {
+        return Err(format!(
+            "Parallelism must be between 1 and {}",
+            MAX_PARALLEL_LIMIT
+        ));
+    }
+    if let Some(seconds) = group.timeout_seconds {
+        if !(1..=MAX_TIMEOUT_SECONDS).contains(&seconds) {
+            return Err(format!(
+                "Timeout must be between 1 and {} seconds",
+                MAX_TIMEOUT_SECONDS
+            ));
+        }
+    }
+    let mut seen = HashSet::new();
+    for target_id in &group.target_ids {
+        if !seen.insert(*target_id) {
+            return Err(format!(
+                "SSH target {} is in the group more than once",
+                target_id
+            ));
+        }
+    }
+
+    let repository = HostGroupRepository::new();
+    let group_id = repository
+        .save_group(
+            group.id,
+            name,
+            group.max_parallel,
+            group.timeout_seconds,
+            &group.target_ids,
+        )
+        .await
+        .map_err(|e| format!("Failed to save host group: {}", e))?;
+    repository
+        .get_group(group_id)
+        .await
+        .map_err(|e| format!("Failed to load host group {}: {}", group_id, e))?
+        .map(to_group)
+        .ok_or_else(|| format!("Host group {} not found", group_id))
+}
+
+pub async fn delete_group(group_id: i32) -> Result<(), String> {
+    HostGroupRepository::new()
+        .delete_group(group_id)
+        .await
+        .map_err(|e| format!("Failed to delete host group {}: {}", group_id, e))
+}
+
+/// Start running `script_id` on every host of the group, reporting progress to the webview
+/// window `label`. `max_parallel` and `timeout_seconds` override what is stored with the group.
+/// Returns the id of the fan-out run, which every host and finished event carries.
+pub async fn start_fan_out(
+    app: tauri::AppHandle,
+    label: String,
+    script_id: i32,
+    group_id: i32,
+    max_parallel: Option<i32>,
+    timeout_seconds: Option<i32>,
+) -> Result<String, String> {
+    let group = HostGroupRepository::new()
+        .get_group(group_id)
+        .await
+        .map_err(|e| format!("Failed to load host group {}: {}", group_id, e))?
+        .ok_or_else(|| format!("Host group {} not found", group_id))?;
+    let group = to_group(group);
+    if group.target_ids.is_empty() {
+        return Err(format!("Host group '{}' has no hosts", group.name));
+    }
+    let mut targets = Vec::with_capacity(group.target_ids.len());
+    for target_id in &group.target_ids {
+        targets.push(ssh::load_target(*target_id).await?);
+    }
+    // Resolved once so that every host runs exactly the same command
+    let (command, env) = executor::stored_command(script_id).await?;
+    let max_parallel = max_parallel
+        .unwrap_or(group.max_parallel)
+        .clamp(1, MAX_PARALLEL_LIMIT) as usize;
+    let timeout_seconds = timeout_seconds
+        .or(group.timeout_seconds)
+        .map(|seconds| seconds.clamp(1, MAX_TIMEOUT_SECONDS) as u64);
+
+    let handle = RT_HANDLE
+        .get()
+        .ok_or_else(|| "Runtime not initialized".to_string())?
+        .clone();
+    let fan_out_run_id = executor::new_run_id();
+    let run = FanOutRun {
+        app,
+        target: EventTarget::webview_window(label),
+        group_id,
+        script_id,
+        fan_out_run_id: fan_out_run_id.clone(),
+        command,
+        env,
+        timeout_seconds,
+    };
+    println!(
+        "Running script {} on host group '{}' ({} hosts, up to {} at a time) as {}",
+        script_id,
+        group.name,
+        targets.len(),
+        max_parallel,
+        fan_out_run_id
+    );
+    handle.clone().spawn(async move {
+        let _database = backup::use_database().await;
+        run.execute(targets, max_parallel, handle).await;
+    });
+    Ok(fan_out_run_id)
+}
+
+#[derive(Clone)]
+struct FanOutRun {
+    app: tauri::AppHandle,
+    target: EventTarget,
+    group_id: i32,
+    script_id: i32,
+    fan_out_run_id: String,
+    command: String,
+    env: Vec<EnvVar>,
+    timeout_seconds: Option<u64>,
+}
+
+impl FanOutRun {
+    async fn execute(
+        self,
+        targets: Vec<SshTarget>,
+        max_parallel: usize,
+        handle: tokio::runtime::Handle,
+    ) {
+        let started = Instant::now();
+        let total = targets.len();
+        let mut pending = targets.into_iter().enumerate();
+        let mut results: Vec<Option<HostResult>> = vec![None; total];
+        let mut running = JoinSet::new();
+        loop {
+            while running.len() < max_parallel {
+                let Some((index, host)) = pending.next() else {
+                    break;
+                };
+                let run = self.clone();
+                running.spawn_on(async move { (index, run.run_host(host).await) }, &handle);
+            }
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                // The host is left out of the results below
+                Err(e) => eprintln!("Fan-out host task failed: {}", e),
+            }
+        }
+
+        let hosts: Vec<HostResult> = results.into_iter().flatten().collect();
+        let mut summary = FanOutSummary {
+            total,
+            ..FanOutSummary::default()
+        };
+        for host in &hosts {
+            match host.status {
+                HostStatus::Succeeded => summary.succeeded += 1,
+                HostStatus::TimedOut => summary.timed_out += 1,
+                HostStatus::Failed | HostStatus::Started => summary.failed += 1,
+            }
+        }
+        // Hosts whose task died count as failed
+        summary.failed += total - hosts.len();
+        println!(
+            "Fan-out {} finished: {} succeeded, {} failed, {} timed out",
+            self.fan_out_run_id, summary.succeeded, summary.failed, summary.timed_out
+        );
+
+        let finished = FanOutFinishedEvent {
+            fan_out_run_id: self.fan_out_run_id.clone(),
+            group_id: self.group_id,
+            script_id: self.script_id,
+            duration_ms: started.elapsed().as_millis() as u64,
+            summary,
+            hosts,
+        };
+        if let Err(e) = self
+            .app
+            .emit_to(self.target.clone(), FAN_OUT_FINISHED_EVENT, finished)
+        {
+            eprintln!("Failed to emit {}: {}", FAN_OUT_FINISHED_EVENT, e);
+        }
+    }
+
+    async fn run_host(self, host: SshTarget) -> HostResult {
+        let started = Instant::now();
+        let run_id = executor::new_run_id();
+        let result = HostResult {
+            target_id: host.id,
+            target_name: host.name.clone(),
+            status: HostStatus::Started,
+            run_id: run_id.clone(),
+            exit_code: None,
+            duration_ms: 0,
+            error: None,
+        };
+        self.emit_host(result.clone());
+
+        let run = SilentRun {
+            run_id: run_id.clone(),
+            command: self.command.clone(),
+            script_id: Some(self.script_id),
+            mode: ExecutionMode::FanOut,
+            stdin: None,
+            interactive_stdin: false,
+            env: self.env.clone(),
+            ssh_target_id: Some(host.id),
+            timeout_seconds: self.timeout_seconds,
+        };
+        let outcome = executor::run_silent(run, |stream, line| {
+            let event = ScriptOutputEvent {
+                run_id: run_id.clone(),
+                stream,
+                line,
+            };
+            if let Err(e) = self
+                .app
+                .emit_to(self.target.clone(), SCRIPT_OUTPUT_EVENT, event)
+            {
+                eprintln!("Failed to emit {}: {}", SCRIPT_OUTPUT_EVENT, e);
+            }
+        })
+        .await;
+        let result = match outcome {
+            Ok(outcome) => {
+                let cancelled =
+                    jobs::get(&run_id).is_ok_and(|info| info.state == JobState::Cancelled);
+                let (status, error) = match outcome.limit_exceeded {
+                    // Only hosts with `timeout` stop the script themselves, see ssh::spawn_job
+                    Some(LimitExceeded::Timeout { seconds }) => (
+                        HostStatus::TimedOut,
+                        Some(format!(
+                            "Timed out after {}s; unless the host has the timeout command the \
+                             script may still be running there",
+                            seconds
+                        )),
+                    ),
+                    _ if outcome.success => (HostStatus::Succeeded, None),
+                    _ => (HostStatus::Failed, None),
+                };
+                let error = error.or_else(|| cancelled.then(|| "Cancelled".to_string()));
+                HostResult {
+                    status,
+                    exit_code: outcome.exit_code,
+                    error,
+                    ..result
+                }
+            }
+            Err(e) => {
+                eprintln!("Fan-out to {} could not be started: {}", host.address(), e);
+                HostResult {
+                    status: HostStatus::Failed,
+                    error: Some(e),
+                    ..result
+                }
+            }
+        };
+        let result = HostResult {
+            duration_ms: started.elapsed().as_millis() as u64,
+            ..result
+        };
+        self.emit_host(result.clone());
+        result
+    }
+
+    fn emit_host(&self, host: HostResult) {
+        let event = FanOutHostEvent {
+            fan_out_run_id: self.fan_out_run_id.clone(),
+            group_id: self.group_id,
+            script_id: self.script_id,
+            host,
+        };
+        if let Err(e) = self
+            .app
+            .emit_to(self.target.clone(), FAN_OUT_HOST_EVENT, event)
+        {
+            eprintln!("Failed to emit {}: {}", FAN_OUT_HOST_EVENT, e);
+        }
+    }
+}
+
+fn to_group(group: crate::prisma::ssh_host_group::Data) -> HostGroup {
+    HostGroup {
+        id: Some(group.id),
+        name: group.name,
+        max_parallel: group.max_parallel,
+        timeout_seconds: group.timeout_seconds,
+        target_ids: group
+            .members
+            .unwrap_or_default()
+            .into_iter()
+            .map(|member| member.ssh_target_id)
+            .collect(),
+    }
+}
@@ -0,0 +1,321 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::watch;
+
+use crate::JOB_REGISTRY;
+
+/// How long a cancelled job gets to exit after SIGTERM before its process group is SIGKILLed.
+pub const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Finished jobs are kept around so `wait_job` can still report them; beyond this count the
+/// oldest finished entries are dropped.
+const MAX_FINISHED_JOBS: usize = 50;
+
+pub type JobRegistry = Arc<Mutex<HashMap<String, Job>>>;
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub run_id: String,
+    pub command: String,
+    pub pid: Option<u32>,
+    /// Epoch milliseconds, same unit as the `created_at` columns in the database.
+    pub started_at: f64,
+    pub state: JobState,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    /// Whether `write_stdin` can still reach the process.
+    pub stdin_open: bool,
+}
+
+pub struct Job {
+    status: watch::Sender<JobInfo>,
+    cancel_requested: bool,
+    // Async lock so a write waiting on a full pipe does not hold up the registry
+    stdin: Option<Arc<tokio::sync::Mutex<ChildStdin>>>,
+}
+
+pub fn registry() -> Result<&'static JobRegistry, String> {
+    JOB_REGISTRY
+        .get()
+        .ok_or_else(|| "Job registry not initialized".to_string())
+}
+
+/// Epoch milliseconds, the unit of every timestamp column in the database.
+pub fn now_ms() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or(0.0)
+}
+
+/// Put the child in its own process group so that cancelling can signal every process the
+/// login shell spawns, not just the shell itself.
+pub fn isolate_process_group(cmd: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Record a freshly spawned process under `run_id`. A run id that is still running is rejected
+/// so two processes can never share one handle.
+pub fn register(run_id: &str, command: &str, pid: Option<u32>) -> Result<(), String> {
+    let mut jobs = registry()?.lock().unwrap();
+    if let Some(existing) = jobs.get(run_id) {
+        if existing.status.borrow().state == JobState::Running {
+            return Err(format!("A job with run id '{}' is already running", run_id));
+        }
+    }
+
+    let (status, _) = watch::channel(JobInfo {
+        run_id: run_id.to_string(),
+        command: command.to_string(),
+        pid,
+        started_at: now_ms(),
+        state: JobState::Running,
+        exit_code: None,
+        duration_ms: None,
+        stdin_open: false,
+    });
+    jobs.insert(
+        run_id.to_string(),
+        Job {
+            status,
+            cancel_requested: false,
+            stdin: None,
+        },
+    );
+    prune_finished(&mut jobs);
+    Ok(())
+}
+
+/// Mark a job as finished. A job that was cancelled keeps the `CANCELLED` state regardless of
+/// how the process group ended up exiting.
+pub fn finish(run_id: &str, exit_code: Option<i32>, success: bool, duration: Duration) {
+    let Ok(registry) = registry() else {
+        return;
+    };
+    let mut jobs = registry.lock().unwrap();
+    if let Some(job) = jobs.get_mut(run_id) {
+        job.stdin = None;
+        let state = if job.cancel_requested {
+            JobState::Cancelled
+        } else if success {
+            JobState::Succeeded
+        } else {
+            JobState::Failed
+        };
+        job.status.send_modify(|info| {
+            info.state = state;
+            info.exit_code = exit_code;
+            info.duration_ms = Some(duration.as_millis() as u64);
+            info.stdin_open = false;
+        });
+    }
+}
+
+/// Keep the stdin pipe of a registered job so input can be sent to it while it runs.
+pub fn attach_stdin(run_id: &str, stdin: ChildStdin) -> Result<(), String> {
+    let mut jobs = registry()?.lock().unwrap();
+    let job = jobs
+        .get_mut(run_id)
+        .ok_or_else(|| format!("No job with run id '{}'", run_id))?;
+    job.stdin = Some(Arc::new(tokio::sync::Mutex::new(stdin)));
+    job.status.send_modify(|info| info.stdin_open = true);
+    Ok(())
+}
+
+/// Write `data` to the stdin of a running job, then close it (the process sees EOF) if `close`.
+pub async fn write_stdin(run_id: &str, data: &[u8], close: bool) -> Result<(), String> {
+    let stdin = {
+        let jobs = registry()?.lock().unwrap();
+        let job = jobs
+            .get(run_id)
+            .ok_or_else(|| format!("No job with run id '{}'", run_id))?;
+        job.stdin
+            .clone()
+            .ok_or_else(|| format!("Job '{}' does not accept input", run_id))?
+    };
+
+    if !data.is_empty() {
+        let mut pipe = stdin.lock().await;
+        let written = match pipe.write_all(data).await {
+            Ok(()) => pipe.flush().await,
+            Err(e) => Err(e),
+        };
+        written.map_err(|e| format!("Failed to write to job '{}': {}", run_id, e))?;
+    }
+    if close {
+        close_stdin(run_id)?;
+    }
+    Ok(())
+}
+
+/// Close the stdin of a job so the process reads EOF. Writes still in flight finish first.
+pub fn close_stdin(run_id: &str) -> Result<(), String> {
+    let mut jobs = registry()?.lock().unwrap();
+    let job = jobs
+        .get_mut(run_id)
+        .ok_or_else(|| format!("No job with run id '{}'", run_id))?;
+    job.stdin = None;
+    job.status.send_modify(|info| info.stdin_open = false);
+    Ok(())
+}
+
+/// Send an initial payload to a job in the background, then close its stdin if `close` the way
+/// `command < file` would. Failures are logged: a process exiting without reading all of its
+/// input is not an error of the run.
+pub fn feed_stdin(run_id: &str, data: Vec<u8>, close: bool) {
+    let run_id = run_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = write_stdin(&run_id, &data, close).await {
+            eprintln!("{}", e);
+        }
+    });
+}
+
+/// The current state of one job.
+pub fn get(run_id: &str) -> Result<JobInfo, String> {
+    let jobs = registry()?.lock().unwrap();
+    jobs.get(run_id)
+        .map(|job| job.status.borrow().clone())
+        .ok_or_else(|| format!("No job with run id '{}'", run_id))
+}
+
+pub fn list() -> Result<Vec<JobInfo>, String> {
+    let jobs = registry()?.lock().unwrap();
+    let mut infos: Vec<JobInfo> = jobs
+        .values()
+        .map(|job| job.status.borrow().clone())
+        .collect();
+    infos.sort_by(|a, b| a.started_at.total_cmp(&b.started_at));
+    Ok(infos)
+}
+
+/// Send SIGTERM to the job's process group, escalating to SIGKILL if it is still running
+/// after `grace`.
+pub fn cancel(run_id: &str, grace: Duration) -> Result<(), String> {
+    let (pid, mut status) = {
+        let mut jobs = registry()?.lock().unwrap();
+        let job = jobs
+            .get_mut(run_id)
+            .ok_or_else(|| format!("No job with run id '{}'", run_id))?;
+        let info = job.status.borrow().clone();
+        if info.state != JobState::Running {
+            return Ok(());
+        }
+        job.cancel_requested = true;
+        let pid = info
+            .pid
+            .ok_or_else(|| format!("Job '{}' has no process id", run_id))?;
+        (pid, job.status.subscribe())
+    };
+
+    println!("Cancelling job {} (process group {})", run_id, pid);
+    signal_process_group(pid, false)?;
+
+    tauri::async_runtime::spawn(async move {
+        let exited = tokio::time::timeout(
+            grace,
+            status.wait_for(|info| info.state != JobState::Running),
+        )
+        .await;
+        if exited.is_err() {
+            println!(
+                "Process group {} did not exit within {:?}, sending SIGKILL",
+                pid, grace
+            );
+            if let Err(e) = signal_process_group(pid, true) {
+                eprintln!("{}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Resolve once the job has finished, or fail after `timeout` if one is given.
+pub async fn wait(run_id: &str, timeout: Option<Duration>) -> Result<JobInfo, String> {
+    let mut status = {
+        let jobs = registry()?.lock().unwrap();
+        jobs.get(run_id)
+            .ok_or_else(|| format!("No job with run id '{}'", run_id))?
+            .status
+            .subscribe()
+    };
+
+    let finished = status.wait_for(|info| info.state != JobState::Running);
+    let info = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, finished)
+            .await
+            .map_err(|_| format!("Timed out waiting for job '{}'", run_id))?,
+        None => finished.await,
+    }
+    .map_err(|_| format!("Job '{}' was removed before it finished", run_id))?;
+
+    Ok(info.clone())
+}
+
+/// Immediately SIGKILL a process group, used when a run breaks one of its execution limits.
+pub fn kill_process_group(pid: u32) -> Result<(), String> {
+    signal_process_group(pid, true)
+}
+
+fn prune_finished(jobs: &mut HashMap<String, Job>) {
+    let mut finished: Vec<(String, f64)> = jobs
+        .iter()
+        .filter_map(|(run_id, job)| {
+            let info = job.status.borrow();
+            (info.state != JobState::Running).then(|| (run_id.clone(), info.started_at))
+        })
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let excess = finished.len() - MAX_FINISHED_JOBS;
+    for (run_id, _) in finished.into_iter().take(excess) {
+        jobs.remove(&run_id);
+    }
+}
+
+#[cfg(unix)]
+fn signal_process_group(pid: u32, force: bool) -> Result<(), String> {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    // A negative pid addresses the whole process group led by `pid`.
+    let result = unsafe { libc::kill(-(pid as libc::pid_t), signal) };
+    if result == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        // The group is already gone
+        return Ok(());
+    }
+    Err(format!("Failed to signal process group {}: {}", pid, err))
+}
+
+#[cfg(not(unix))]
+fn signal_process_group(pid: u32, _force: bool) -> Result<(), String> {
+    // taskkill /T terminates the whole process tree
+    std::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .output()
+        .map_err(|e| format!("Failed to kill process tree {}: {}", pid, e))?;
+    Ok(())
+}
@@ -0,0 +1,239 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::executor::placeholders::shell_quote;
+
+// Scripts no terminal ever started are removed on a later launch
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+const CREATE_ATTEMPTS: usize = 8;
+
+/// A command written to its own executable file, for terminals and multiplexers that start a
+/// program rather than take our shell text. The command is written as is, on its own lines, so
+/// it never has to survive another round of quoting. The file is owner-only, lives in a
+/// directory only the current user can enter, and deletes itself as soon as it starts running.
+///
+/// Dropping it before [`Self::launched`] removes the file, so a failed launch leaves nothing
+/// behind.
+#[derive(Debug)]
+pub struct LaunchScript {
+    path: PathBuf,
+    launched: bool,
+}
+
+impl LaunchScript {
+    /// Write `body` to a new script run by bash. With `keep_open` an interactive bash takes over
+    /// once the body has finished, so the window stays open.
+    pub fn write(body: &str, keep_open: bool) -> Result<Self, String> {
+        let dir = private_dir()?;
+        sweep_stale(&dir);
+
+        let mut content = String::from("#!/bin/bash\nrm -f -- \"$0\"\n");
+        content.push_str(body);
+        if !body.ends_with('\n') {
+            content.push('\n');
+        }
+        if keep_open {
+            content.push_str("exec bash\n");
+        }
+
+        for _ in 0..CREATE_ATTEMPTS {
+            let path = dir.join(format!("launch-{:016x}.sh", rand::random::<u64>()));
+            match create_executable(&path) {
+                Ok(mut file) => {
+                    let script = Self {
+                        path,
+                        launched: false,
+                    };
+                    file.write_all(content.as_bytes())
+                        .and_then(|_| file.sync_all())
+                        .map_err(|e| format!("Failed to write {}: {}", script.path.display(), e))?;
+                    return Ok(script);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e)),
+            }
+        }
+        Err(format!(
+            "Failed to find a free script name in {}",
+            dir.display()
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path as a single shell word, for launchers that only take a command line.
+    pub fn shell_word(&self) -> String {
+        shell_quote(&self.path.to_string_lossy())
+    }
+
+    /// The script has been handed to whatever runs it and will delete itself from now on.
+    pub fn launched(mut self) -> PathBuf {
+        self.launched = true;
+        self.path.clone()
+    }
+}
+
+impl Drop for LaunchScript {
+    fn drop(&mut self) {
+        if !self.launched {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// `text` as an AppleScript string literal.
+#[cfg(any(target_os = "macos", test))]
+pub fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// One directory per user so nobody else can list, swap or read the scripts
+fn private_dir() -> Result<PathBuf, String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+        let uid = unsafe { libc::geteuid() };
+        let dir = std::env::temp_dir().join(format!("ssm-launch-{}", uid));
+        match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(format!("Failed to create {}: {}", dir.display(), e)),
+        }
+
+        // An existing one must be ours, a real directory and closed to others
+        let meta = std::fs::symlink_metadata(&dir)
+            .map_err(|e| format!("Failed to inspect {}: {}", dir.display(), e))?;
+        if !meta.is_dir() || meta.uid() != uid {
+            return Err(format!(
+                "{} is not a directory owned by the current user",
+                dir.display()
+            ));
+        }
+        if meta.permissions().mode() & 0o077 != 0 {
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+                .map_err(|e| format!("Failed to restrict {}: {}", dir.display(), e))?;
+        }
+        Ok(dir)
+    }
+    #[cfg(not(unix))]
+    {
+        let dir = std::env::temp_dir().join(format!("ssm-launch-{}", whoami::username()));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(dir)
+    }
+}
+
+fn create_executable(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o700);
+    }
+    options.open(path)
+}
+
+fn sweep_stale(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let is_stale = entry
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .is_ok_and(|modified| {
+                now.duration_since(modified)
+                    .is_ok_and(|age| age > STALE_AFTER)
+            });
+        if is_stale {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Command;
+
+    // Run the script by path, the way terminals do, and return its stdout
+    fn run(body: &str) -> String {
+        let script = LaunchScript::write(body, false).unwrap();
+        let path = script.launched();
+        let output = Command::new(&path).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert!(!path.exists(), "the script should delete itself");
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn single_and_double_quotes_reach_the_shell_unchanged() {
+        let body = r#"printf '%s\n' "it's" 'say "hi"' "back\\slash" '$HOME' "a;b""#;
+        assert_eq!(run(body), "it's\nsay \"hi\"\nback\\slash\n$HOME\na;b\n");
+    }
+
+    #[test]
+    fn newlines_and_trailing_comments_do_not_swallow_the_rest() {
+        let body = "echo one # a comment\ncat <<'EOF'\nline 'two'\nEOF\necho three";
+        assert_eq!(run(body), "one\nline 'two'\nthree\n");
+
+        let script = LaunchScript::write("echo hi # keep open", true).unwrap();
+        let content = std::fs::read_to_string(script.path()).unwrap();
+        assert!(content.ends_with("echo hi # keep open\nexec bash\n"));
+    }
+
+    #[test]
+    fn unicode_is_written_byte_for_byte() {
+        let body = "echo 'héllo wörld – 世界 🚀'\nname=\"ünï\"; echo \"$name\"";
+        assert_eq!(run(body), "héllo wörld – 世界 🚀\nünï\n");
+    }
+
+    #[test]
+    fn scripts_are_private_and_unique() {
+        let first = LaunchScript::write("true", false).unwrap();
+        let second = LaunchScript::write("true", false).unwrap();
+        assert_ne!(first.path(), second.path());
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(first.path()), 0o700);
+        assert_eq!(mode(first.path().parent().unwrap()), 0o700);
+    }
+
+    #[test]
+    fn unlaunched_scripts_are_removed_on_drop() {
+        let script = LaunchScript::write("true", false).unwrap();
+        let path = script.path().to_path_buf();
+        assert!(path.exists());
+        drop(script);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn shell_word_survives_the_shell() {
+        let script = LaunchScript::write("echo ran", false).unwrap();
+        let word = script.shell_word();
+        script.launched();
+        let output = Command::new("sh").arg("-c").arg(&word).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ran\n");
+    }
+
+    #[test]
+    fn applescript_strings_escape_quotes_and_backslashes() {
+        assert_eq!(
+            applescript_string(r#"/tmp/it's "x" \y"#),
+            r#""/tmp/it's \"x\" \\y""#
+        );
+    }
+}
@@ -4,20 +4,54 @@
 #[macro_use]
 extern crate objc;
 
+mod backup;
+mod chains;
+mod dag;
+mod db;
+mod environment;
+mod executor;
+mod fanout;
+mod jobs;
+mod launcher;
+mod library;
+mod mirror;
+mod multiplexer;
 mod prisma;
-
+mod pty;
+mod revisions;
+mod scheduler;
+mod secrets;
+mod ssh;
+mod terminal;
+mod watcher;
+
+use db::repository::execution_limit_repository::ExecutionLimitRepository;
+use db::repository::history_repository::{HistoryPage, HistoryRepository};
+use executor::history::{ExecutionMode, HistoryEntry};
+use executor::interpreter::{self, Interpreter};
+use executor::limits::ExecutionLimits;
+use executor::placeholders;
+use executor::run_config::{ResolvedRunConfig, RunConfig};
 use prisma::PrismaClient;
 use serde_json;
+use std::collections::HashMap;
 use std::process::{Child, Command};
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use tauri::{Emitter, Manager};
 use tauri_plugin_opener::OpenerExt;
 
 pub static RT_HANDLE: OnceLock<tokio::runtime::Handle> = OnceLock::new();
-pub static PRISMA_CLIENT: OnceLock<PrismaClient> = OnceLock::new();
+// Replaced when a backup is restored; use db::get_db::get_db()
+pub static PRISMA_CLIENT: RwLock<Option<&'static PrismaClient>> = RwLock::new(None);
 pub static SPRING_BOOT_PROCESS: OnceLock<Arc<Mutex<Option<Child>>>> = OnceLock::new();
+pub static JOB_REGISTRY: OnceLock<Arc<Mutex<HashMap<String, jobs::Job>>>> = OnceLock::new();
+pub static PTY_SESSIONS: OnceLock<pty::PtyRegistry> = OnceLock::new();
 pub static BACKEND_PORT: OnceLock<u16> = OnceLock::new();
 pub static CLEANUP_DONE: OnceLock<Arc<Mutex<bool>>> = OnceLock::new();
+pub static SECRET_STORE: OnceLock<secrets::SecretStore> = OnceLock::new();
+pub static SCHEDULER: OnceLock<scheduler::Scheduler> = OnceLock::new();
+pub static WATCHERS: OnceLock<watcher::Watchers> = OnceLock::new();
+pub static MIRROR: OnceLock<mirror::Mirror> = OnceLock::new();
 #[cfg(target_os = "macos")]
 pub static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
 
@@ -102,17 +136,61 @@ async fn close_subwindow(app: tauri::AppHandle, label: String) -> Result<(), Str
 }
 
 #[tauri::command]
-async fn run_script(command: String) -> Result<(), String> {
+async fn run_script(
+    command: String,
+    script_id: Option<i32>,
+    parameters: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let (command, parameter_env) =
+        placeholders::resolve_command(&command, script_id, parameters).await?;
+    let mut run_config = RunConfig::load_for_run(script_id).await?;
     println!("Running script command in Terminal: {}", command);
-    open_terminal_with_command(command);
-    Ok(())
+    record_terminal_run(script_id, &command, &run_config).await;
+    let command = run_config.inject_secrets(&command).await?;
+    environment::merge_env_vars(&mut run_config.env, parameter_env);
+    let command = interpreter::terminal_command(run_config.interpreter, &command)?;
+    run_config.secrets.stage_env_file()?;
+    open_terminal_with_command(command, run_config).await
 }
 
 #[tauri::command]
-async fn execute_command_in_shell(command: String) -> Result<(), String> {
+async fn execute_command_in_shell(
+    command: String,
+    script_id: Option<i32>,
+    parameters: Option<HashMap<String, String>>,
+) -> Result<(), String> {
+    let (command, parameter_env) =
+        placeholders::resolve_command(&command, script_id, parameters).await?;
+    let mut run_config = RunConfig::load_for_run(script_id).await?;
     println!("Executing command in new shell: {}", command);
-    open_terminal_and_keep_open(command);
-    Ok(())
+    record_terminal_run(script_id, &command, &run_config).await;
+    let command = run_config.inject_secrets(&command).await?;
+    environment::merge_env_vars(&mut run_config.env, parameter_env);
+    let command = interpreter::terminal_command(run_config.interpreter, &command)?;
+    run_config.secrets.stage_env_file()?;
+    if let Some(multiplexer) = multiplexer::preferred().await? {
+        return multiplexer::send_script(multiplexer, script_id, &command, &run_config).await;
+    }
+    open_terminal_and_keep_open(command, run_config).await
+}
+
+// Terminal runs are out of our hands once the window opens, so only the launch is recorded
+async fn record_terminal_run(
+    script_id: Option<i32>,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) {
+    let run_id = executor::new_run_id();
+    let history = HistoryEntry::start(
+        script_id,
+        &run_id,
+        ExecutionMode::Terminal,
+        command,
+        run_config,
+    );
+    if let Some(history) = history {
+        history.record(None).await;
+    }
 }
 
 // #[tauri::command]
@@ -139,92 +217,787 @@ async fn execute_command_in_shell(command: String) -> Result<(), String> {
 //     Ok(())
 // }
 
+/// Run a command silently and return its stdout. `stdin` is written to the process and then
+/// closed; with `interactive_stdin` it stays open afterwards for `write_job_stdin`. Without
+/// either the process reads EOF from stdin.
+/// With `ssh_target_id` the command runs on that SSH target instead of locally.
 #[tauri::command]
-async fn execute_command(command: String) -> Result<String, String> {
+async fn execute_command(
+    command: String,
+    script_id: Option<i32>,
+    run_id: Option<String>,
+    parameters: Option<HashMap<String, String>>,
+    stdin: Option<String>,
+    interactive_stdin: Option<bool>,
+    ssh_target_id: Option<i32>,
+) -> Result<String, String> {
+    let _database = backup::use_database().await;
+    let (command, parameter_env) =
+        placeholders::resolve_command(&command, script_id, parameters).await?;
     println!("Executing command: {}", command);
 
-    // Get the user's home directory
-    let home = std::env::var("HOME").unwrap_or_else(|_| {
-        dirs::home_dir()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "/Users".to_string())
-    });
-
-    // Detect the user's shell from /etc/passwd or use zsh as default
-    let shell = std::env::var("SHELL").unwrap_or_else(|_| {
-        std::fs::read_to_string("/etc/passwd")
-            .ok()
-            .and_then(|content| {
-                content.lines().find_map(|line| {
-                    if line.contains(&home) {
-                        line.split(':').last().map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .unwrap_or_else(|| "/bin/zsh".to_string())
-    });
-
-    #[cfg(debug_assertions)]
-    println!("Using shell: {} for command: {}", shell, command);
-
-    // Build the command that sources the shell config files before running
-    let wrapped_command = if shell.contains("zsh") {
-        format!(
-            "source ~/.zshrc 2>/dev/null; source ~/.zprofile 2>/dev/null; {}",
-            command
-        )
-    } else if shell.contains("bash") {
-        format!(
-            "source ~/.bash_profile 2>/dev/null; source ~/.bashrc 2>/dev/null; {}",
-            command
-        )
-    } else {
-        command.clone()
+    let run = executor::SilentRun {
+        run_id: run_id.unwrap_or_else(executor::new_run_id),
+        command: command.clone(),
+        script_id,
+        mode: ExecutionMode::Silent,
+        stdin,
+        interactive_stdin: interactive_stdin.unwrap_or(false),
+        env: parameter_env,
+        ssh_target_id,
+        timeout_seconds: None,
     };
-
-    let output = tokio::process::Command::new(&shell)
-        .arg("-l") // Login shell
-        .arg("-c")
-        .arg(&wrapped_command)
-        .env("HOME", &home) // Ensure HOME is set
-        .env(
-            "USER",
-            std::env::var("USER").unwrap_or_else(|_| whoami::username()),
-        )
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute command: {}", e))?;
-
-    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let outcome = executor::run_silent(run, |_, _| {}).await?;
 
     #[cfg(debug_assertions)]
     {
         println!("Command executed: {}", command);
-        if !stdout.is_empty() {
-            println!("Output: {}", stdout);
+        if !outcome.stdout.is_empty() {
+            println!("Output: {}", outcome.stdout);
         }
-        if !stderr.is_empty() {
-            eprintln!("Error: {}", stderr);
+        if !outcome.stderr.is_empty() {
+            eprintln!("Error: {}", outcome.stderr);
         }
     }
 
     // Return error if command failed
-    if !output.status.success() {
-        let error_msg = if !stderr.is_empty() {
-            stderr
-        } else if !stdout.is_empty() {
-            stdout
-        } else {
-            format!("Command failed with exit code: {:?}", output.status.code())
-        };
-        return Err(error_msg);
+    if !outcome.success {
+        return Err(outcome.error_message());
     }
 
     // Return stdout on success
-    Ok(stdout)
+    Ok(outcome.stdout)
+}
+
+/// Run a command silently and stream its stdout/stderr line by line to the calling window as
+/// `script-output` events, ending with one `script-exited` event carrying the exit code and
+/// duration. Returns the run id immediately so the frontend can match events to this run.
+/// `stdin`, `interactive_stdin` and `ssh_target_id` work as for `execute_command`.
+#[tauri::command]
+async fn execute_command_streaming(
+    window: tauri::WebviewWindow,
+    command: String,
+    script_id: Option<i32>,
+    run_id: Option<String>,
+    parameters: Option<HashMap<String, String>>,
+    stdin: Option<String>,
+    interactive_stdin: Option<bool>,
+    ssh_target_id: Option<i32>,
+) -> Result<String, String> {
+    let interactive_stdin = interactive_stdin.unwrap_or(false);
+    let _database = backup::use_database().await;
+    let (command, parameter_env) =
+        placeholders::resolve_command(&command, script_id, parameters).await?;
+    println!("Executing command with streaming output: {}", command);
+
+    let mut limits = executor::load_limits(script_id).await?;
+    let target = match ssh_target_id {
+        Some(target_id) => Some(ssh::load_target(target_id).await?),
+        None => None,
+    };
+    let mut run_config = match target {
+        Some(_) => {
+            limits = limits.without_rlimits();
+            RunConfig::load_for_remote_run(script_id).await?
+        }
+        None => RunConfig::load_for_run(script_id).await?,
+    };
+    let run_id = run_id.unwrap_or_else(executor::new_run_id);
+    let mut history = HistoryEntry::start(
+        script_id,
+        &run_id,
+        ExecutionMode::Streaming,
+        &command,
+        &run_config,
+    );
+    if let (Some(history), Some(target)) = (history.as_mut(), &target) {
+        history.set_remote(target);
+    }
+    let shell_command = run_config.inject_secrets(&command).await?;
+    environment::merge_env_vars(&mut run_config.env, parameter_env);
+    let child = executor::spawn_run(
+        &run_id,
+        &shell_command,
+        &run_config,
+        &limits,
+        target.as_ref(),
+        stdin.is_some() || interactive_stdin,
+    )
+    .await?;
+    executor::spawn_streaming(
+        window.app_handle().clone(),
+        window.label().to_string(),
+        run_id.clone(),
+        child,
+        limits,
+        run_config.secrets.mask,
+        history,
+    );
+    if let Some(input) = stdin {
+        jobs::feed_stdin(&run_id, input.into_bytes(), !interactive_stdin);
+    }
+    Ok(run_id)
+}
+
+/// Executions of one script, newest first. `page` is zero-based.
+#[tauri::command]
+async fn get_script_history(
+    script_id: i32,
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<HistoryPage, String> {
+    let (page, page_size) = history_page_bounds(page, page_size);
+    HistoryRepository::new()
+        .get_script_history(script_id, page, page_size)
+        .await
+        .map_err(|e| format!("Failed to load script history: {}", e))
+}
+
+/// Executions of every script, newest first. `page` is zero-based.
+#[tauri::command]
+async fn get_execution_timeline(
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<HistoryPage, String> {
+    let (page, page_size) = history_page_bounds(page, page_size);
+    HistoryRepository::new()
+        .get_timeline(page, page_size)
+        .await
+        .map_err(|e| format!("Failed to load execution timeline: {}", e))
+}
+
+fn history_page_bounds(page: Option<i64>, page_size: Option<i64>) -> (i64, i64) {
+    const DEFAULT_PAGE_SIZE: i64 = 20;
+    const MAX_PAGE_SIZE: i64 = 200;
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    (page.unwrap_or(0).max(0), page_size.clamp(1, MAX_PAGE_SIZE))
+}
+
+/// The `{{param:name:type=default}}` placeholders of a script, with the values used last time.
+#[tauri::command]
+async fn get_script_parameters(
+    script_id: i32,
+) -> Result<Vec<placeholders::ScriptParameter>, String> {
+    placeholders::script_parameters(script_id).await
+}
+
+#[tauri::command]
+async fn get_script_run_config(script_id: i32) -> Result<RunConfig, String> {
+    RunConfig::load(Some(script_id)).await
+}
+
+/// Store the working directory and env var overrides of a script. The working directory may
+/// use `~` and `$VAR`; it is expanded and checked each time the script runs.
+#[tauri::command]
+async fn set_script_run_config(script_id: i32, config: RunConfig) -> Result<RunConfig, String> {
+    config.save(script_id).await?;
+    RunConfig::load(Some(script_id)).await
+}
+
+#[tauri::command]
+async fn list_environment_profiles() -> Result<Vec<environment::EnvironmentProfile>, String> {
+    environment::list_profiles().await
+}
+
+/// Create (no `id`) or update an environment profile, replacing its variables.
+#[tauri::command]
+async fn save_environment_profile(
+    profile: environment::EnvironmentProfile,
+) -> Result<environment::EnvironmentProfile, String> {
+    environment::save_profile(profile).await
+}
+
+#[tauri::command]
+async fn delete_environment_profile(profile_id: i32) -> Result<(), String> {
+    environment::delete_profile(profile_id).await
+}
+
+#[tauri::command]
+async fn get_workspace_environment(
+    workspace_id: i32,
+) -> Result<environment::WorkspaceEnvironment, String> {
+    environment::get_workspace_environment(workspace_id).await
+}
+
+#[tauri::command]
+async fn set_workspace_env_vars(
+    workspace_id: i32,
+    vars: Vec<executor::run_config::EnvVar>,
+) -> Result<(), String> {
+    environment::set_workspace_env_vars(workspace_id, vars).await
+}
+
+#[tauri::command]
+async fn attach_environment_profile(workspace_id: i32, profile_id: i32) -> Result<(), String> {
+    environment::attach_profile(workspace_id, profile_id).await
+}
+
+#[tauri::command]
+async fn detach_environment_profile(workspace_id: i32, profile_id: i32) -> Result<(), String> {
+    environment::detach_profile(workspace_id, profile_id).await
+}
+
+/// Switch the active environment profile of a workspace (`null` for none). Every window is
+/// told through an `environment-profile-changed` event.
+#[tauri::command]
+async fn switch_environment_profile(
+    app: tauri::AppHandle,
+    workspace_id: i32,
+    profile_id: Option<i32>,
+) -> Result<Option<environment::ActiveProfile>, String> {
+    environment::switch_profile(&app, workspace_id, profile_id).await
+}
+
+#[tauri::command]
+async fn get_script_execution_limits(script_id: i32) -> Result<ExecutionLimits, String> {
+    executor::load_limits(Some(script_id)).await
+}
+
+/// Store the execution limits for a script; `null` fields mean unlimited.
+#[tauri::command]
+async fn set_script_execution_limits(
+    script_id: i32,
+    limits: ExecutionLimits,
+) -> Result<ExecutionLimits, String> {
+    let saved = ExecutionLimitRepository::new()
+        .upsert_limits(script_id, &limits)
+        .await
+        .map_err(|e| format!("Failed to save execution limits: {}", e))?;
+    Ok(ExecutionLimits::from(&saved))
+}
+
+/// List running jobs and recently finished ones, oldest first.
+#[tauri::command]
+fn list_jobs() -> Result<Vec<jobs::JobInfo>, String> {
+    jobs::list()
+}
+
+/// Cancel a running job by signalling its whole process group: SIGTERM first, then SIGKILL
+/// if it has not exited after the grace period.
+#[tauri::command]
+fn cancel_job(run_id: String) -> Result<(), String> {
+    jobs::cancel(&run_id, jobs::CANCEL_GRACE_PERIOD)
+}
+
+/// Resolve with the job's final state once it has finished.
+#[tauri::command]
+async fn wait_job(run_id: String, timeout_ms: Option<u64>) -> Result<jobs::JobInfo, String> {
+    jobs::wait(&run_id, timeout_ms.map(std::time::Duration::from_millis)).await
+}
+
+/// Write text to the stdin of a running job; end `data` with a newline to send a line. `close`
+/// sends EOF afterwards.
+#[tauri::command]
+async fn write_job_stdin(run_id: String, data: String, close: Option<bool>) -> Result<(), String> {
+    jobs::write_stdin(&run_id, data.as_bytes(), close.unwrap_or(false)).await
+}
+
+/// Like `write_job_stdin` for binary payloads.
+#[tauri::command]
+async fn write_job_stdin_bytes(
+    run_id: String,
+    data: Vec<u8>,
+    close: Option<bool>,
+) -> Result<(), String> {
+    jobs::write_stdin(&run_id, &data, close.unwrap_or(false)).await
+}
+
+#[tauri::command]
+fn close_job_stdin(run_id: String) -> Result<(), String> {
+    jobs::close_stdin(&run_id)
+}
+
+/// Start a pseudo-terminal session in the calling window running the user's login shell, or
+/// `command` when given (followed by an interactive shell if `keep_open`). Output arrives as
+/// `pty-output` events and the end as one `pty-exited` event; returns the session id.
+#[tauri::command]
+async fn open_pty_session(
+    window: tauri::WebviewWindow,
+    command: Option<String>,
+    script_id: Option<i32>,
+    parameters: Option<HashMap<String, String>>,
+    keep_open: Option<bool>,
+    cols: u16,
+    rows: u16,
+) -> Result<String, String> {
+    let session_id = executor::new_run_id();
+    let mut run_config = RunConfig::load_for_run(script_id).await?;
+    let (command, history) = match command {
+        Some(command) => {
+            let (command, parameter_env) =
+                placeholders::resolve_command(&command, script_id, parameters).await?;
+            let history = HistoryEntry::start(
+                script_id,
+                &session_id,
+                ExecutionMode::Pty,
+                &command,
+                &run_config,
+            );
+            let command = run_config.inject_secrets(&command).await?;
+            environment::merge_env_vars(&mut run_config.env, parameter_env);
+            (Some(command), history)
+        }
+        None => (None, None),
+    };
+    pty::open_session(
+        window.app_handle().clone(),
+        window.label().to_string(),
+        session_id.clone(),
+        pty::PtyLaunch {
+            command,
+            keep_open: keep_open.unwrap_or(false),
+            run_config,
+            history,
+            cols,
+            rows,
+        },
+    )?;
+    Ok(session_id)
+}
+
+#[tauri::command]
+async fn write_pty_input(session_id: String, data: String) -> Result<(), String> {
+    pty::write_input(&session_id, &data).await
+}
+
+#[tauri::command]
+async fn resize_pty(session_id: String, cols: u16, rows: u16) -> Result<(), String> {
+    pty::resize(&session_id, cols, rows)
+}
+
+#[tauri::command]
+async fn close_pty_session(session_id: String) -> Result<(), String> {
+    pty::close(&session_id)
+}
+
+#[tauri::command]
+async fn list_pty_sessions() -> Result<Vec<pty::PtySessionInfo>, String> {
+    pty::list()
+}
+
+/// Names and backends of the stored secrets; values are never sent to the frontend.
+#[tauri::command]
+async fn list_secrets() -> Result<Vec<secrets::SecretInfo>, String> {
+    secrets::list_secrets().await
+}
+
+#[tauri::command]
+async fn set_secret(name: String, value: String) -> Result<secrets::SecretInfo, String> {
+    secrets::set_secret(name, value).await
+}
+
+#[tauri::command]
+async fn delete_secret(name: String) -> Result<(), String> {
+    secrets::delete_secret(name).await
+}
+
+#[tauri::command]
+async fn list_script_chains() -> Result<Vec<chains::ScriptChain>, String> {
+    chains::list_chains().await
+}
+
+#[tauri::command]
+async fn save_script_chain(chain: chains::ScriptChain) -> Result<chains::ScriptChain, String> {
+    chains::save_chain(chain).await
+}
+
+#[tauri::command]
+async fn delete_script_chain(chain_id: i32) -> Result<(), String> {
+    chains::delete_chain(chain_id).await
+}
+
+/// Run the steps of a chain one after another in the background. Progress arrives as
+/// `chain-step` and `chain-finished` events; returns the chain run id they carry.
+#[tauri::command]
+async fn run_script_chain(window: tauri::WebviewWindow, chain_id: i32) -> Result<String, String> {
+    chains::start_chain(
+        window.app_handle().clone(),
+        window.label().to_string(),
+        chain_id,
+    )
+    .await
+}
+
+/// Runs of one chain, newest first. `page` is zero-based.
+#[tauri::command]
+async fn get_chain_history(
+    chain_id: i32,
+    page: Option<i64>,
+    page_size: Option<i64>,
+) -> Result<chains::ChainRunPage, String> {
+    let (page, page_size) = history_page_bounds(page, page_size);
+    chains::chain_history(chain_id, page, page_size).await
+}
+
+#[tauri::command]
+async fn list_script_dags() -> Result<Vec<dag::ScriptDag>, String> {
+    dag::list_dags().await
+}
+
+/// Rejected if the dependencies form a cycle; the error names the scripts along it.
+#[tauri::command]
+async fn save_script_dag(graph: dag::ScriptDag) -> Result<dag::ScriptDag, String> {
+    dag::save_dag(graph).await
+}
+
+#[tauri::command]
+async fn delete_script_dag(dag_id: i32) -> Result<(), String> {
+    dag::delete_dag(dag_id).await
+}
+
+/// Run a dependency graph in the background, independent nodes side by side. Progress arrives
+/// as `dag-node` and `dag-finished` events; returns the graph run id they carry.
+#[tauri::command]
+async fn run_script_dag(
+    window: tauri::WebviewWindow,
+    dag_id: i32,
+    max_parallel: Option<i32>,
+) -> Result<String, String> {
+    dag::start_dag(
+        window.app_handle().clone(),
+        window.label().to_string(),
+        dag_id,
+        max_parallel,
+    )
+    .await
+}
+
+/// Every schedule with its next few fire times.
+#[tauri::command]
+async fn list_schedules() -> Result<Vec<scheduler::ScheduleInfo>, String> {
+    scheduler::list_schedules().await
+}
+
+#[tauri::command]
+async fn save_schedule(
+    schedule: scheduler::ScheduleInput,
+) -> Result<scheduler::ScheduleInfo, String> {
+    scheduler::save_schedule(schedule).await
+}
+
+#[tauri::command]
+async fn delete_schedule(schedule_id: i32) -> Result<(), String> {
+    scheduler::delete_schedule(schedule_id).await
+}
+
+#[tauri::command]
+async fn get_schedule_fire_times(
+    schedule_id: i32,
+    count: Option<usize>,
+) -> Result<Vec<scheduler::FireTime>, String> {
+    scheduler::upcoming_fire_times(schedule_id, count.unwrap_or(10)).await
+}
+
+/// Fire times of an expression being edited, so mistakes show before it is saved.
+#[tauri::command]
+async fn preview_schedule_fire_times(
+    cron_expression: String,
+    timezone: Option<String>,
+    count: Option<usize>,
+) -> Result<Vec<scheduler::FireTime>, String> {
+    scheduler::preview_fire_times(
+        &cron_expression,
+        timezone.as_deref().unwrap_or("UTC"),
+        count.unwrap_or(10),
+    )
+}
+
+#[tauri::command]
+async fn list_watch_triggers() -> Result<Vec<watcher::WatchTriggerInfo>, String> {
+    watcher::list_triggers().await
+}
+
+#[tauri::command]
+async fn save_watch_trigger(
+    app: tauri::AppHandle,
+    trigger: watcher::WatchTriggerInput,
+) -> Result<(), String> {
+    watcher::save_trigger(&app, trigger).await
+}
+
+#[tauri::command]
+async fn delete_watch_trigger(app: tauri::AppHandle, trigger_id: i32) -> Result<(), String> {
+    watcher::delete_trigger(&app, trigger_id).await
+}
+
+#[tauri::command]
+async fn set_watchers_paused(
+    app: tauri::AppHandle,
+    paused: bool,
+) -> Result<watcher::WatcherStatus, String> {
+    watcher::set_paused(&app, paused).await
+}
+
+#[tauri::command]
+fn get_watcher_status() -> Result<watcher::WatcherStatus, String> {
+    watcher::status()
+}
+
+#[tauri::command]
+fn get_mirror_status() -> Result<mirror::MirrorStatus, String> {
+    mirror::status()
+}
+
+/// Mirror the library to `dir` as .sh/.md files, or stop mirroring when `None`.
+#[tauri::command]
+async fn set_mirror_dir(
+    app: tauri::AppHandle,
+    dir: Option<String>,
+) -> Result<mirror::MirrorStatus, String> {
+    mirror::set_dir(&app, dir).await
+}
+
+#[tauri::command]
+async fn sync_mirror(app: tauri::AppHandle) -> Result<mirror::SyncReport, String> {
+    mirror::sync(&app).await
+}
+
+#[tauri::command]
+async fn resolve_mirror_conflict(
+    app: tauri::AppHandle,
+    script_id: i32,
+    keep: mirror::MirrorSide,
+) -> Result<mirror::SyncReport, String> {
+    mirror::resolve(&app, script_id, keep).await
+}
+
+#[tauri::command]
+async fn list_ssh_targets() -> Result<Vec<ssh::SshTarget>, String> {
+    ssh::list_targets().await
+}
+
+#[tauri::command]
+async fn save_ssh_target(target: ssh::SshTargetInput) -> Result<ssh::SshTarget, String> {
+    ssh::save_target(target).await
+}
+
+#[tauri::command]
+async fn delete_ssh_target(target_id: i32) -> Result<(), String> {
+    ssh::delete_target(target_id).await
+}
+
+/// Connect to the target once to check the host key and the credentials.
+#[tauri::command]
+async fn test_ssh_target(target_id: i32) -> Result<(), String> {
+    ssh::test_target(target_id).await
+}
+
+#[tauri::command]
+async fn disconnect_ssh_target(target_id: i32) -> Result<(), String> {
+    ssh::disconnect_target(target_id).await
+}
+
+#[tauri::command]
+async fn list_host_groups() -> Result<Vec<fanout::HostGroup>, String> {
+    fanout::list_groups().await
+}
+
+#[tauri::command]
+async fn save_host_group(group: fanout::HostGroup) -> Result<fanout::HostGroup, String> {
+    fanout::save_group(group).await
+}
+
+#[tauri::command]
+async fn delete_host_group(group_id: i32) -> Result<(), String> {
+    fanout::delete_group(group_id).await
+}
+
+/// Run a script on every host of a group. Progress arrives as `fan-out-host` and
+/// `script-output` events, the per-host results and summary as one `fan-out-finished` event.
+/// Returns the fan-out run id.
+#[tauri::command]
+async fn run_fan_out(
+    window: tauri::WebviewWindow,
+    script_id: i32,
+    group_id: i32,
+    max_parallel: Option<i32>,
+    timeout_seconds: Option<i32>,
+) -> Result<String, String> {
+    fanout::start_fan_out(
+        window.app_handle().clone(),
+        window.label().to_string(),
+        script_id,
+        group_id,
+        max_parallel,
+        timeout_seconds,
+    )
+    .await
+}
+
+/// The terminal emulator preference and which emulators are installed. Only Linux uses it.
+#[tauri::command]
+async fn get_terminal_settings() -> Result<terminal::TerminalSettings, String> {
+    terminal::get_settings().await
+}
+
+#[tauri::command]
+async fn set_terminal_preference(
+    emulator: Option<terminal::TerminalEmulator>,
+    custom_template: Option<String>,
+) -> Result<terminal::TerminalSettings, String> {
+    terminal::set_preference(emulator, custom_template).await
+}
+
+/// Whether "run in shell" goes to a tmux or zellij session instead of a new terminal window.
+#[tauri::command]
+async fn get_multiplexer_settings() -> Result<multiplexer::MultiplexerSettings, String> {
+    multiplexer::get_settings().await
+}
+
+#[tauri::command]
+async fn set_shell_multiplexer(
+    multiplexer: Option<multiplexer::Multiplexer>,
+) -> Result<multiplexer::MultiplexerSettings, String> {
+    multiplexer::set_preference(multiplexer).await
+}
+
+#[tauri::command]
+async fn list_multiplexer_sessions() -> Result<Vec<multiplexer::MultiplexerSession>, String> {
+    multiplexer::list_sessions().await
+}
+
+#[tauri::command]
+async fn attach_multiplexer_session(
+    multiplexer: multiplexer::Multiplexer,
+    name: String,
+) -> Result<(), String> {
+    multiplexer::attach_session(multiplexer, name).await
+}
+
+#[tauri::command]
+async fn kill_multiplexer_session(
+    multiplexer: multiplexer::Multiplexer,
+    name: String,
+) -> Result<(), String> {
+    multiplexer::kill_session(multiplexer, name).await
+}
+
+/// Every supported interpreter and where it is installed, if it is.
+#[tauri::command]
+async fn list_interpreters() -> Result<Vec<interpreter::InterpreterInfo>, String> {
+    Ok(interpreter::list_interpreters())
+}
+
+#[tauri::command]
+async fn get_script_interpreter(script_id: i32) -> Result<Option<Interpreter>, String> {
+    interpreter::configured(Some(script_id)).await
+}
+
+/// `None` runs the script with the interpreter on its shebang line, or the login shell.
+#[tauri::command]
+async fn set_script_interpreter(
+    script_id: i32,
+    interpreter: Option<Interpreter>,
+) -> Result<(), String> {
+    interpreter::set_interpreter(script_id, interpreter).await
+}
+
+#[tauri::command]
+async fn get_migration_status() -> Result<db::migrations::MigrationStatus, String> {
+    db::migrations::status(db::get_db::get_db()).await
+}
+
+#[tauri::command]
+async fn list_backups(app_handle: tauri::AppHandle) -> Result<Vec<backup::BackupInfo>, String> {
+    backup::list(&get_database_path(&app_handle)?)
+}
+
+#[tauri::command]
+async fn create_backup(app_handle: tauri::AppHandle) -> Result<backup::BackupInfo, String> {
+    let _database = backup::use_database().await;
+    backup::create(
+        &get_database_path(&app_handle)?,
+        backup::BackupReason::Manual,
+    )
+    .await
+}
+
+/// Restore the backup `name` and return the snapshot taken of the database it replaced. Refused
+/// while jobs are running; schedules, watch triggers and the mirror pause until it is done.
+#[tauri::command]
+async fn restore_backup(
+    app_handle: tauri::AppHandle,
+    name: String,
+) -> Result<backup::BackupInfo, String> {
+    let db_path = get_database_path(&app_handle)?;
+    let lock = backup::lock_for_restore().await?;
+
+    // The backend keeps its own connections open and must not write while the data is replaced
+    tokio::task::spawn_blocking(|| {
+        kill_spring_boot_backend();
+        verify_backend_killed();
+    })
+    .await
+    .map_err(|e| format!("Failed to stop the backend: {}", e))?;
+
+    let result = backup::restore(&lock, &db_path, &name).await;
+    restart_spring_boot_backend(app_handle);
+    result
+}
+
+/// Write the library and the images its scripts show to a zip archive at `path`.
+#[tauri::command]
+async fn export_library(
+    app_handle: tauri::AppHandle,
+    path: String,
+) -> Result<library::ExportSummary, String> {
+    let images_dir = get_images_dir(app_handle).await?;
+    library::export(
+        std::path::Path::new(&path),
+        std::path::Path::new(&images_dir),
+    )
+    .await
+}
+
+/// Import a library archive written by `export_library`, backing up the database first.
+#[tauri::command]
+async fn import_library(
+    app_handle: tauri::AppHandle,
+    path: String,
+    strategy: library::ImportStrategy,
+) -> Result<library::ImportSummary, String> {
+    let db_path = get_database_path(&app_handle)?;
+    let images_dir = get_images_dir(app_handle).await?;
+    let _database = backup::use_database().await;
+    library::import(
+        std::path::Path::new(&path),
+        std::path::Path::new(&images_dir),
+        &db_path,
+        strategy,
+    )
+    .await
+}
+
+/// A script's saved revisions, newest first.
+#[tauri::command]
+async fn list_script_revisions(script_id: i32) -> Result<Vec<revisions::RevisionInfo>, String> {
+    revisions::list(script_id).await
+}
+
+/// Unified diff between two revisions of a script, or between a revision and the script as it
+/// is now when `to_revision_id` is omitted.
+#[tauri::command]
+async fn diff_script_revisions(
+    from_revision_id: i32,
+    to_revision_id: Option<i32>,
+) -> Result<revisions::RevisionDiff, String> {
+    revisions::diff(from_revision_id, to_revision_id).await
+}
+
+#[tauri::command]
+async fn restore_script_revision(revision_id: i32) -> Result<(), String> {
+    revisions::restore(revision_id).await
+}
+
+#[tauri::command]
+async fn get_revision_retention() -> Result<Option<i32>, String> {
+    revisions::get_retention().await
+}
+
+/// Keep the newest `keep` revisions of every script, or all of them when `keep` is null.
+#[tauri::command]
+async fn set_revision_retention(keep: Option<i32>) -> Result<(), String> {
+    revisions::set_retention(keep).await
 }
 
 #[tauri::command]
@@ -471,7 +1244,90 @@ pub fn run() {
             raise_subwindows,
             run_script,
             execute_command,
+            execute_command_streaming,
+            list_jobs,
+            cancel_job,
+            wait_job,
+            write_job_stdin,
+            write_job_stdin_bytes,
+            close_job_stdin,
+            open_pty_session,
+            write_pty_input,
+            resize_pty,
+            close_pty_session,
+            list_pty_sessions,
+            list_secrets,
+            set_secret,
+            delete_secret,
+            list_script_chains,
+            save_script_chain,
+            delete_script_chain,
+            run_script_chain,
+            get_chain_history,
+            list_script_dags,
+            save_script_dag,
+            delete_script_dag,
+            run_script_dag,
+            list_schedules,
+            save_schedule,
+            delete_schedule,
+            get_schedule_fire_times,
+            preview_schedule_fire_times,
+            list_watch_triggers,
+            save_watch_trigger,
+            delete_watch_trigger,
+            set_watchers_paused,
+            get_watcher_status,
+            get_mirror_status,
+            set_mirror_dir,
+            sync_mirror,
+            resolve_mirror_conflict,
+            list_ssh_targets,
+            save_ssh_target,
+            delete_ssh_target,
+            test_ssh_target,
+            disconnect_ssh_target,
+            list_host_groups,
+            save_host_group,
+            delete_host_group,
+            run_fan_out,
+            get_terminal_settings,
+            set_terminal_preference,
+            get_multiplexer_settings,
+            set_shell_multiplexer,
+            list_multiplexer_sessions,
+            attach_multiplexer_session,
+            kill_multiplexer_session,
+            list_interpreters,
+            get_script_interpreter,
+            set_script_interpreter,
+            get_script_parameters,
+            get_script_history,
+            get_execution_timeline,
+            get_script_run_config,
+            set_script_run_config,
+            list_environment_profiles,
+            save_environment_profile,
+            delete_environment_profile,
+            get_workspace_environment,
+            set_workspace_env_vars,
+            attach_environment_profile,
+            detach_environment_profile,
+            switch_environment_profile,
+            get_script_execution_limits,
+            set_script_execution_limits,
             execute_command_in_shell,
+            get_migration_status,
+            list_backups,
+            create_backup,
+            restore_backup,
+            export_library,
+            import_library,
+            list_script_revisions,
+            diff_script_revisions,
+            restore_script_revision,
+            get_revision_retention,
+            set_revision_retention,
             get_backend_port,
             check_backend_health,
             set_title_bar_color,
@@ -490,7 +1346,17 @@ pub fn run() {
                 .set(Arc::new(Mutex::new(false)))
                 .map_err(|_| "Failed to initialize cleanup flag")?;
 
-            // 0.1. Store app handle for macOS delegate
+            // 0.1. Initialize job registry for silent script runs
+            JOB_REGISTRY
+                .set(Arc::new(Mutex::new(HashMap::new())))
+                .map_err(|_| "Failed to initialize job registry")?;
+
+            // 0.2. Initialize registry of embedded terminal sessions
+            PTY_SESSIONS
+                .set(Arc::new(Mutex::new(HashMap::new())))
+                .map_err(|_| "Failed to initialize PTY session registry")?;
+
+            // 0.3. Store app handle for macOS delegate
             #[cfg(target_os = "macos")]
             APP_HANDLE
                 .set(app.handle().clone())
@@ -506,6 +1372,36 @@ pub fn run() {
             // 3. Initialize database
             init_db(app.handle())?;
 
+            // 3.1. Initialize the secret store next to the database
+            let db_path = get_database_path(app.handle())?;
+            let data_dir = std::path::Path::new(&db_path)
+                .parent()
+                .ok_or("Failed to resolve app data directory")?;
+            SECRET_STORE
+                .set(secrets::SecretStore::new(data_dir))
+                .map_err(|_| "Failed to initialize secret store")?;
+
+            // 3.2. Start firing scheduled scripts
+            SCHEDULER
+                .set(scheduler::Scheduler::default())
+                .map_err(|_| "Failed to initialize scheduler")?;
+            scheduler::start(app.handle().clone());
+
+            // 3.3. Start the file-watch triggers
+            WATCHERS
+                .set(watcher::Watchers::default())
+                .map_err(|_| "Failed to initialize file watchers")?;
+            watcher::start(app.handle().clone());
+
+            // 3.4. Back up the database once a day
+            backup::start(db_path.clone());
+
+            // 3.5. Mirror the library to its directory
+            MIRROR
+                .set(mirror::Mirror::default())
+                .map_err(|_| "Failed to initialize the library mirror")?;
+            mirror::start(app.handle().clone());
+
             // 4. Initialize and optionally start Spring Boot
             init_spring_boot(app.handle().clone())?;
 
@@ -596,154 +1492,108 @@ fn get_database_path(app_handle: &tauri::AppHandle) -> Result<String, String> {
 }
 
 // Open Terminal.app with a command and keep it open (shows Terminal window)
-pub fn open_terminal_and_keep_open(command: String) {
+pub async fn open_terminal_and_keep_open(
+    command: String,
+    run_config: ResolvedRunConfig,
+) -> Result<(), String> {
     println!("open_terminal_and_keep_open called with: {}", command);
 
     #[cfg(target_os = "macos")]
     {
         let home_dir = dirs::home_dir().unwrap_or_default();
 
-        // Create a temporary script file to avoid complex quoting issues
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join(format!("tauri_script_{}.sh", std::process::id()));
-
-        let script_content = format!(
-            "#!/bin/bash\ncd '{}'\n{}\nrm '{}'\n",
-            home_dir.display(),
-            command,
-            script_path.display()
-        );
-
-        // Write the script file
-        if let Err(e) = std::fs::write(&script_path, script_content) {
-            eprintln!("Failed to write script file: {:?}", e);
-            return;
-        }
-
-        // Make it executable
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Err(e) =
-                std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))
-            {
-                eprintln!("Failed to set script permissions: {:?}", e);
-                return;
-            }
-        }
-
-        let script_path_str = script_path.to_string_lossy().to_string();
+        // Run the command from its own file so it never has to be quoted into AppleScript
+        let script = launcher::LaunchScript::write(
+            &format!(
+                "cd {}\n{}{}",
+                placeholders::shell_quote(&home_dir.to_string_lossy()),
+                run_config.shell_prelude(),
+                command
+            ),
+            false,
+        )?;
 
         // AppleScript that runs the script and KEEPS the terminal window open
         let applescript = format!(
             r#"tell application "Terminal"
-    do script "{}"
+    do script {}
     activate
 end tell"#,
-            script_path_str
+            launcher::applescript_string(&script.shell_word())
         );
 
-        println!("Running script via temporary file: {}", script_path_str);
+        println!(
+            "Running script via temporary file: {}",
+            script.path().display()
+        );
+        let script_path = script.launched();
 
-        std::thread::spawn(move || {
-            match Command::new("osascript")
-                .arg("-e")
-                .arg(&applescript)
-                .output()
-            {
-                Ok(output) => {
-                    println!("Terminal opened successfully");
-                    if !output.stdout.is_empty() {
-                        println!("stdout: {}", String::from_utf8_lossy(&output.stdout));
-                    }
-                    if !output.stderr.is_empty() {
-                        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
-                    }
-                }
-                Err(e) => eprintln!("Failed to open Terminal: {:?}", e),
-            }
-        });
+        std::thread::spawn(move || run_applescript(&applescript, &script_path));
     }
 
     #[cfg(target_os = "windows")]
     {
         // For Windows, open cmd with the command and keep it open
-        std::thread::spawn(
-            move || match Command::new("cmd").args(&["/K", &command]).spawn() {
+        std::thread::spawn(move || {
+            let mut cmd = Command::new("cmd");
+            cmd.args(&["/K", &command]);
+            if let Some(dir) = &run_config.working_dir {
+                cmd.current_dir(dir);
+            }
+            cmd.envs(
+                run_config
+                    .env
+                    .iter()
+                    .chain(&run_config.secrets.env)
+                    .map(|var| (&var.name, &var.value)),
+            );
+            match cmd.spawn() {
                 Ok(_) => println!("CMD opened successfully"),
                 Err(e) => eprintln!("Failed to open CMD: {:?}", e),
-            },
-        );
+            }
+        });
     }
 
     #[cfg(target_os = "linux")]
     {
-        // For Linux, try to use gnome-terminal or xterm and keep it open
-        std::thread::spawn(move || {
-            let terminal_result = Command::new("gnome-terminal")
-                .arg("--")
-                .arg("bash")
-                .arg("-c")
-                .arg(&format!("{}; exec bash", command))
-                .spawn();
-
-            match terminal_result {
-                Ok(_) => println!("Terminal opened successfully"),
-                Err(_) => {
-                    // Fallback to xterm
-                    match Command::new("xterm")
-                        .arg("-e")
-                        .arg(&format!("bash -c '{}; exec bash'", command))
-                        .spawn()
-                    {
-                        Ok(_) => println!("xterm opened successfully"),
-                        Err(e) => eprintln!("Failed to open terminal: {:?}", e),
-                    }
-                }
-            }
-        });
+        // For Linux, use the preferred terminal emulator and keep it open
+        let script = launcher::LaunchScript::write(
+            &format!("{}{}", run_config.shell_prelude(), command),
+            true,
+        )?;
+        terminal::open(script.path()).await?;
+        script.launched();
     }
+
+    Ok(())
 }
 
 // Open Terminal.app with a command (shows Terminal window)
-pub fn open_terminal_with_command(command: String) {
+pub async fn open_terminal_with_command(
+    command: String,
+    run_config: ResolvedRunConfig,
+) -> Result<(), String> {
     println!("open_terminal_with_command called with: {}", command);
 
     #[cfg(target_os = "macos")]
     {
         let home_dir = dirs::home_dir().unwrap_or_default();
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
-
-        // Create a temporary script file to avoid complex quoting issues
-        let temp_dir = std::env::temp_dir();
-        let script_path = temp_dir.join(format!("tauri_script_{}.sh", std::process::id()));
-
-        let script_content = format!("#!/bin/bash\ncd '{}'\n{}\n", home_dir.display(), command);
-
-        // Write the script file
-        if let Err(e) = std::fs::write(&script_path, script_content) {
-            eprintln!("Failed to write script file: {:?}", e);
-            return;
-        }
 
-        // Make it executable
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Err(e) =
-                std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))
-            {
-                eprintln!("Failed to set script permissions: {:?}", e);
-                return;
-            }
-        }
-
-        let script_path_str = script_path.to_string_lossy().to_string();
+        // Run the command from its own file so it never has to be quoted into AppleScript
+        let script = launcher::LaunchScript::write(
+            &format!(
+                "cd {}\n{}{}",
+                placeholders::shell_quote(&home_dir.to_string_lossy()),
+                run_config.shell_prelude(),
+                command
+            ),
+            false,
+        )?;
 
         // AppleScript that runs the script and closes the terminal window automatically
         let applescript = format!(
             r#"tell application "Terminal"
-    set newTab to do script "{}; rm '{}'; exit"
+    set newTab to do script {}
     repeat
         delay 0.1
         if not busy of newTab then
@@ -752,68 +1602,80 @@ pub fn open_terminal_with_command(command: String) {
         end if
     end repeat
 end tell"#,
-            script_path_str, script_path_str
+            launcher::applescript_string(&format!("{}; exit", script.shell_word()))
         );
 
-        println!("Running script via temporary file: {}", script_path_str);
+        println!(
+            "Running script via temporary file: {}",
+            script.path().display()
+        );
+        let script_path = script.launched();
 
-        std::thread::spawn(move || {
-            match Command::new("osascript")
-                .arg("-e")
-                .arg(&applescript)
-                .output()
-            {
-                Ok(output) => {
-                    println!("Terminal opened successfully");
-                    if !output.stdout.is_empty() {
-                        println!("stdout: {}", String::from_utf8_lossy(&output.stdout));
-                    }
-                    if !output.stderr.is_empty() {
-                        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
-                    }
-                }
-                Err(e) => eprintln!("Failed to open Terminal: {:?}", e),
-            }
-        });
+        std::thread::spawn(move || run_applescript(&applescript, &script_path));
     }
 
     #[cfg(target_os = "windows")]
     {
         // For Windows, open cmd with the command
-        std::thread::spawn(
-            move || match Command::new("cmd").args(&["/K", &command]).spawn() {
+        std::thread::spawn(move || {
+            let mut cmd = Command::new("cmd");
+            cmd.args(&["/K", &command]);
+            if let Some(dir) = &run_config.working_dir {
+                cmd.current_dir(dir);
+            }
+            cmd.envs(
+                run_config
+                    .env
+                    .iter()
+                    .chain(&run_config.secrets.env)
+                    .map(|var| (&var.name, &var.value)),
+            );
+            match cmd.spawn() {
                 Ok(_) => println!("CMD opened successfully"),
                 Err(e) => eprintln!("Failed to open CMD: {:?}", e),
-            },
-        );
+            }
+        });
     }
 
     #[cfg(target_os = "linux")]
     {
-        // For Linux, try to use gnome-terminal or xterm
-        std::thread::spawn(move || {
-            let terminal_result = Command::new("gnome-terminal")
-                .arg("--")
-                .arg("bash")
-                .arg("-c")
-                .arg(&format!("{}; exec bash", command))
-                .spawn();
-
-            match terminal_result {
-                Ok(_) => println!("Terminal opened successfully"),
-                Err(_) => {
-                    // Fallback to xterm
-                    match Command::new("xterm")
-                        .arg("-e")
-                        .arg(&format!("bash -c '{}; exec bash'", command))
-                        .spawn()
-                    {
-                        Ok(_) => println!("xterm opened successfully"),
-                        Err(e) => eprintln!("Failed to open terminal: {:?}", e),
-                    }
-                }
+        // For Linux, use the preferred terminal emulator
+        let script = launcher::LaunchScript::write(
+            &format!("{}{}", run_config.shell_prelude(), command),
+            true,
+        )?;
+        terminal::open(script.path()).await?;
+        script.launched();
+    }
+
+    Ok(())
+}
+
+// Ask Terminal.app to run a launcher script. The script deletes itself once it starts, so it
+// is only removed here when Terminal never got it
+#[cfg(target_os = "macos")]
+fn run_applescript(applescript: &str, script_path: &std::path::Path) {
+    match Command::new("osascript")
+        .arg("-e")
+        .arg(applescript)
+        .output()
+    {
+        Ok(output) => {
+            println!("Terminal opened successfully");
+            if !output.stdout.is_empty() {
+                println!("stdout: {}", String::from_utf8_lossy(&output.stdout));
             }
-        });
+            if !output.stderr.is_empty() {
+                eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+            }
+            if !output.status.success() {
+                let _ = std::fs::remove_file(script_path);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to open Terminal: {:?}", e);
+            let _ = std::fs::remove_file(script_path);
+        }
     }
 }
 
@@ -821,63 +1683,33 @@ end tell"#,
 pub fn run_terminal_command(command: String) {
     let rt_handle = RT_HANDLE.get().expect("Runtime handle not initialized");
     rt_handle.spawn(async move {
-        // Get the user's home directory
-        let home = std::env::var("HOME").unwrap_or_else(|_| {
-            dirs::home_dir()
-                .map(|p| p.to_string_lossy().to_string())
-                .unwrap_or_else(|| "/Users".to_string())
-        });
-
-        // Detect the user's shell from /etc/passwd or use zsh as default
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| {
-            std::fs::read_to_string("/etc/passwd")
-                .ok()
-                .and_then(|content| {
-                    content.lines().find_map(|line| {
-                        if line.contains(&home) {
-                            line.split(':').last().map(|s| s.to_string())
-                        } else {
-                            None
-                        }
-                    })
-                })
-                .unwrap_or_else(|| "/bin/zsh".to_string())
-        });
-
-        #[cfg(debug_assertions)]
-        println!("Using shell: {} for command: {}", shell, command);
-
-        // Build the command that sources the shell config files before running
-        let wrapped_command = if shell.contains("zsh") {
-            format!(
-                "source ~/.zshrc 2>/dev/null; source ~/.zprofile 2>/dev/null; {}",
-                command
+        let run_id = executor::new_run_id();
+        let started = std::time::Instant::now();
+        let limits = ExecutionLimits::default();
+        let output = match executor::spawn_job(
+            &run_id,
+            &command,
+            executor::build_shell_command(&command, &ResolvedRunConfig::default())
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped()),
+        ) {
+            Ok(child) => Ok(executor::run_to_completion(
+                &run_id,
+                child,
+                &limits,
+                &secrets::SecretMask::default(),
+                started,
+                |_, _| {},
             )
-        } else if shell.contains("bash") {
-            format!(
-                "source ~/.bash_profile 2>/dev/null; source ~/.bashrc 2>/dev/null; {}",
-                command
-            )
-        } else {
-            command.clone()
+            .await),
+            Err(e) => Err(e),
         };
 
-        let output = tokio::process::Command::new(&shell)
-            .arg("-l") // Login shell
-            .arg("-c")
-            .arg(&wrapped_command)
-            .env("HOME", &home) // Ensure HOME is set
-            .env(
-                "USER",
-                std::env::var("USER").unwrap_or_else(|_| whoami::username()),
-            )
-            .output()
-            .await;
-
         match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
+            Ok(outcome) => {
+                let stdout = &outcome.stdout;
+                let stderr = &outcome.stderr;
 
                 #[cfg(debug_assertions)]
                 {
@@ -891,7 +1723,7 @@ pub fn run_terminal_command(command: String) {
                 }
 
                 // Show errors in both debug and release mode
-                if !output.status.success() && !stderr.is_empty() {
+                if !outcome.success && !stderr.is_empty() {
                     eprintln!("Command '{}' failed: {}", command, stderr);
                 }
             }
@@ -951,6 +1783,28 @@ fn start_spring_boot_backend(app_handle: tauri::AppHandle, port: u16) -> Result<
     Ok(())
 }
 
+// Start the backend again on its port after it was stopped while the app kept running
+fn restart_spring_boot_backend(app_handle: tauri::AppHandle) {
+    #[cfg(not(debug_assertions))]
+    {
+        let Some(&port) = BACKEND_PORT.get() else {
+            eprintln!("Backend port not set, cannot restart Spring Boot backend");
+            return;
+        };
+        std::thread::spawn(move || {
+            if let Err(e) = start_spring_boot_backend(app_handle, port) {
+                eprintln!("Failed to restart Spring Boot backend: {}", e);
+            }
+        });
+    }
+
+    #[cfg(debug_assertions)]
+    {
+        let _ = app_handle;
+        println!("Development mode: Please restart Spring Boot from IntelliJ");
+    }
+}
+
 fn check_backend_health_sync() -> Result<bool, String> {
     let rt =
         tokio::runtime::Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
@@ -1116,6 +1970,7 @@ pub fn init_db(app_handle: &tauri::AppHandle) -> Result<(), String> {
 
     // Use a separate thread to avoid async runtime conflicts
     let database_url_clone = database_url.clone();
+    let db_path_clone = db_path.clone();
     std::thread::spawn(move || {
         // Ensure DATABASE_URL is set in this thread too
         std::env::set_var("DATABASE_URL", &database_url_clone);
@@ -1158,25 +2013,18 @@ pub fn init_db(app_handle: &tauri::AppHandle) -> Result<(), String> {
                 panic!("Failed to enable foreign key constraints!");
             }
 
-            // Always run schema sync to ensure database matches current schema
-            // This allows automatic schema updates when the app is updated
-            println!("Syncing database schema...");
-            client
-                ._db_push()
-                .accept_data_loss()
-                .await
-                .expect("Failed to sync database schema");
-            println!("Database schema synced successfully");
+            // Apply the migrations in prisma/migrations this database has not seen yet
+            println!("Migrating database schema...");
+            db::migrations::run(&client, &db_path_clone).await?;
 
-            PRISMA_CLIENT
-                .set(client)
-                .expect("Failed to set Prisma client");
+            db::get_db::set_db(client);
 
             println!("Database initialized successfully");
-        });
+            Ok::<(), String>(())
+        })
     })
     .join()
-    .map_err(|_| "Failed to initialize database".to_string())?;
+    .map_err(|_| "Failed to initialize database".to_string())??;
 
     Ok(())
 }
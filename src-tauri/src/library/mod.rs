@@ -0,0 +1,948 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::backup::{self, BackupReason};
+use crate::db::repository::library_repository::{Library, LibraryRepository, ScriptContent};
+
+/// Written into every archive; archives from newer versions are refused.
+pub const FORMAT: &str = "shell-script-manager-library";
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+// Markdown scripts refer to pasted images as `images/<file name>`
+const IMAGE_PREFIX: &str = "images/";
+const SYSTEM_LEVEL: &str = "SYSTEM";
+// An import runs in one transaction, which the query engine otherwise aborts after 5 seconds
+const IMPORT_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+// File name and contents of the images read from an archive
+type Images = Vec<(String, Vec<u8>)>;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub format: String,
+    pub version: u32,
+    /// Epoch milliseconds.
+    pub exported_at: f64,
+    pub workspaces: Vec<WorkspaceEntry>,
+    pub folders: Vec<FolderEntry>,
+    pub scripts: Vec<ScriptEntry>,
+    pub workspace_folders: Vec<WorkspaceFolderEntry>,
+    pub folder_folders: Vec<FolderFolderEntry>,
+    pub folder_scripts: Vec<FolderScriptEntry>,
+    /// File names under `images/` in the archive.
+    pub images: Vec<String>,
+}
+
+// Ids are those of the exporting database and only link the entries of one manifest together
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceEntry {
+    pub id: i32,
+    pub name: String,
+    pub ordering: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderEntry {
+    pub id: i32,
+    pub name: String,
+    pub ordering: i32,
+    /// Enum values: SYSTEM, USER
+    pub system_level: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptEntry {
+    pub id: i32,
+    pub name: String,
+    pub command: String,
+    pub ordering: i32,
+    pub is_markdown: bool,
+    pub locked: bool,
+    pub show_shell: bool,
+    pub interpreter: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceFolderEntry {
+    pub workspace_id: i32,
+    pub folder_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderFolderEntry {
+    pub parent_folder_id: i32,
+    pub child_folder_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderScriptEntry {
+    pub folder_id: i32,
+    pub script_id: i32,
+}
+
+/// What happens to the library already in the database.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportStrategy {
+    /// Workspaces and folders with the same name in the same place are reused, and scripts with
+    /// the same name in the same folder are overwritten by the imported ones.
+    Merge,
+    /// Everything but system folders is deleted first.
+    Replace,
+    /// Like `Merge`, but scripts that already exist are left as they are.
+    SkipDuplicates,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub workspaces: usize,
+    pub folders: usize,
+    pub scripts: usize,
+    pub images: usize,
+    /// Images scripts refer to that are not in the images directory.
+    pub missing_images: Vec<String>,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub workspaces_created: usize,
+    pub folders_created: usize,
+    pub scripts_created: usize,
+    pub scripts_updated: usize,
+    pub scripts_skipped: usize,
+    pub images_copied: usize,
+    /// Images stored under a new name because a different image had theirs; the scripts that
+    /// show them were updated.
+    pub images_renamed: usize,
+    /// The snapshot taken before the import.
+    pub backup_path: Option<String>,
+}
+
+/// Write the whole library and the images its scripts show to a zip archive at `path`.
+/// Scripts that are in no folder are not visible in the app and are left out.
+pub async fn export(path: &Path, images_dir: &Path) -> Result<ExportSummary, String> {
+    let library = LibraryRepository::new()
+        .get_library()
+        .await
+        .map_err(|e| format!("Failed to load the library: {}", e))?;
+    let mut manifest = manifest(library);
+
+    let mut images = vec![];
+    let mut missing_images = vec![];
+    let mut names: Vec<String> = manifest
+        .scripts
+        .iter()
+        .flat_map(|script| {
+            image_references(&script.command)
+                .into_iter()
+                .map(|(start, end)| script.command[start..end].to_string())
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let file = images_dir.join(&name);
+        if file.is_file() {
+            images.push((name, file));
+        } else {
+            missing_images.push(name);
+        }
+    }
+    manifest.images = images.iter().map(|(name, _)| name.clone()).collect();
+
+    let summary = ExportSummary {
+        workspaces: manifest.workspaces.len(),
+        folders: manifest.folders.len(),
+        scripts: manifest.scripts.len(),
+        images: images.len(),
+        missing_images,
+    };
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || write_archive(&path, &manifest, &images))
+        .await
+        .map_err(|e| format!("Export task failed: {}", e))??;
+    Ok(summary)
+}
+
+/// Add the library in the archive at `path` to the database, giving every row a new id, and
+/// copy its images into `images_dir`. The database is backed up first, and the import happens in
+/// one transaction: when it fails, the library is left as it was.
+pub async fn import(
+    path: &Path,
+    images_dir: &Path,
+    db_path: &str,
+    strategy: ImportStrategy,
+) -> Result<ImportSummary, String> {
+    let archive = path.to_path_buf();
+    let (manifest, images) = tokio::task::spawn_blocking(move || read_archive(&archive))
+        .await
+        .map_err(|e| format!("Import task failed: {}", e))??;
+    validate(&manifest)?;
+
+    let backup = backup::create(db_path, BackupReason::PreImport).await?;
+    let mut summary = ImportSummary {
+        backup_path: Some(backup.path),
+        ..Default::default()
+    };
+
+    let renames = store_images(images_dir, images, &mut summary)?;
+    let (transaction, client) = crate::db::get_db::get_db()
+        ._transaction()
+        .with_timeout(IMPORT_TIMEOUT_MS)
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start the import: {}", e))?;
+    let imported = import_rows(
+        LibraryRepository::with_client(&client),
+        &manifest,
+        &renames,
+        strategy,
+        summary,
+    )
+    .await;
+    match imported {
+        Ok(summary) => {
+            transaction
+                .commit(client)
+                .await
+                .map_err(|e| format!("Failed to save the import: {}", e))?;
+            Ok(summary)
+        }
+        Err(e) => {
+            if let Err(rollback) = transaction.rollback(client).await {
+                eprintln!("Failed to roll back the import: {}", rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
+async fn import_rows(
+    repository: LibraryRepository<'_>,
+    manifest: &Manifest,
+    renames: &HashMap<String, String>,
+    strategy: ImportStrategy,
+    summary: ImportSummary,
+) -> Result<ImportSummary, String> {
+    if strategy == ImportStrategy::Replace {
+        repository
+            .clear_library()
+            .await
+            .map_err(|e| format!("Failed to clear the library: {}", e))?;
+    }
+    let library = repository
+        .get_library()
+        .await
+        .map_err(|e| format!("Failed to load the library: {}", e))?;
+
+    Importer {
+        repository,
+        strategy,
+        existing: Existing::new(&library),
+        offsets: Offsets::new(&library),
+        workspaces: HashMap::new(),
+        folders: HashMap::new(),
+        scripts: HashMap::new(),
+        summary,
+    }
+    .run(manifest, renames)
+    .await
+}
+
+fn manifest(library: Library) -> Manifest {
+    let listed: HashSet<i32> = library
+        .folder_scripts
+        .iter()
+        .map(|rel| rel.shell_script_id)
+        .collect();
+    Manifest {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        exported_at: chrono::Utc::now().timestamp_millis() as f64,
+        workspaces: library
+            .workspaces
+            .into_iter()
+            .map(|workspace| WorkspaceEntry {
+                id: workspace.id,
+                name: workspace.name,
+                ordering: workspace.ordering,
+            })
+            .collect(),
+        folders: library
+            .folders
+            .into_iter()
+            .map(|folder| FolderEntry {
+                id: folder.id,
+                name: folder.name,
+                ordering: folder.ordering,
+                system_level: folder.system_level,
+            })
+            .collect(),
+        scripts: library
+            .scripts
+            .into_iter()
+            .filter(|script| listed.contains(&script.id))
+            .map(|script| ScriptEntry {
+                id: script.id,
+                name: script.name,
+                command: script.command,
+                ordering: script.ordering,
+                is_markdown: script.is_markdown,
+                locked: script.locked,
+                show_shell: script.show_shell,
+                interpreter: script.interpreter,
+            })
+            .collect(),
+        workspace_folders: library
+            .workspace_folders
+            .into_iter()
+            .map(|rel| WorkspaceFolderEntry {
+                workspace_id: rel.workspace_id,
+                folder_id: rel.folder_id,
+            })
+            .collect(),
+        folder_folders: library
+            .folder_folders
+            .into_iter()
+            .map(|rel| FolderFolderEntry {
+                parent_folder_id: rel.parent_folder_id,
+                child_folder_id: rel.child_folder_id,
+            })
+            .collect(),
+        folder_scripts: library
+            .folder_scripts
+            .into_iter()
+            .map(|rel| FolderScriptEntry {
+                folder_id: rel.scripts_folder_id,
+                script_id: rel.shell_script_id,
+            })
+            .collect(),
+        images: vec![],
+    }
+}
+
+fn write_archive(
+    path: &Path,
+    manifest: &Manifest,
+    images: &[(String, PathBuf)],
+) -> Result<(), String> {
+    let partial = path.with_file_name(format!(
+        "{}.partial",
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    ));
+    let result = write_zip(&partial, manifest, images).and_then(|_| {
+        std::fs::rename(&partial, path)
+            .map_err(|e| format!("Failed to move archive to {}: {}", path.display(), e))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    result
+}
+
+fn write_zip(path: &Path, manifest: &Manifest, images: &[(String, PathBuf)]) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let compressed = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    // Pasted images are PNGs, which do not compress any further
+    let stored =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    let json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    zip.start_file(MANIFEST_NAME, compressed)
+        .and_then(|_| zip.write_all(&json).map_err(Into::into))
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+    for (name, file) in images {
+        let data =
+            std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        zip.start_file(format!("{}{}", IMAGE_PREFIX, name), stored)
+            .and_then(|_| zip.write_all(&data).map_err(Into::into))
+            .map_err(|e| format!("Failed to write image {}: {}", name, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn read_archive(path: &Path) -> Result<(Manifest, Images), String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut zip = zip::ZipArchive::new(file)
+        .map_err(|e| format!("{} is not a zip archive: {}", path.display(), e))?;
+
+    let mut json = String::new();
+    zip.by_name(MANIFEST_NAME)
+        .map_err(|e| format!("{} has no {}: {}", path.display(), MANIFEST_NAME, e))?
+        .read_to_string(&mut json)
+        .map_err(|e| format!("Failed to read {}: {}", MANIFEST_NAME, e))?;
+    let manifest: Manifest = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {}: {}", MANIFEST_NAME, e))?;
+    if manifest.format != FORMAT {
+        return Err(format!("{} is not a script library export", path.display()));
+    }
+    if manifest.version == 0 || manifest.version > FORMAT_VERSION {
+        return Err(format!(
+            "The archive has format version {}, this app reads up to version {}",
+            manifest.version, FORMAT_VERSION
+        ));
+    }
+
+    let mut images = vec![];
+    for name in &manifest.images {
+        // Only plain names, so nothing is written outside the images directory
+        if !is_plain_file_name(name) {
+            return Err(format!("Invalid image name in the archive: {}", name));
+        }
+        let mut data = vec![];
+        zip.by_name(&format!("{}{}", IMAGE_PREFIX, name))
+            .map_err(|e| format!("Image {} is missing from the archive: {}", name, e))?
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read image {}: {}", name, e))?;
+        images.push((name.clone(), data));
+    }
+    Ok((manifest, images))
+}
+
+// Every relation has to point at entries of the same manifest
+fn validate(manifest: &Manifest) -> Result<(), String> {
+    fn ids<'a>(kind: &str, ids: impl Iterator<Item = &'a i32>) -> Result<HashSet<i32>, String> {
+        let mut seen = HashSet::new();
+        for id in ids {
+            if !seen.insert(*id) {
+                return Err(format!("The archive lists {} {} twice", kind, id));
+            }
+        }
+        Ok(seen)
+    }
+    let workspaces = ids("workspace", manifest.workspaces.iter().map(|w| &w.id))?;
+    let folders = ids("folder", manifest.folders.iter().map(|f| &f.id))?;
+    let scripts = ids("script", manifest.scripts.iter().map(|s| &s.id))?;
+
+    let check = |kind: &str, known: &HashSet<i32>, id: i32| {
+        if known.contains(&id) {
+            Ok(())
+        } else {
+            Err(format!(
+                "The archive refers to {} {}, which it does not contain",
+                kind, id
+            ))
+        }
+    };
+    for rel in &manifest.workspace_folders {
+        check("workspace", &workspaces, rel.workspace_id)?;
+        check("folder", &folders, rel.folder_id)?;
+    }
+    for rel in &manifest.folder_folders {
+        check("folder", &folders, rel.parent_folder_id)?;
+        check("folder", &folders, rel.child_folder_id)?;
+    }
+    for rel in &manifest.folder_scripts {
+        check("folder", &folders, rel.folder_id)?;
+        check("script", &scripts, rel.script_id)?;
+    }
+
+    // Folders are imported after the parent they are placed under, which has to lead to a
+    // workspace or the top level eventually
+    for folder in &manifest.folders {
+        let mut seen = HashSet::new();
+        let mut id = folder.id;
+        while !manifest
+            .workspace_folders
+            .iter()
+            .any(|rel| rel.folder_id == id)
+        {
+            if !seen.insert(id) {
+                return Err("The folders in the archive contain each other".to_string());
+            }
+            match manifest
+                .folder_folders
+                .iter()
+                .find(|rel| rel.child_folder_id == id)
+            {
+                Some(rel) => id = rel.parent_folder_id,
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
+
+// An image whose name is taken by a different file goes to the first free `<stem>-<n>.<ext>`,
+// unless one of those already holds the same bytes; returns old name -> new name
+fn store_images(
+    images_dir: &Path,
+    images: Images,
+    summary: &mut ImportSummary,
+) -> Result<HashMap<String, String>, String> {
+    std::fs::create_dir_all(images_dir)
+        .map_err(|e| format!("Failed to create images directory: {}", e))?;
+    let mut renames = HashMap::new();
+    for (name, data) in images {
+        let (stored, copied) = store_image(images_dir, &name, &data)?;
+        if copied {
+            summary.images_copied += 1;
+        }
+        if stored != name {
+            renames.insert(name, stored);
+            summary.images_renamed += 1;
+        }
+    }
+    Ok(renames)
+}
+
+fn store_image(images_dir: &Path, name: &str, data: &[u8]) -> Result<(String, bool), String> {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    loop {
+        let path = images_dir.join(&candidate);
+        match std::fs::read(&path) {
+            Ok(existing) if existing == data => return Ok((candidate, false)),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                std::fs::write(&path, data)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                return Ok((candidate, true));
+            }
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+        n += 1;
+        candidate = format!("{}-{}{}", stem, n, extension);
+    }
+}
+
+/// Byte ranges of the file names in `images/<file name>` references.
+fn image_references(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = vec![];
+    for (index, _) in text.match_indices(IMAGE_PREFIX) {
+        // Not the tail of a longer path such as `docs/images/`
+        let starts_path = text[..index]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || matches!(c, '/' | '.' | '_' | '-')));
+        if !starts_path {
+            continue;
+        }
+        let start = index + IMAGE_PREFIX.len();
+        let end = text[start..]
+            .find(|c: char| {
+                c.is_whitespace()
+                    || matches!(
+                        c,
+                        '(' | ')'
+                            | '['
+                            | ']'
+                            | '<'
+                            | '>'
+                            | '"'
+                            | '\''
+                            | '`'
+                            | '?'
+                            | '#'
+                            | '/'
+                            | '\\'
+                    )
+            })
+            .map_or(text.len(), |offset| start + offset);
+        if !text[end..].starts_with('/') && is_plain_file_name(&text[start..end]) {
+            spans.push((start, end));
+        }
+    }
+    spans
+}
+
+fn rewrite_images(text: &str, renames: &HashMap<String, String>) -> String {
+    let mut rewritten = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, end) in image_references(text) {
+        if let Some(new_name) = renames.get(&text[start..end]) {
+            rewritten.push_str(&text[copied..start]);
+            rewritten.push_str(new_name);
+            copied = end;
+        }
+    }
+    rewritten.push_str(&text[copied..]);
+    rewritten
+}
+
+fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':'])
+        && Path::new(name)
+            .file_name()
+            .is_some_and(|file_name| file_name == name)
+}
+
+#[derive(Clone, Copy)]
+enum Parent {
+    Workspace(i32),
+    Folder(i32),
+}
+
+// The library in the database, kept current while the import adds to it
+struct Existing {
+    workspaces: Vec<(i32, String)>,
+    /// id -> (name, system level)
+    folders: HashMap<i32, (String, String)>,
+    scripts: HashMap<i32, String>,
+    workspace_folders: HashSet<(i32, i32)>,
+    folder_folders: HashSet<(i32, i32)>,
+    folder_scripts: HashSet<(i32, i32)>,
+}
+
+impl Existing {
+    fn new(library: &Library) -> Self {
+        Self {
+            workspaces: library
+                .workspaces
+                .iter()
+                .map(|workspace| (workspace.id, workspace.name.clone()))
+                .collect(),
+            folders: library
+                .folders
+                .iter()
+                .map(|folder| {
+                    (
+                        folder.id,
+                        (folder.name.clone(), folder.system_level.clone()),
+                    )
+                })
+                .collect(),
+            scripts: library
+                .scripts
+                .iter()
+                .map(|script| (script.id, script.name.clone()))
+                .collect(),
+            workspace_folders: library
+                .workspace_folders
+                .iter()
+                .map(|rel| (rel.workspace_id, rel.folder_id))
+                .collect(),
+            folder_folders: library
+                .folder_folders
+                .iter()
+                .map(|rel| (rel.parent_folder_id, rel.child_folder_id))
+                .collect(),
+            folder_scripts: library
+                .folder_scripts
+                .iter()
+                .map(|rel| (rel.scripts_folder_id, rel.shell_script_id))
+                .collect(),
+        }
+    }
+
+    fn workspace_named(&self, name: &str) -> Option<i32> {
+        self.workspaces
+            .iter()
+            .filter(|(_, existing)| existing == name)
+            .map(|(id, _)| *id)
+            .min()
+    }
+
+    // System folders are unique by name; others have to sit in the same place
+    fn folder_named(&self, name: &str, system_level: &str, parent: Option<Parent>) -> Option<i32> {
+        self.folders
+            .iter()
+            .filter(|(_, (existing, level))| existing == name && level == system_level)
+            .map(|(id, _)| *id)
+            .filter(|id| match (system_level == SYSTEM_LEVEL, parent) {
+                (true, _) => true,
+                (false, Some(Parent::Workspace(workspace_id))) => {
+                    self.workspace_folders.contains(&(workspace_id, *id))
+                }
+                (false, Some(Parent::Folder(folder_id))) => {
+                    self.folder_folders.contains(&(folder_id, *id))
+                }
+                (false, None) => {
+                    !self.workspace_folders.iter().any(|(_, child)| child == id)
+                        && !self.folder_folders.iter().any(|(_, child)| child == id)
+                }
+            })
+            .min()
+    }
+
+    fn script_named(&self, folder_id: i32, name: &str) -> Option<i32> {
+        self.folder_scripts
+            .iter()
+            .filter(|(folder, script)| {
+                *folder == folder_id && self.scripts.get(script).is_some_and(|n| n == name)
+            })
+            .map(|(_, script)| *script)
+            .min()
+    }
+}
+
+// Imported rows are ordered after the existing ones, in the order they had
+struct Offsets {
+    workspace: i32,
+    folder: i32,
+    script: i32,
+}
+
+impl Offsets {
+    fn new(library: &Library) -> Self {
+        fn after(orderings: impl Iterator<Item = i32>) -> i32 {
+            orderings.max().map_or(0, |max| max + 1)
+        }
+        Self {
+            workspace: after(library.workspaces.iter().map(|w| w.ordering)),
+            folder: after(library.folders.iter().map(|f| f.ordering)),
+            script: after(library.scripts.iter().map(|s| s.ordering)),
+        }
+    }
+}
+
+struct Importer<'a> {
+    repository: LibraryRepository<'a>,
+    strategy: ImportStrategy,
+    existing: Existing,
+    offsets: Offsets,
+    // Manifest id -> database id
+    workspaces: HashMap<i32, i32>,
+    folders: HashMap<i32, i32>,
+    scripts: HashMap<i32, i32>,
+    summary: ImportSummary,
+}
+
+impl Importer<'_> {
+    async fn run(
+        mut self,
+        manifest: &Manifest,
+        renames: &HashMap<String, String>,
+    ) -> Result<ImportSummary, String> {
+        let mut workspaces: Vec<&WorkspaceEntry> = manifest.workspaces.iter().collect();
+        workspaces.sort_by_key(|workspace| workspace.ordering);
+        for workspace in workspaces {
+            self.import_workspace(workspace).await?;
+        }
+
+        // Parents first, so folders can be matched by where they sit
+        let mut remaining: Vec<&FolderEntry> = manifest.folders.iter().collect();
+        remaining.sort_by_key(|folder| folder.ordering);
+        while !remaining.is_empty() {
+            let mut waiting = vec![];
+            for folder in &remaining {
+                match self.parent(manifest, folder) {
+                    Some(parent) => self.import_folder(folder, parent).await?,
+                    None => waiting.push(*folder),
+                }
+            }
+            if waiting.len() == remaining.len() {
+                return Err("The folders in the archive contain each other".to_string());
+            }
+            remaining = waiting;
+        }
+
+        for rel in &manifest.workspace_folders {
+            let link = (
+                self.workspaces[&rel.workspace_id],
+                self.folders[&rel.folder_id],
+            );
+            if self.existing.workspace_folders.insert(link) {
+                self.repository
+                    .link_workspace_folder(link.0, link.1)
+                    .await
+                    .map_err(|e| format!("Failed to add a folder to a workspace: {}", e))?;
+            }
+        }
+        for rel in &manifest.folder_folders {
+            let link = (
+                self.folders[&rel.parent_folder_id],
+                self.folders[&rel.child_folder_id],
+            );
+            if self.existing.folder_folders.insert(link) {
+                self.repository
+                    .link_folder_folder(link.0, link.1)
+                    .await
+                    .map_err(|e| format!("Failed to nest a folder: {}", e))?;
+            }
+        }
+
+        let scripts: HashMap<i32, &ScriptEntry> = manifest
+            .scripts
+            .iter()
+            .map(|script| (script.id, script))
+            .collect();
+        let mut folder_scripts: Vec<&FolderScriptEntry> = manifest.folder_scripts.iter().collect();
+        folder_scripts.sort_by_key(|rel| scripts[&rel.script_id].ordering);
+        for rel in folder_scripts {
+            self.import_script(
+                scripts[&rel.script_id],
+                self.folders[&rel.folder_id],
+                renames,
+            )
+            .await?;
+        }
+        Ok(self.summary)
+    }
+
+    // `None` until the parent folder has been imported
+    fn parent(&self, manifest: &Manifest, folder: &FolderEntry) -> Option<Option<Parent>> {
+        if let Some(rel) = manifest
+            .workspace_folders
+            .iter()
+            .find(|rel| rel.folder_id == folder.id)
+        {
+            return Some(Some(Parent::Workspace(self.workspaces[&rel.workspace_id])));
+        }
+        match manifest
+            .folder_folders
+            .iter()
+            .find(|rel| rel.child_folder_id == folder.id)
+        {
+            Some(rel) => self
+                .folders
+                .get(&rel.parent_folder_id)
+                .map(|id| Some(Parent::Folder(*id))),
+            None => Some(None),
+        }
+    }
+
+    async fn import_workspace(&mut self, workspace: &WorkspaceEntry) -> Result<(), String> {
+        let existing = match self.strategy {
+            ImportStrategy::Replace => None,
+            _ => self.existing.workspace_named(&workspace.name),
+        };
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = self
+                    .repository
+                    .create_workspace(
+                        workspace.name.clone(),
+                        workspace.ordering + self.offsets.workspace,
+                    )
+                    .await
+                    .map_err(|e| format!("Failed to create workspace {}: {}", workspace.name, e))?;
+                self.existing.workspaces.push((id, workspace.name.clone()));
+                self.summary.workspaces_created += 1;
+                id
+            }
+        };
+        self.workspaces.insert(workspace.id, id);
+        Ok(())
+    }
+
+    async fn import_folder(
+        &mut self,
+        folder: &FolderEntry,
+        parent: Option<Parent>,
+    ) -> Result<(), String> {
+        // Replacing keeps the system folders, so those are still matched
+        let existing = match self.strategy {
+            ImportStrategy::Replace if folder.system_level != SYSTEM_LEVEL => None,
+            _ => self
+                .existing
+                .folder_named(&folder.name, &folder.system_level, parent),
+        };
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = self
+                    .repository
+                    .create_folder(
+                        folder.name.clone(),
+                        folder.ordering + self.offsets.folder,
+                        folder.system_level.clone(),
+                    )
+                    .await
+                    .map_err(|e| format!("Failed to create folder {}: {}", folder.name, e))?;
+                self.existing
+                    .folders
+                    .insert(id, (folder.name.clone(), folder.system_level.clone()));
+                self.summary.folders_created += 1;
+                id
+            }
+        };
+        self.folders.insert(folder.id, id);
+        Ok(())
+    }
+
+    async fn import_script(
+        &mut self,
+        script: &ScriptEntry,
+        folder_id: i32,
+        renames: &HashMap<String, String>,
+    ) -> Result<(), String> {
+        // A script in several folders is imported once and linked to each
+        let id = match self.scripts.get(&script.id) {
+            Some(id) => *id,
+            None => {
+                let existing = match self.strategy {
+                    ImportStrategy::Replace => None,
+                    _ => self.existing.script_named(folder_id, &script.name),
+                };
+                let content = ScriptContent {
+                    name: script.name.clone(),
+                    command: rewrite_images(&script.command, renames),
+                    is_markdown: script.is_markdown,
+                    locked: script.locked,
+                    show_shell: script.show_shell,
+                    interpreter: script.interpreter.clone(),
+                };
+                let id = match existing {
+                    Some(id) if self.strategy == ImportStrategy::SkipDuplicates => {
+                        self.summary.scripts_skipped += 1;
+                        id
+                    }
+                    Some(id) => {
+                        self.repository
+                            .update_script_content(id, content)
+                            .await
+                            .map_err(|e| {
+                                format!("Failed to update script {}: {}", script.name, e)
+                            })?;
+                        self.summary.scripts_updated += 1;
+                        id
+                    }
+                    None => {
+                        let id = self
+                            .repository
+                            .create_script(content, script.ordering + self.offsets.script)
+                            .await
+                            .map_err(|e| {
+                                format!("Failed to create script {}: {}", script.name, e)
+                            })?;
+                        self.existing.scripts.insert(id, script.name.clone());
+                        self.summary.scripts_created += 1;
+                        id
+                    }
+                };
+                self.scripts.insert(script.id, id);
+                id
+            }
+        };
+        if self.existing.folder_scripts.insert((folder_id, id)) {
+            self.repository
+                .link_folder_script(folder_id, id)
+                .await
+                .map_err(|e| {
+                    format!("Failed to add script {} to its folder: {}", script.name, e)
+                })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1140 @@
+#[cfg(test)]
+mod tests;
+
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::backup;
+use crate::db::repository::app_state_repository::AppStateRepository;
+use crate::db::repository::library_repository::{Library, LibraryRepository, ScriptContent};
+use crate::db::repository::mirror_repository::MirrorRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::MIRROR;
+
+/// Emitted after a sync that changed the library, so open views can reload it.
+pub const MIRROR_SYNCED_EVENT: &str = "mirror-synced";
+/// Emitted with the [`MirrorStatus`] whenever the conflicts change.
+pub const MIRROR_CONFLICTS_EVENT: &str = "mirror-conflicts";
+
+// Scripts are edited through the Spring backend, which does not tell us, so the library is
+// compared with the directory on this interval as well as whenever a file changes
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const SHEBANG: &str = "#!";
+
+/// Keeps a directory in sync with the library: a directory per workspace and folder, and a
+/// `.sh` or `.md` file per script whose front-matter header carries its id and ordering.
+#[derive(Default)]
+pub struct Mirror {
+    state: Mutex<MirrorState>,
+    // One sync at a time; the watcher, the poll loop and commands all start them
+    sync: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct MirrorState {
+    dir: Option<PathBuf>,
+    // Dropping the debouncer stops the watcher
+    watcher: Option<Debouncer<RecommendedWatcher>>,
+    last_synced_at: Option<f64>,
+    error: Option<String>,
+    conflicts: Vec<MirrorConflict>,
+    // Chosen by the user and applied by the next sync
+    resolutions: HashMap<i32, MirrorSide>,
+}
+
+#[derive(Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorConflict {
+    pub script_id: i32,
+    pub name: String,
+    /// Relative to the mirror directory.
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConflictKind {
+    /// The script and its file were both changed since the last sync.
+    BothChanged,
+    /// The file was deleted and the script changed.
+    DeletedInDirectory,
+    /// The script was deleted and the file changed.
+    DeletedInLibrary,
+    /// The directory already had a different file for the script before its first sync.
+    NotSynced,
+}
+
+/// Which version a conflict is resolved with.
+#[derive(Clone, Copy, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MirrorSide {
+    Library,
+    Directory,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorStatus {
+    pub dir: Option<String>,
+    /// Epoch milliseconds.
+    pub last_synced_at: Option<f64>,
+    /// Why the last sync failed, e.g. the directory is gone.
+    pub error: Option<String>,
+    pub conflicts: Vec<MirrorConflict>,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Files written or moved to match the library.
+    pub files_written: usize,
+    pub files_deleted: usize,
+    /// Scripts updated from their edited, renamed or moved file.
+    pub scripts_updated: usize,
+    pub scripts_created: usize,
+    pub scripts_deleted: usize,
+    pub conflicts: usize,
+    /// Files left alone, with the reason.
+    pub skipped: Vec<String>,
+}
+
+impl SyncReport {
+    fn changed_library(&self) -> bool {
+        self.scripts_updated + self.scripts_created + self.scripts_deleted > 0
+    }
+}
+
+/// Resume mirroring to the stored directory and keep it in sync while the app runs.
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        match AppStateRepository::new().get_app_state().await {
+            Ok(state) => {
+                if let Some(dir) = state.and_then(|state| state.mirror_dir) {
+                    activate(&app, Some(PathBuf::from(dir)));
+                }
+            }
+            Err(e) => eprintln!("Failed to load the mirror directory: {}", e),
+        }
+        let mut last_error = None;
+        loop {
+            if mirror().is_ok_and(|mirror| mirror.state.lock().unwrap().dir.is_some()) {
+                // Only new errors, the same one would otherwise be printed every few seconds
+                let error = sync(&app).await.err();
+                if let Some(e) = error.as_ref().filter(|e| Some(*e) != last_error.as_ref()) {
+                    eprintln!("Library mirror sync failed: {}", e);
+                }
+                last_error = error;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+pub fn status() -> Result<MirrorStatus, String> {
+    let state = mirror()?.state.lock().unwrap();
+    Ok(MirrorStatus {
+        dir: state
+            .dir
+            .as_ref()
+            .map(|dir| dir.to_string_lossy().into_owned()),
+        last_synced_at: state.last_synced_at,
+        error: state.error.clone(),
+        conflicts: state.conflicts.clone(),
+    })
+}
+
+/// Mirror the library to `dir`, or stop mirroring when `None`. Files already in the directory,
+/// e.g. a clone of another mirror, are merged with the library on the first sync; those that
+/// differ from their script are reported as conflicts.
+pub async fn set_dir(app: &tauri::AppHandle, dir: Option<String>) -> Result<MirrorStatus, String> {
+    let dir = dir
+        .map(|dir| dir.trim().to_string())
+        .filter(|dir| !dir.is_empty());
+    if let Some(dir) = &dir {
+        if !Path::new(dir).is_absolute() {
+            return Err(format!("'{}' is not an absolute directory", dir));
+        }
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+    }
+
+    let mirror = mirror()?;
+    {
+        let _sync = mirror.sync.lock().await;
+        let _database = backup::use_database().await;
+        AppStateRepository::new()
+            .set_mirror_dir(dir.clone())
+            .await
+            .map_err(|e| format!("Failed to save the mirror directory: {}", e))?;
+        // Start over: what was synced before says nothing about the directory as it is now
+        MirrorRepository::new()
+            .clear()
+            .await
+            .map_err(|e| format!("Failed to reset the mirror: {}", e))?;
+        activate(app, dir.as_ref().map(PathBuf::from));
+    }
+    match &dir {
+        Some(dir) => {
+            println!("Mirroring the library to {}", dir);
+            sync(app).await?;
+        }
+        None => println!("Stopped mirroring the library"),
+    }
+    status()
+}
+
+/// Bring the library and the directory in line with each other.
+pub async fn sync(app: &tauri::AppHandle) -> Result<SyncReport, String> {
+    let mirror = mirror()?;
+    let _sync = mirror.sync.lock().await;
+    let _database = backup::use_database().await;
+    let (dir, resolutions) = {
+        let state = mirror.state.lock().unwrap();
+        (state.dir.clone(), state.resolutions.clone())
+    };
+    let dir = dir.ok_or_else(|| "The library is not mirrored to a directory".to_string())?;
+
+    let result = Pass::new(dir, resolutions.clone()).run().await;
+    let mut state = mirror.state.lock().unwrap();
+    let (report, conflicts) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            state.error = Some(e.clone());
+            return Err(e);
+        }
+    };
+    state
+        .resolutions
+        .retain(|script_id, _| !resolutions.contains_key(script_id));
+    state.error = None;
+    state.last_synced_at = Some(crate::jobs::now_ms());
+    let conflicts_changed = state.conflicts != conflicts;
+    state.conflicts = conflicts;
+    drop(state);
+
+    if report.changed_library() {
+        println!(
+            "Library mirror: {} script(s) updated, {} created, {} deleted from the directory",
+            report.scripts_updated, report.scripts_created, report.scripts_deleted
+        );
+        if let Err(e) = app.emit(MIRROR_SYNCED_EVENT, ()) {
+            eprintln!("Failed to emit {}: {}", MIRROR_SYNCED_EVENT, e);
+        }
+    }
+    if conflicts_changed {
+        if let Err(e) = app.emit(MIRROR_CONFLICTS_EVENT, status()?) {
+            eprintln!("Failed to emit {}: {}", MIRROR_CONFLICTS_EVENT, e);
+        }
+    }
+    Ok(report)
+}
+
+/// Settle a conflict by keeping one side, which the other is then overwritten with.
+pub async fn resolve(
+    app: &tauri::AppHandle,
+    script_id: i32,
+    keep: MirrorSide,
+) -> Result<SyncReport, String> {
+    {
+        let mut state = mirror()?.state.lock().unwrap();
+        if !state
+            .conflicts
+            .iter()
+            .any(|conflict| conflict.script_id == script_id)
+        {
+            return Err(format!("Script {} has no mirror conflict", script_id));
+        }
+        state.resolutions.insert(script_id, keep);
+    }
+    sync(app).await
+}
+
+// Switch the watcher to `dir`; the caller holds the sync lock
+fn activate(app: &tauri::AppHandle, dir: Option<PathBuf>) {
+    let Ok(mirror) = mirror() else {
+        return;
+    };
+    let mut state = mirror.state.lock().unwrap();
+    *state = MirrorState::default();
+    if let Some(dir) = dir {
+        match watch(app, &dir) {
+            Ok(watcher) => state.watcher = Some(watcher),
+            Err(e) => {
+                eprintln!("Library mirror: {}", e);
+                state.error = Some(e);
+            }
+        }
+        state.dir = Some(dir);
+    }
+}
+
+fn watch(app: &tauri::AppHandle, dir: &Path) -> Result<Debouncer<RecommendedWatcher>, String> {
+    // Events may report the resolved path (e.g. /private/var on macOS)
+    let resolved_dir =
+        std::fs::canonicalize(dir).map_err(|e| format!("Cannot watch {}: {}", dir.display(), e))?;
+    let base_dir = dir.to_path_buf();
+    let watched_dir = resolved_dir.clone();
+    let app = app.clone();
+    let handler = move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            // Git and most editors keep their own files in hidden directories
+            let relevant = events.iter().any(|event| {
+                event
+                    .path
+                    .strip_prefix(&resolved_dir)
+                    .or_else(|_| event.path.strip_prefix(&base_dir))
+                    .is_ok_and(|relative| !is_hidden(relative))
+            });
+            if relevant {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = sync(&app).await {
+                        eprintln!("Library mirror sync failed: {}", e);
+                    }
+                });
+            }
+        }
+        Err(e) => eprintln!("Library mirror: {}", e),
+    };
+
+    let mut debouncer = new_debouncer(DEBOUNCE, handler)
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    debouncer
+        .watcher()
+        .watch(&watched_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Cannot watch {}: {}", watched_dir.display(), e))?;
+    Ok(debouncer)
+}
+
+fn is_hidden(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+// What the directory held for a script after the last sync
+struct Entry {
+    path: String,
+    synced_hash: String,
+}
+
+struct MirrorFile {
+    /// Relative to the mirror directory, separated by `/`.
+    path: String,
+    hash: String,
+    header: Option<Header>,
+    /// The script's command, without the header.
+    body: String,
+}
+
+#[derive(Clone, Copy)]
+struct Header {
+    id: Option<i32>,
+    ordering: Option<i32>,
+}
+
+// Where a script's file belongs according to the library
+struct Placement {
+    folder_id: i32,
+    path: String,
+}
+
+// One comparison of the library with the directory
+struct Pass {
+    dir: PathBuf,
+    resolutions: HashMap<i32, MirrorSide>,
+    library: LibraryRepository<'static>,
+    scripts: ScriptRepository,
+    entries: MirrorRepository,
+    now: f64,
+    report: SyncReport,
+    conflicts: Vec<MirrorConflict>,
+}
+
+impl Pass {
+    fn new(dir: PathBuf, resolutions: HashMap<i32, MirrorSide>) -> Self {
+        Self {
+            dir,
+            resolutions,
+            library: LibraryRepository::new(),
+            scripts: ScriptRepository::new(),
+            entries: MirrorRepository::new(),
+            now: crate::jobs::now_ms(),
+            report: SyncReport::default(),
+            conflicts: vec![],
+        }
+    }
+
+    async fn run(mut self) -> Result<(SyncReport, Vec<MirrorConflict>), String> {
+        if !self.dir.is_dir() {
+            return Err(format!("{} does not exist", self.dir.display()));
+        }
+        let library = self
+            .library
+            .get_library()
+            .await
+            .map_err(|e| format!("Failed to load the library: {}", e))?;
+        let mut layout = Layout::new(&library);
+        let entries: HashMap<i32, Entry> = self
+            .entries
+            .get_all()
+            .await
+            .map_err(|e| format!("Failed to load the mirror state: {}", e))?
+            .into_iter()
+            .map(|entry| {
+                let path = entry.path;
+                let synced_hash = entry.synced_hash;
+                (entry.shell_script_id, Entry { path, synced_hash })
+            })
+            .collect();
+        let files = scan(&self.dir, &mut self.report.skipped)?;
+        // A missing drive or a wiped checkout must not read as every script being deleted
+        if files.is_empty() && !entries.is_empty() {
+            return Err(format!(
+                "{} no longer holds any scripts; choose it again to write the library into it",
+                self.dir.display()
+            ));
+        }
+        for dir in layout.dirs() {
+            let path = self.dir.join(dir);
+            std::fs::create_dir_all(&path)
+                .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        }
+
+        let mut claimed = claim(&files, &entries);
+        // Ids from another database, e.g. a clone of someone else's mirror, make new scripts
+        claimed.retain(|script_id, _| {
+            layout.scripts.contains_key(script_id) || entries.contains_key(script_id)
+        });
+        let mut unclaimed: Vec<&MirrorFile> = files
+            .values()
+            .filter(|file| !claimed.values().any(|path| *path == file.path))
+            .collect();
+
+        let mut scripts: Vec<_> = library.scripts.iter().collect();
+        scripts.sort_by_key(|script| script.id);
+        for script in scripts {
+            let Some(placement) = layout.scripts.get(&script.id) else {
+                continue;
+            };
+            let file = claimed.get(&script.id).map(|path| &files[path]);
+            let script = Script {
+                id: script.id,
+                name: &script.name,
+                command: &script.command,
+                ordering: script.ordering,
+                is_markdown: script.is_markdown,
+            };
+            self.sync_script(&script, placement, entries.get(&script.id), file, &layout)
+                .await?;
+        }
+
+        let mut deleted: Vec<_> = entries
+            .iter()
+            .filter(|(script_id, _)| !layout.scripts.contains_key(script_id))
+            .collect();
+        deleted.sort_by_key(|(script_id, _)| **script_id);
+        for (script_id, entry) in deleted {
+            let file = claimed.get(script_id).map(|path| &files[path]);
+            if self.sync_deleted(*script_id, entry, file).await? {
+                unclaimed.extend(file);
+            }
+        }
+
+        for file in unclaimed {
+            self.create_script(file, &mut layout).await?;
+        }
+        prune(&self.dir, Path::new(""), &layout.dirs());
+        self.report.conflicts = self.conflicts.len();
+        Ok((self.report, self.conflicts))
+    }
+
+    async fn sync_script(
+        &mut self,
+        script: &Script<'_>,
+        placement: &Placement,
+        entry: Option<&Entry>,
+        file: Option<&MirrorFile>,
+        layout: &Layout,
+    ) -> Result<(), String> {
+        let rendered = render(
+            script.id,
+            script.ordering,
+            script.is_markdown,
+            script.command,
+        );
+        let current = file.map(|file| file.path.as_str());
+        let Some(file) = file else {
+            return match entry {
+                None => {
+                    self.write(script.id, &rendered, None, &placement.path)
+                        .await
+                }
+                Some(entry) if hash(&rendered) == entry.synced_hash => {
+                    self.delete_script(script.id, placement.folder_id).await
+                }
+                Some(entry) => {
+                    match self.conflict(script, &entry.path, ConflictKind::DeletedInDirectory) {
+                        Some(MirrorSide::Library) => {
+                            self.write(script.id, &rendered, None, &placement.path)
+                                .await
+                        }
+                        Some(MirrorSide::Directory) => {
+                            self.delete_script(script.id, placement.folder_id).await
+                        }
+                        None => Ok(()),
+                    }
+                }
+            };
+        };
+
+        let keep = match compare(entry, file, &hash(&rendered), &placement.path) {
+            Change::Neither => return Ok(()),
+            Change::Library => Some(MirrorSide::Library),
+            Change::Directory => Some(MirrorSide::Directory),
+            Change::Conflict(kind) => self.conflict(script, &file.path, kind),
+        };
+        match keep {
+            Some(MirrorSide::Library) => {
+                self.write(script.id, &rendered, current, &placement.path)
+                    .await
+            }
+            Some(MirrorSide::Directory) => {
+                self.update_script(script, placement, file, layout).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    // For a script that is gone from the library; true when its file is to become a new script
+    async fn sync_deleted(
+        &mut self,
+        script_id: i32,
+        entry: &Entry,
+        file: Option<&MirrorFile>,
+    ) -> Result<bool, String> {
+        let keep_file = match file {
+            None => false,
+            Some(file) if file.hash == entry.synced_hash => {
+                self.remove(&file.path)?;
+                false
+            }
+            Some(file) => {
+                let name = stem(&file.path);
+                let script = Script {
+                    id: script_id,
+                    name: &name,
+                    command: &file.body,
+                    ordering: 0,
+                    is_markdown: false,
+                };
+                match self.conflict(&script, &file.path, ConflictKind::DeletedInLibrary) {
+                    Some(MirrorSide::Library) => {
+                        self.remove(&file.path)?;
+                        false
+                    }
+                    Some(MirrorSide::Directory) => true,
+                    None => return Ok(false),
+                }
+            }
+        };
+        self.forget(script_id).await?;
+        Ok(keep_file)
+    }
+
+    // The resolution the user chose, or else a conflict to show them
+    fn conflict(&mut self, script: &Script, path: &str, kind: ConflictKind) -> Option<MirrorSide> {
+        let resolution = self.resolutions.get(&script.id).copied();
+        if resolution.is_none() {
+            self.conflicts.push(MirrorConflict {
+                script_id: script.id,
+                name: script.name.to_string(),
+                path: path.to_string(),
+                kind,
+            });
+        }
+        resolution
+    }
+
+    // Apply an edited, renamed or moved file to its script
+    async fn update_script(
+        &mut self,
+        script: &Script<'_>,
+        placement: &Placement,
+        file: &MirrorFile,
+        layout: &Layout,
+    ) -> Result<(), String> {
+        let failed = format!("Failed to update script {} from {}", script.id, file.path);
+        if file.body != script.command {
+            self.scripts
+                .update_script_command(script.id, file.body.clone())
+                .await
+                .map_err(|e| format!("{}: {}", failed, e))?;
+        }
+        let ordering = file
+            .header
+            .and_then(|header| header.ordering)
+            .unwrap_or(script.ordering);
+        if ordering != script.ordering {
+            self.scripts
+                .update_script_ordering(script.id, ordering)
+                .await
+                .map_err(|e| format!("{}: {}", failed, e))?;
+        }
+        if file.path != placement.path {
+            let name = stem(&file.path);
+            if name != file_name(script.name) {
+                self.scripts
+                    .update_script_name(script.id, name)
+                    .await
+                    .map_err(|e| format!("{}: {}", failed, e))?;
+            }
+            let folder_id = layout.dir_folders.get(parent(&file.path)).copied();
+            if let Some(folder_id) = folder_id.filter(|id| *id != placement.folder_id) {
+                self.library
+                    .unlink_folder_script(placement.folder_id, script.id)
+                    .await
+                    .map_err(|e| format!("{}: {}", failed, e))?;
+                self.library
+                    .link_folder_script(folder_id, script.id)
+                    .await
+                    .map_err(|e| format!("{}: {}", failed, e))?;
+            }
+        }
+        self.report.scripts_updated += 1;
+
+        // Rewrites the header if it was edited or removed
+        let rendered = render(script.id, ordering, script.is_markdown, &file.body);
+        self.write(script.id, &rendered, Some(&file.path), &file.path)
+            .await
+    }
+
+    // A file the library does not know yet becomes a script in its directory's folder
+    async fn create_script(
+        &mut self,
+        file: &MirrorFile,
+        layout: &mut Layout,
+    ) -> Result<(), String> {
+        let Some(folder_id) = layout.dir_folders.get(parent(&file.path)).copied() else {
+            self.report
+                .skipped
+                .push(format!("{}: not in a folder's directory", file.path));
+            return Ok(());
+        };
+        let is_markdown = file.path.ends_with(".md");
+        let ordering = file
+            .header
+            .and_then(|header| header.ordering)
+            .unwrap_or_else(|| layout.next_ordering(folder_id));
+        let content = ScriptContent {
+            name: stem(&file.path),
+            command: file.body.clone(),
+            is_markdown,
+            locked: true,
+            show_shell: false,
+            interpreter: None,
+        };
+        let failed = format!("Failed to import {}", file.path);
+        let script_id = self
+            .library
+            .create_script(content, ordering)
+            .await
+            .map_err(|e| format!("{}: {}", failed, e))?;
+        self.library
+            .link_folder_script(folder_id, script_id)
+            .await
+            .map_err(|e| format!("{}: {}", failed, e))?;
+        self.report.scripts_created += 1;
+
+        let rendered = render(script_id, ordering, is_markdown, &file.body);
+        self.write(script_id, &rendered, Some(&file.path), &file.path)
+            .await
+    }
+
+    async fn delete_script(&mut self, script_id: i32, folder_id: i32) -> Result<(), String> {
+        self.scripts
+            .delete_script(script_id, folder_id)
+            .await
+            .map_err(|e| format!("Failed to delete script {}: {}", script_id, e))?;
+        self.report.scripts_deleted += 1;
+        self.forget(script_id).await
+    }
+
+    // Write a script's file to `path` and record it. The file stays where it is when another file
+    // is in the way.
+    async fn write(
+        &mut self,
+        script_id: i32,
+        rendered: &str,
+        current: Option<&str>,
+        path: &str,
+    ) -> Result<(), String> {
+        let target = if Some(path) == current || !self.dir.join(path).exists() {
+            path.to_string()
+        } else {
+            match current {
+                Some(current) => current.to_string(),
+                None => free_path(&self.dir, path),
+            }
+        };
+        let full = self.dir.join(&target);
+        if std::fs::read_to_string(&full).ok().as_deref() != Some(rendered) {
+            std::fs::write(&full, rendered)
+                .map_err(|e| format!("Failed to write {}: {}", full.display(), e))?;
+            self.report.files_written += 1;
+        }
+        if let Some(current) = current.filter(|current| *current != target) {
+            self.remove(current)?;
+        }
+        self.entries
+            .upsert(script_id, &target, &hash(rendered), self.now)
+            .await
+            .map_err(|e| format!("Failed to update the mirror state: {}", e))?;
+        Ok(())
+    }
+
+    fn remove(&mut self, path: &str) -> Result<(), String> {
+        let full = self.dir.join(path);
+        match std::fs::remove_file(&full) {
+            Ok(()) => {
+                self.report.files_deleted += 1;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Failed to delete {}: {}", full.display(), e)),
+        }
+    }
+
+    async fn forget(&self, script_id: i32) -> Result<(), String> {
+        self.entries
+            .delete(script_id)
+            .await
+            .map_err(|e| format!("Failed to update the mirror state: {}", e))
+    }
+}
+
+struct Script<'a> {
+    id: i32,
+    name: &'a str,
+    command: &'a str,
+    ordering: i32,
+    is_markdown: bool,
+}
+
+// Where everything in the library goes in the directory
+struct Layout {
+    /// Directories of the workspaces and folders, parents first.
+    dirs: Vec<String>,
+    dir_folders: HashMap<String, i32>,
+    scripts: HashMap<i32, Placement>,
+    next_ordering: HashMap<i32, i32>,
+}
+
+impl Layout {
+    // Workspaces and the folders in none are at the top. A folder in several places, or a script
+    // in several folders, appears in the first one only.
+    fn new(library: &Library) -> Self {
+        let mut layout = Self {
+            dirs: vec![],
+            dir_folders: HashMap::new(),
+            scripts: HashMap::new(),
+            next_ordering: HashMap::new(),
+        };
+        let mut names = Names::default();
+        let folders: HashMap<i32, &_> = library.folders.iter().map(|f| (f.id, f)).collect();
+        let scripts: HashMap<i32, &_> = library.scripts.iter().map(|s| (s.id, s)).collect();
+        let by_ordering = |ids: &mut Vec<i32>| {
+            ids.sort_by_key(|id| (folders.get(id).map_or(0, |folder| folder.ordering), *id))
+        };
+
+        let mut workspaces: Vec<_> = library.workspaces.iter().collect();
+        workspaces.sort_by_key(|workspace| (workspace.ordering, workspace.id));
+        let nested: HashSet<i32> = library
+            .workspace_folders
+            .iter()
+            .map(|rel| rel.folder_id)
+            .chain(library.folder_folders.iter().map(|rel| rel.child_folder_id))
+            .collect();
+
+        // (directory, folders in it)
+        let mut pending: Vec<(String, Vec<i32>)> = vec![];
+        for workspace in workspaces {
+            let dir = names.claim("", &file_name(&workspace.name), "");
+            let mut children: Vec<i32> = library
+                .workspace_folders
+                .iter()
+                .filter(|rel| rel.workspace_id == workspace.id)
+                .map(|rel| rel.folder_id)
+                .collect();
+            by_ordering(&mut children);
+            layout.dirs.push(dir.clone());
+            pending.push((dir, children));
+        }
+        let mut top: Vec<i32> = folders
+            .keys()
+            .filter(|id| !nested.contains(id))
+            .copied()
+            .collect();
+        by_ordering(&mut top);
+        pending.push((String::new(), top));
+
+        let mut placed: HashSet<i32> = HashSet::new();
+        let mut index = 0;
+        while index < pending.len() {
+            let (dir, children) = pending[index].clone();
+            index += 1;
+            for folder_id in children {
+                let Some(folder) = folders.get(&folder_id) else {
+                    continue;
+                };
+                if !placed.insert(folder_id) {
+                    continue;
+                }
+                let folder_dir = names.claim(&dir, &file_name(&folder.name), "");
+                layout.dirs.push(folder_dir.clone());
+                layout.dir_folders.insert(folder_dir.clone(), folder_id);
+
+                let mut in_folder: Vec<_> = library
+                    .folder_scripts
+                    .iter()
+                    .filter(|rel| rel.scripts_folder_id == folder_id)
+                    .filter_map(|rel| scripts.get(&rel.shell_script_id))
+                    .collect();
+                in_folder.sort_by_key(|script| (script.ordering, script.id));
+                for script in &in_folder {
+                    if layout.scripts.contains_key(&script.id) {
+                        continue;
+                    }
+                    let extension = if script.is_markdown { ".md" } else { ".sh" };
+                    let path = names.claim(&folder_dir, &file_name(&script.name), extension);
+                    layout
+                        .scripts
+                        .insert(script.id, Placement { folder_id, path });
+                }
+                let next = in_folder.iter().map(|script| script.ordering + 1).max();
+                layout.next_ordering.insert(folder_id, next.unwrap_or(0));
+
+                let mut children: Vec<i32> = library
+                    .folder_folders
+                    .iter()
+                    .filter(|rel| rel.parent_folder_id == folder_id)
+                    .map(|rel| rel.child_folder_id)
+                    .collect();
+                by_ordering(&mut children);
+                pending.push((folder_dir, children));
+            }
+        }
+        layout
+    }
+
+    fn dirs(&self) -> HashSet<String> {
+        self.dirs.iter().cloned().collect()
+    }
+
+    fn next_ordering(&mut self, folder_id: i32) -> i32 {
+        let next = self.next_ordering.entry(folder_id).or_insert(0);
+        *next += 1;
+        *next - 1
+    }
+}
+
+// Which side to sync a script with its file from
+#[derive(Debug, PartialEq)]
+enum Change {
+    Neither,
+    Library,
+    Directory,
+    Conflict(ConflictKind),
+}
+
+// Compare a script, rendered to `library_hash` at `library_path`, and its file with the last sync
+fn compare(
+    entry: Option<&Entry>,
+    file: &MirrorFile,
+    library_hash: &str,
+    library_path: &str,
+) -> Change {
+    let Some(entry) = entry else {
+        return if file.hash == library_hash {
+            Change::Library
+        } else {
+            Change::Conflict(ConflictKind::NotSynced)
+        };
+    };
+    // Renaming or moving counts as a change too
+    let file_changed = file.hash != entry.synced_hash || file.path != entry.path;
+    let library_changed = library_hash != entry.synced_hash || library_path != entry.path;
+    match (file_changed, library_changed) {
+        (false, false) => Change::Neither,
+        (true, false) => Change::Directory,
+        // Also when both sides changed the same way
+        (false, true) => Change::Library,
+        (true, true) if file.hash == library_hash => Change::Library,
+        (true, true) => Change::Conflict(ConflictKind::BothChanged),
+    }
+}
+
+// Names taken per directory, compared case-insensitively for macOS and Windows
+#[derive(Default)]
+struct Names {
+    taken: HashMap<String, HashSet<String>>,
+}
+
+impl Names {
+    // `<name><extension>` in `dir`, or `<name> (<n>)<extension>` if that is taken
+    fn claim(&mut self, dir: &str, name: &str, extension: &str) -> String {
+        let taken = self.taken.entry(dir.to_string()).or_default();
+        let mut candidate = format!("{}{}", name, extension);
+        let mut n = 1;
+        while !taken.insert(candidate.to_lowercase()) {
+            n += 1;
+            candidate = format!("{} ({}){}", name, n, extension);
+        }
+        join(dir, &candidate)
+    }
+}
+
+// Which file holds which script: the one with its id in the header, preferring the path of the
+// last sync if several have it, or a file at that path whose header was removed
+fn claim(
+    files: &BTreeMap<String, MirrorFile>,
+    entries: &HashMap<i32, Entry>,
+) -> HashMap<i32, String> {
+    let mut claimed: HashMap<i32, String> = HashMap::new();
+    for file in files.values() {
+        let Some(id) = file.header.and_then(|header| header.id) else {
+            continue;
+        };
+        let synced_here = entries
+            .get(&id)
+            .is_some_and(|entry| entry.path == file.path);
+        if synced_here || !claimed.contains_key(&id) {
+            claimed.insert(id, file.path.clone());
+        }
+    }
+    for (id, entry) in entries {
+        let headerless = files
+            .get(&entry.path)
+            .is_some_and(|file| file.header.is_none());
+        if headerless && !claimed.contains_key(id) {
+            claimed.insert(*id, entry.path.clone());
+        }
+    }
+    claimed
+}
+
+// Every .sh and .md file, skipping hidden files and directories such as .git
+fn scan(dir: &Path, skipped: &mut Vec<String>) -> Result<BTreeMap<String, MirrorFile>, String> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![String::new()];
+    while let Some(relative) = pending.pop() {
+        let path = dir.join(&relative);
+        let entries = std::fs::read_dir(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let relative = join(&relative, &name);
+            // Symlinked directories are not followed, they could lead back up the tree
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push(relative);
+                continue;
+            }
+            let markdown = name.ends_with(".md");
+            if !markdown && !name.ends_with(".sh") {
+                continue;
+            }
+            let content = match std::fs::read_to_string(entry.path()) {
+                Ok(content) => content,
+                Err(e) => {
+                    skipped.push(format!("{}: {}", relative, e));
+                    continue;
+                }
+            };
+            let (header, body) = parse(&content, markdown);
+            files.insert(
+                relative.clone(),
+                MirrorFile {
+                    path: relative,
+                    hash: hash(&content),
+                    header,
+                    body,
+                },
+            );
+        }
+    }
+    Ok(files)
+}
+
+// Remove directories left empty by moved or deleted folders
+fn prune(dir: &Path, relative: &Path, kept: &HashSet<String>) {
+    let Ok(entries) = std::fs::read_dir(dir.join(relative)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') || !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let child = relative.join(&name);
+        prune(dir, &child, kept);
+        let key = child.to_string_lossy().replace('\\', "/");
+        if !kept.contains(&key) {
+            // Fails, as intended, unless the directory is empty
+            let _ = std::fs::remove_dir(dir.join(&child));
+        }
+    }
+}
+
+/// The file for a script: its command after a header with its id and ordering, as YAML
+/// front-matter in markdown and as comments in shell scripts, below the shebang line if any.
+fn render(id: i32, ordering: i32, markdown: bool, command: &str) -> String {
+    let (fence, prefix) = if markdown {
+        ("---", "")
+    } else {
+        ("# ---", "# ")
+    };
+    let header = format!(
+        "{fence}\n{prefix}id: {id}\n{prefix}ordering: {ordering}\n{fence}\n",
+        fence = fence,
+        prefix = prefix,
+        id = id,
+        ordering = ordering
+    );
+    // A shebang line without a newline stays below the header, or it would read back with one
+    match command.split_once('\n') {
+        Some((shebang, rest)) if !markdown && shebang.starts_with(SHEBANG) => {
+            format!("{}\n{}{}", shebang, header, rest)
+        }
+        _ => format!("{}{}", header, command),
+    }
+}
+
+// The header `render` writes, and the command around it. Front-matter without an id is the
+// user's own and stays part of the command.
+fn parse(content: &str, markdown: bool) -> (Option<Header>, String) {
+    let unparsed = (None, content.to_string());
+    let (shebang, rest) = match content.strip_prefix(SHEBANG) {
+        Some(_) if !markdown => match content.split_once('\n') {
+            Some((shebang, rest)) => (Some(shebang), rest),
+            None => return unparsed,
+        },
+        _ => (None, content),
+    };
+    let fence = if markdown { "---" } else { "# ---" };
+    let mut lines = rest.split_inclusive('\n');
+    let Some(first) = lines.next().filter(|line| line.trim_end() == fence) else {
+        return unparsed;
+    };
+    let mut consumed = first.len();
+    let mut fields = HashMap::new();
+    let mut closed = false;
+    for line in lines {
+        consumed += line.len();
+        let line = line.trim_end();
+        if line == fence {
+            closed = true;
+            break;
+        }
+        let field = if markdown {
+            Some(line)
+        } else {
+            line.strip_prefix('#').map(str::trim_start)
+        };
+        match field.and_then(|field| field.split_once(':')) {
+            Some((key, value)) => {
+                fields.insert(key.trim().to_string(), value.trim().to_string());
+            }
+            None => return unparsed,
+        }
+    }
+    if !closed || !fields.contains_key("id") {
+        return unparsed;
+    }
+    let header = Header {
+        id: fields.get("id").and_then(|id| id.parse().ok()),
+        ordering: fields
+            .get("ordering")
+            .and_then(|ordering| ordering.parse().ok()),
+    };
+    let body = &rest[consumed..];
+    let body = match shebang {
+        Some(shebang) => format!("{}\n{}", shebang, body),
+        None => body.to_string(),
+    };
+    (Some(header), body)
+}
+
+fn hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+// A script or folder name as a file name: no path separators or characters Windows refuses,
+// and no leading dot, which would hide it
+fn file_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+// The file name without the .sh or .md extension
+fn stem(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".sh")
+        .or_else(|| name.strip_suffix(".md"))
+        .unwrap_or(name)
+        .to_string()
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+// `<stem> (<n>)<extension>` next to `path` that does not exist yet
+fn free_path(dir: &Path, path: &str) -> String {
+    let extension = if path.ends_with(".md") { ".md" } else { ".sh" };
+    let name = stem(path);
+    let folder = parent(path);
+    (2..)
+        .map(|n| join(folder, &format!("{} ({}){}", name, n, extension)))
+        .find(|candidate| !dir.join(candidate).exists())
+        .unwrap_or_default()
+}
+
+fn mirror() -> Result<&'static Mirror, String> {
+    MIRROR
+        .get()
+        .ok_or_else(|| "Library mirror not initialized".to_string())
+}
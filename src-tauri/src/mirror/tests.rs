@@ -0,0 +1,246 @@
+use super::*;
+
+fn round_trip(command: &str, markdown: bool) {
+    let rendered = render(7, 3, markdown, command);
+    let (header, body) = parse(&rendered, markdown);
+    let header = header.unwrap_or_else(|| panic!("no header in {:?}", rendered));
+    assert_eq!((header.id, header.ordering), (Some(7), Some(3)));
+    assert_eq!(body, command, "rendered as {:?}", rendered);
+}
+
+fn file(path: &str, content: &str) -> MirrorFile {
+    let markdown = path.ends_with(".md");
+    let (header, body) = parse(content, markdown);
+    MirrorFile {
+        path: path.to_string(),
+        hash: hash(content),
+        header,
+        body,
+    }
+}
+
+fn entry(path: &str, synced_hash: &str) -> Entry {
+    Entry {
+        path: path.to_string(),
+        synced_hash: synced_hash.to_string(),
+    }
+}
+
+fn files(list: Vec<MirrorFile>) -> BTreeMap<String, MirrorFile> {
+    list.into_iter()
+        .map(|file| (file.path.clone(), file))
+        .collect()
+}
+
+#[test]
+fn parses_what_it_renders() {
+    for command in [
+        "",
+        "echo hi\n",
+        "echo hi",
+        "#!/bin/bash",
+        "#!/bin/bash\n",
+        "#!/usr/bin/env python3\nprint('hi')\n",
+        "echo a\r\necho b\r\n",
+        "#!/bin/sh\r\necho a\r\n",
+        "# ---\n# not: a header\n# ---\necho hi\n",
+    ] {
+        round_trip(command, false);
+    }
+    for command in [
+        "",
+        "# Notes\ntext\n",
+        "no trailing newline",
+        "---\ntitle: Notes\ntags: ops\n---\n# Notes\n",
+        "line one\r\nline two\r\n",
+        "#!/bin/bash\necho hi\n",
+    ] {
+        round_trip(command, true);
+    }
+}
+
+#[test]
+fn puts_the_header_below_the_shebang() {
+    assert_eq!(
+        render(7, 3, false, "#!/bin/bash\necho hi\n"),
+        "#!/bin/bash\n# ---\n# id: 7\n# ordering: 3\n# ---\necho hi\n"
+    );
+    assert_eq!(
+        render(7, 3, false, "#!/bin/bash\n"),
+        "#!/bin/bash\n# ---\n# id: 7\n# ordering: 3\n# ---\n"
+    );
+    assert_eq!(
+        render(7, 3, false, "#!/bin/bash"),
+        "# ---\n# id: 7\n# ordering: 3\n# ---\n#!/bin/bash"
+    );
+    assert_eq!(
+        render(7, 3, true, "#!/bin/bash\n"),
+        "---\nid: 7\nordering: 3\n---\n#!/bin/bash\n"
+    );
+}
+
+#[test]
+fn reads_headers_edited_elsewhere() {
+    // Saved with Windows line endings, and without a newline after the header
+    let (header, body) = parse(
+        "# ---\r\n# id: 4\r\n# ordering: 1\r\n# ---\r\necho hi\r\n",
+        false,
+    );
+    assert_eq!(header.and_then(|header| header.id), Some(4));
+    assert_eq!(body, "echo hi\r\n");
+    let (header, body) = parse("---\nid: 4\n---", true);
+    assert_eq!(
+        header.map(|header| (header.id, header.ordering)),
+        Some((Some(4), None))
+    );
+    assert_eq!(body, "");
+}
+
+#[test]
+fn leaves_front_matter_without_an_id_to_the_command() {
+    for (content, markdown) in [
+        ("---\ntitle: Notes\n---\ntext\n", true),
+        ("# ---\n# title: Notes\n# ---\necho hi\n", false),
+        ("#!/bin/bash\n# ---\n# title: Notes\n# ---\n", false),
+        // Never closed, or not made of fields
+        ("---\nid: 4\ntext\n", true),
+        ("# ---\n# id: 4\necho hi\n", false),
+        // A shebang line alone has nothing below it to hold a header
+        ("#!/bin/bash", false),
+    ] {
+        let (header, body) = parse(content, markdown);
+        assert!(header.is_none(), "{:?} has a header", content);
+        assert_eq!(body, content);
+    }
+}
+
+#[test]
+fn cleans_names_for_every_file_system() {
+    assert_eq!(file_name("a/b\\c:d*e?f\"g<h>i|j"), "a_b_c_d_e_f_g_h_i_j");
+    assert_eq!(file_name("tab\there"), "tab_here");
+    assert_eq!(file_name("  .hidden  "), "hidden");
+    assert_eq!(file_name("trailing dot."), "trailing dot");
+    assert_eq!(file_name(" .. "), "_");
+    assert_eq!(file_name(""), "_");
+}
+
+#[test]
+fn numbers_names_that_collide() {
+    let mut names = Names::default();
+    assert_eq!(names.claim("Ops", "Deploy", ".sh"), "Ops/Deploy.sh");
+    // Case-insensitive file systems see these as the same file
+    assert_eq!(names.claim("Ops", "deploy", ".sh"), "Ops/deploy (2).sh");
+    assert_eq!(names.claim("Ops", "DEPLOY", ".sh"), "Ops/DEPLOY (3).sh");
+    // Names that only differ before cleaning
+    assert_eq!(names.claim("Ops", &file_name("a/b"), ".sh"), "Ops/a_b.sh");
+    assert_eq!(
+        names.claim("Ops", &file_name("a:b"), ".sh"),
+        "Ops/a_b (2).sh"
+    );
+    // A name that looks numbered already is skipped over
+    assert_eq!(names.claim("", "x (2)", ".sh"), "x (2).sh");
+    assert_eq!(names.claim("", "x", ".sh"), "x.sh");
+    assert_eq!(names.claim("", "X", ".sh"), "X (3).sh");
+    // Other directories and extensions are separate
+    assert_eq!(names.claim("Dev", "deploy", ".sh"), "Dev/deploy.sh");
+    assert_eq!(names.claim("Ops", "deploy", ".md"), "Ops/deploy.md");
+    assert_eq!(names.claim("", "Ops", ""), "Ops");
+}
+
+#[test]
+fn claims_the_file_synced_last_for_a_copied_header() {
+    let content = render(7, 0, false, "echo hi\n");
+    let files = files(vec![
+        file("a.sh", &content),
+        file("b.sh", &content),
+        file("c.sh", &render(8, 0, false, "ls\n")),
+    ]);
+
+    let mut entries = HashMap::new();
+    entries.insert(7, entry("b.sh", &hash(&content)));
+    let claimed = claim(&files, &entries);
+    assert_eq!(claimed[&7], "b.sh");
+    assert_eq!(claimed[&8], "c.sh");
+
+    // Without a previous sync the first file in path order has it
+    assert_eq!(claim(&files, &HashMap::new())[&7], "a.sh");
+}
+
+#[test]
+fn claims_the_synced_path_when_its_header_was_removed() {
+    let mut entries = HashMap::new();
+    entries.insert(7, entry("a.sh", "old"));
+
+    let headerless = files(vec![file("a.sh", "echo hi\n")]);
+    assert_eq!(claim(&headerless, &entries)[&7], "a.sh");
+
+    // A file elsewhere that still has the id wins over the path
+    let moved = files(vec![
+        file("a.sh", "echo hi\n"),
+        file("b.sh", &render(7, 0, false, "echo hi\n")),
+    ]);
+    assert_eq!(claim(&moved, &entries)[&7], "b.sh");
+
+    // Another script's file at the path is not taken
+    let replaced = files(vec![file("a.sh", &render(8, 0, false, "ls\n"))]);
+    let claimed = claim(&replaced, &entries);
+    assert!(!claimed.contains_key(&7));
+    assert_eq!(claimed[&8], "a.sh");
+}
+
+#[test]
+fn detects_which_side_changed() {
+    let synced = render(7, 0, false, "echo hi\n");
+    let edited = render(7, 0, false, "echo bye\n");
+    let other = render(7, 0, false, "echo other\n");
+    let last = entry("Ops/a.sh", &hash(&synced));
+    let changed = |entry, file: &MirrorFile, library: &str, path: &str| {
+        compare(entry, file, &hash(library), path)
+    };
+
+    let unchanged = file("Ops/a.sh", &synced);
+    assert_eq!(
+        changed(Some(&last), &unchanged, &synced, "Ops/a.sh"),
+        Change::Neither
+    );
+    assert_eq!(
+        changed(Some(&last), &file("Ops/a.sh", &edited), &synced, "Ops/a.sh"),
+        Change::Directory
+    );
+    assert_eq!(
+        changed(Some(&last), &file("Dev/a.sh", &synced), &synced, "Ops/a.sh"),
+        Change::Directory
+    );
+    assert_eq!(
+        changed(Some(&last), &unchanged, &edited, "Ops/a.sh"),
+        Change::Library
+    );
+    assert_eq!(
+        changed(Some(&last), &unchanged, &synced, "Ops/b.sh"),
+        Change::Library
+    );
+
+    // Both sides changed, the same way or not
+    assert_eq!(
+        changed(Some(&last), &file("Ops/a.sh", &edited), &edited, "Ops/a.sh"),
+        Change::Library
+    );
+    assert_eq!(
+        changed(Some(&last), &file("Ops/a.sh", &edited), &other, "Ops/a.sh"),
+        Change::Conflict(ConflictKind::BothChanged)
+    );
+    assert_eq!(
+        changed(Some(&last), &file("Dev/a.sh", &synced), &edited, "Ops/a.sh"),
+        Change::Conflict(ConflictKind::BothChanged)
+    );
+
+    // Never synced
+    assert_eq!(
+        changed(None, &unchanged, &synced, "Ops/a.sh"),
+        Change::Library
+    );
+    assert_eq!(
+        changed(None, &unchanged, &edited, "Ops/a.sh"),
+        Change::Conflict(ConflictKind::NotSynced)
+    );
+}
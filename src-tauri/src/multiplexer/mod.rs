@@ -0,0 +1,421 @@
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use crate::db::repository::app_state_repository::AppStateRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::executor::placeholders::shell_quote;
+use crate::executor::run_config::ResolvedRunConfig;
+use crate::launcher::LaunchScript;
+use crate::terminal;
+
+/// Every session the app creates is named with this prefix; other sessions are never listed,
+/// attached or killed.
+pub const SESSION_PREFIX: &str = "ssm-";
+
+// Commands that are not part of a workspace share one session
+const SHARED_SESSION: &str = "ssm-scripts";
+const WORKSPACE_SESSION_PREFIX: &str = "ssm-workspace-";
+const MAX_WINDOW_NAME_LEN: usize = 40;
+
+/// Where "run in shell" sends scripts instead of opening a new terminal window.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Multiplexer {
+    Tmux,
+    Zellij,
+}
+
+impl Multiplexer {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Multiplexer::Tmux => "TMUX",
+            Multiplexer::Zellij => "ZELLIJ",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        [Multiplexer::Tmux, Multiplexer::Zellij]
+            .into_iter()
+            .find(|multiplexer| multiplexer.as_str() == value)
+    }
+
+    fn binary(&self) -> &'static str {
+        match self {
+            Multiplexer::Tmux => "tmux",
+            Multiplexer::Zellij => "zellij",
+        }
+    }
+
+    fn is_installed(&self) -> bool {
+        terminal::find_in_path(self.binary()).is_some()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiplexerSettings {
+    /// `None` opens a new terminal window for every run.
+    pub multiplexer: Option<Multiplexer>,
+    /// The multiplexers installed on this machine.
+    pub available: Vec<Multiplexer>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiplexerSession {
+    pub multiplexer: Multiplexer,
+    pub name: String,
+    /// `None` for the session shared by scripts outside any workspace.
+    pub workspace_id: Option<i32>,
+    /// Only known for tmux.
+    pub windows: Option<u32>,
+    pub attached: bool,
+}
+
+pub async fn get_settings() -> Result<MultiplexerSettings, String> {
+    Ok(MultiplexerSettings {
+        multiplexer: preferred().await?,
+        available: [Multiplexer::Tmux, Multiplexer::Zellij]
+            .into_iter()
+            .filter(|multiplexer| multiplexer.is_installed())
+            .collect(),
+    })
+}
+
+/// Send "run in shell" scripts to `multiplexer`, or back to terminal windows with `None`.
+pub async fn set_preference(
+    multiplexer: Option<Multiplexer>,
+) -> Result<MultiplexerSettings, String> {
+    if let Some(multiplexer) = multiplexer {
+        if !multiplexer.is_installed() {
+            return Err(format!("{} is not installed", multiplexer.binary()));
+        }
+    }
+    AppStateRepository::new()
+        .set_shell_multiplexer(multiplexer.map(|multiplexer| multiplexer.as_str().to_string()))
+        .await
+        .map_err(|e| format!("Failed to save multiplexer preference: {}", e))?;
+    get_settings().await
+}
+
+/// The multiplexer "run in shell" uses, if one is chosen.
+pub async fn preferred() -> Result<Option<Multiplexer>, String> {
+    let state = AppStateRepository::new()
+        .get_app_state()
+        .await
+        .map_err(|e| format!("Failed to load multiplexer preference: {}", e))?;
+    Ok(state
+        .and_then(|state| state.shell_multiplexer)
+        .as_deref()
+        .and_then(Multiplexer::parse))
+}
+
+/// Run `command` in a window named after the script, inside the session of the script's
+/// workspace. The session and the window are created when missing; an existing window gets the
+/// command typed into its shell, so it runs once whatever is running there has finished.
+pub async fn send_script(
+    multiplexer: Multiplexer,
+    script_id: Option<i32>,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) -> Result<(), String> {
+    let (session, window) = session_and_window(script_id).await?;
+    // The typed line stays short and the prelude's variables do not end up in shell history
+    let script = LaunchScript::write(&format!("{}{}", run_config.shell_prelude(), command), false)?;
+
+    println!(
+        "Sending command to {} session {}, window {}",
+        multiplexer.binary(),
+        session,
+        window
+    );
+    match multiplexer {
+        Multiplexer::Tmux => send_to_tmux(&session, &window, &script.shell_word()).await?,
+        Multiplexer::Zellij => send_to_zellij(&session, &window, script.path()).await?,
+    }
+    script.launched();
+    Ok(())
+}
+
+/// The sessions the app created, in every installed multiplexer.
+pub async fn list_sessions() -> Result<Vec<MultiplexerSession>, String> {
+    let mut sessions = vec![];
+    if Multiplexer::Tmux.is_installed() {
+        sessions.extend(list_tmux_sessions().await?);
+    }
+    if Multiplexer::Zellij.is_installed() {
+        sessions.extend(list_zellij_sessions().await?);
+    }
+    Ok(sessions)
+}
+
+/// Open a terminal window attached to the session.
+pub async fn attach_session(multiplexer: Multiplexer, name: String) -> Result<(), String> {
+    check_owned(&name)?;
+    let command = match multiplexer {
+        Multiplexer::Tmux => format!(
+            "tmux attach-session -t {}",
+            shell_quote(&format!("={}", name))
+        ),
+        Multiplexer::Zellij => format!("zellij attach {}", shell_quote(&name)),
+    };
+    crate::open_terminal_with_command(command, ResolvedRunConfig::default()).await
+}
+
+/// End the session and everything running in it.
+pub async fn kill_session(multiplexer: Multiplexer, name: String) -> Result<(), String> {
+    check_owned(&name)?;
+    match multiplexer {
+        Multiplexer::Tmux => run(multiplexer, &["kill-session", "-t", &format!("={}", name)]).await,
+        // Also forgets the session, so it does not come back as a resurrectable one
+        Multiplexer::Zellij => run(multiplexer, &["delete-session", "--force", &name]).await,
+    }
+    .map(|_| ())
+}
+
+async fn session_and_window(script_id: Option<i32>) -> Result<(String, String), String> {
+    let Some(script_id) = script_id else {
+        return Ok((SHARED_SESSION.to_string(), "shell".to_string()));
+    };
+    let repository = ScriptRepository::new();
+    let workspace_id = repository.get_workspace_id(script_id).await.map_err(|e| {
+        format!(
+            "Failed to find the workspace of script {}: {}",
+            script_id, e
+        )
+    })?;
+    let script = repository
+        .get_script(script_id)
+        .await
+        .map_err(|e| format!("Failed to load script {}: {}", script_id, e))?
+        .ok_or_else(|| format!("Script {} not found", script_id))?;
+
+    let session = match workspace_id {
+        Some(workspace_id) => format!("{}{}", WORKSPACE_SESSION_PREFIX, workspace_id),
+        None => SHARED_SESSION.to_string(),
+    };
+    Ok((session, window_name(&script.name, script_id)))
+}
+
+// tmux treats `.` and `:` in targets as separators, so window names keep to a safe set
+fn window_name(script_name: &str, script_id: i32) -> String {
+    let name: String = script_name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_WINDOW_NAME_LEN)
+        .collect();
+    if name.trim().is_empty() {
+        format!("script-{}", script_id)
+    } else {
+        name.trim().to_string()
+    }
+}
+
+async fn send_to_tmux(session: &str, window: &str, run_line: &str) -> Result<(), String> {
+    let tmux = Multiplexer::Tmux;
+    let session_target = format!("={}", session);
+    let window_target = format!("={}:={}", session, window);
+
+    if run(tmux, &["has-session", "-t", &session_target])
+        .await
+        .is_err()
+    {
+        run(
+            tmux,
+            &["new-session", "-d", "-s", session, "-n", window, "bash"],
+        )
+        .await?;
+    } else {
+        let windows = run(
+            tmux,
+            &[
+                "list-windows",
+                "-t",
+                &session_target,
+                "-F",
+                "#{window_name}",
+            ],
+        )
+        .await?;
+        if !windows.lines().any(|name| name == window) {
+            run(
+                tmux,
+                &[
+                    "new-window",
+                    "-d",
+                    "-t",
+                    &format!("{}:", session_target),
+                    "-n",
+                    window,
+                    "bash",
+                ],
+            )
+            .await?;
+        }
+    }
+
+    run(
+        tmux,
+        &["send-keys", "-t", &window_target, "-l", "--", run_line],
+    )
+    .await?;
+    run(tmux, &["send-keys", "-t", &window_target, "Enter"]).await?;
+    Ok(())
+}
+
+async fn send_to_zellij(
+    session: &str,
+    window: &str,
+    script_path: &std::path::Path,
+) -> Result<(), String> {
+    let zellij = Multiplexer::Zellij;
+    let running = list_zellij_sessions().await?;
+    if !running.iter().any(|existing| existing.name == session) {
+        run(zellij, &["attach", "--create-background", session]).await?;
+    }
+    run(
+        zellij,
+        &[
+            "--session",
+            session,
+            "action",
+            "go-to-tab-name",
+            "--create",
+            window,
+        ],
+    )
+    .await?;
+    // A new pane in the tab; it stays open after the script exits, showing its exit code
+    run(
+        zellij,
+        &[
+            "--session",
+            session,
+            "run",
+            "--name",
+            window,
+            "--",
+            &script_path.to_string_lossy(),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+async fn list_tmux_sessions() -> Result<Vec<MultiplexerSession>, String> {
+    let output = match run(
+        Multiplexer::Tmux,
+        &[
+            "list-sessions",
+            "-F",
+            "#{session_name}\t#{session_windows}\t#{session_attached}",
+        ],
+    )
+    .await
+    {
+        Ok(output) => output,
+        // No server running means no sessions
+        Err(e) if e.contains("no server running") || e.contains("error connecting") => {
+            return Ok(vec![])
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.to_string();
+            if !name.starts_with(SESSION_PREFIX) {
+                return None;
+            }
+            let windows = fields.next().and_then(|value| value.parse().ok());
+            let attached = fields
+                .next()
+                .and_then(|value| value.parse::<u32>().ok())
+                .is_some_and(|clients| clients > 0);
+            Some(MultiplexerSession {
+                multiplexer: Multiplexer::Tmux,
+                workspace_id: workspace_of(&name),
+                name,
+                windows,
+                attached,
+            })
+        })
+        .collect())
+}
+
+async fn list_zellij_sessions() -> Result<Vec<MultiplexerSession>, String> {
+    let output = match run(
+        Multiplexer::Zellij,
+        &["list-sessions", "--short", "--no-formatting"],
+    )
+    .await
+    {
+        Ok(output) => output,
+        Err(e) if e.contains("No active zellij sessions") => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|name| name.starts_with(SESSION_PREFIX))
+        .map(|name| MultiplexerSession {
+            multiplexer: Multiplexer::Zellij,
+            name: name.to_string(),
+            workspace_id: workspace_of(name),
+            windows: None,
+            attached: false,
+        })
+        .collect())
+}
+
+fn workspace_of(session: &str) -> Option<i32> {
+    session
+        .strip_prefix(WORKSPACE_SESSION_PREFIX)
+        .and_then(|id| id.parse().ok())
+}
+
+fn check_owned(name: &str) -> Result<(), String> {
+    if name.starts_with(SESSION_PREFIX) {
+        Ok(())
+    } else {
+        Err(format!("Session '{}' was not created by this app", name))
+    }
+}
+
+// Run the multiplexer to completion, returning its stdout or, on failure, its stderr
+async fn run(multiplexer: Multiplexer, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(multiplexer.binary())
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", multiplexer.binary(), e))?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.is_empty() {
+        Err(format!(
+            "{} {} exited with code {:?}",
+            multiplexer.binary(),
+            args.first().unwrap_or(&""),
+            output.status.code()
+        ))
+    } else {
+        Err(stderr)
+    }
+}
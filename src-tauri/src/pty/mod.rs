@@ -0,0 +1,507 @@
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, EventTarget};
+
+use crate::executor::history::HistoryEntry;
+use crate::executor::interpreter;
+use crate::executor::run_config::ResolvedRunConfig;
+use crate::executor::{self, ExecutionOutcome};
+use crate::jobs;
+use crate::secrets::MaskStream;
+use crate::PTY_SESSIONS;
+
+/// Emitted with every chunk the terminal writes, in the order it was written.
+pub const PTY_OUTPUT_EVENT: &str = "pty-output";
+/// Emitted exactly once when the process running in the terminal has exited.
+pub const PTY_EXITED_EVENT: &str = "pty-exited";
+
+const READ_BUFFER_SIZE: usize = 8192;
+/// Only the end of a session's transcript is kept for its history entry.
+const TRANSCRIPT_LIMIT: usize = 64 * 1024;
+/// How long to wait for the last output once the process has exited; background processes
+/// still holding the terminal would otherwise keep the session open forever.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long output that may be the start of a secret is held back when nothing follows it, e.g.
+/// a prompt that happens to end that way.
+const MASK_HOLD_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub type PtyRegistry = Arc<Mutex<HashMap<String, PtySession>>>;
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtySessionInfo {
+    pub session_id: String,
+    /// `None` for a plain interactive shell.
+    pub command: Option<String>,
+    pub window_label: String,
+    pub cols: u16,
+    pub rows: u16,
+    pub started_at: f64,
+}
+
+// Terminal I/O goes through these handles, cloned out of the registry so that a slow write
+// never holds up the other sessions
+pub struct PtySession {
+    master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    info: PtySessionInfo,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyOutputEvent {
+    pub session_id: String,
+    /// Raw terminal output including escape sequences, to be fed to a terminal emulator as is.
+    pub data: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyExitedEvent {
+    pub session_id: String,
+    /// `None` when the process was terminated by a signal.
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub duration_ms: u64,
+}
+
+/// What a new session runs.
+pub struct PtyLaunch {
+    /// Command with its secrets already injected into `run_config`; `None` starts the user's
+    /// login shell.
+    pub command: Option<String>,
+    /// Drop into an interactive shell once `command` has finished instead of ending the session.
+    pub keep_open: bool,
+    pub run_config: ResolvedRunConfig,
+    pub history: Option<HistoryEntry>,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+pub fn registry() -> Result<&'static PtyRegistry, String> {
+    PTY_SESSIONS
+        .get()
+        .ok_or_else(|| "PTY session registry not initialized".to_string())
+}
+
+/// Spawn the login shell in a new pseudo-terminal and stream its output to the webview window
+/// `label`. A command runs with the interpreter of `run_config` or its shebang line, started from
+/// the login shell. The session is registered as a job under `session_id` as well, so it shows
+/// up in `list_jobs` and can be cancelled like any other run.
+pub fn open_session(
+    app: tauri::AppHandle,
+    label: String,
+    session_id: String,
+    launch: PtyLaunch,
+) -> Result<(), String> {
+    let size = pty_size(launch.cols, launch.rows);
+    let pair = native_pty_system()
+        .openpty(size)
+        .map_err(|e| format!("Failed to open pseudo-terminal: {}", e))?;
+
+    let home = executor::resolve_home();
+    let shell = executor::resolve_shell(&home);
+    let mut cmd = CommandBuilder::new(&shell);
+    cmd.arg("-l");
+    if let Some(command) = &launch.command {
+        let command = interpreter::shell_line(launch.run_config.interpreter, command)?;
+        let mut script = executor::wrap_with_shell_config(&shell, &command);
+        if launch.keep_open {
+            script = format!("{}; exec {} -l", script, shell);
+        }
+        cmd.arg("-c");
+        cmd.arg(script);
+    }
+    cmd.env("HOME", &home);
+    cmd.env(
+        "USER",
+        std::env::var("USER").unwrap_or_else(|_| whoami::username()),
+    );
+    cmd.env("TERM", "xterm-256color");
+    cmd.env("COLORTERM", "truecolor");
+    cmd.cwd(launch.run_config.working_dir.as_ref().unwrap_or(&home));
+    for var in launch
+        .run_config
+        .env
+        .iter()
+        .chain(&launch.run_config.secrets.env)
+    {
+        cmd.env(&var.name, &var.value);
+    }
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to read from pseudo-terminal: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to write to pseudo-terminal: {}", e))?;
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to start shell in pseudo-terminal: {}", e))?;
+    // Only the child may keep the slave side open, otherwise reading never reaches EOF
+    drop(pair.slave);
+    let started = Instant::now();
+
+    let job_command = launch.command.clone().unwrap_or_else(|| shell.clone());
+    if let Err(e) = jobs::register(&session_id, &job_command, child.process_id()) {
+        let _ = child.kill();
+        return Err(e);
+    }
+
+    registry()?.lock().unwrap().insert(
+        session_id.clone(),
+        PtySession {
+            master: Arc::new(Mutex::new(pair.master)),
+            writer: Arc::new(Mutex::new(writer)),
+            info: PtySessionInfo {
+                session_id: session_id.clone(),
+                command: launch.command.clone(),
+                window_label: label.clone(),
+                cols: launch.cols,
+                rows: launch.rows,
+                started_at: jobs::now_ms(),
+            },
+        },
+    );
+    println!("Opened PTY session {} running {}", session_id, job_command);
+
+    let target = EventTarget::webview_window(label);
+    let (transcript_tx, transcript_rx) = mpsc::channel();
+    {
+        let app = app.clone();
+        let target = target.clone();
+        let session_id = session_id.clone();
+        let mask = launch.run_config.secrets.mask.clone().stream();
+        std::thread::spawn(move || {
+            let transcript = pump_output(reader, mask, |data| {
+                let event = PtyOutputEvent {
+                    session_id: session_id.clone(),
+                    data: data.to_string(),
+                };
+                if let Err(e) = app.emit_to(target.clone(), PTY_OUTPUT_EVENT, event) {
+                    eprintln!("Failed to emit {}: {}", PTY_OUTPUT_EVENT, e);
+                }
+            });
+            let _ = transcript_tx.send(transcript);
+        });
+    }
+
+    let history = launch.history;
+    std::thread::spawn(move || {
+        let status = child.wait();
+        let (transcript, transcript_cut) = transcript_rx
+            .recv_timeout(OUTPUT_DRAIN_TIMEOUT)
+            .unwrap_or_default();
+        // Dropping the master hangs up anything still attached to the terminal
+        if let Ok(registry) = registry() {
+            let session = registry.lock().unwrap().remove(&session_id);
+            drop(session);
+        }
+
+        let (exit_code, success) = match &status {
+            Ok(status) if status.signal().is_some() => (None, false),
+            Ok(status) => (Some(status.exit_code() as i32), status.success()),
+            Err(e) => {
+                eprintln!("Failed to wait for PTY session {}: {}", session_id, e);
+                (None, false)
+            }
+        };
+        let duration = started.elapsed();
+        jobs::finish(&session_id, exit_code, success, duration);
+        println!(
+            "PTY session {} exited with code {:?}",
+            session_id, exit_code
+        );
+
+        let exited = PtyExitedEvent {
+            session_id: session_id.clone(),
+            exit_code,
+            success,
+            duration_ms: duration.as_millis() as u64,
+        };
+        if let Err(e) = app.emit_to(target, PTY_EXITED_EVENT, exited) {
+            eprintln!("Failed to emit {}: {}", PTY_EXITED_EVENT, e);
+        }
+
+        if let Some(history) = history {
+            let outcome = ExecutionOutcome {
+                exit_code,
+                signal: None,
+                success,
+                duration,
+                stdout: transcript,
+                stderr: String::new(),
+                output_truncated: transcript_cut,
+                limit_exceeded: None,
+            };
+            tauri::async_runtime::spawn(async move {
+                history.record(Some(&outcome)).await;
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Forward keystrokes (or pasted text) to the session as if typed. The write blocks while the
+/// program in the terminal does not read, so it runs off the async runtime.
+pub async fn write_input(session_id: &str, data: &str) -> Result<(), String> {
+    let writer = session_handle(session_id, |session| session.writer.clone())?;
+    let data = data.as_bytes().to_vec();
+    let written = tauri::async_runtime::spawn_blocking(move || {
+        let mut writer = writer.lock().unwrap();
+        writer.write_all(&data).and_then(|_| writer.flush())
+    })
+    .await
+    .map_err(|e| format!("Failed to write to PTY session '{}': {}", session_id, e))?;
+    written.map_err(|e| format!("Failed to write to PTY session '{}': {}", session_id, e))
+}
+
+/// Tell the terminal (and through SIGWINCH the programs in it) about the new window size.
+pub fn resize(session_id: &str, cols: u16, rows: u16) -> Result<(), String> {
+    let master = session_handle(session_id, |session| session.master.clone())?;
+    master
+        .lock()
+        .unwrap()
+        .resize(pty_size(cols, rows))
+        .map_err(|e| format!("Failed to resize PTY session '{}': {}", session_id, e))?;
+    session_handle(session_id, |session| {
+        session.info.cols = cols;
+        session.info.rows = rows;
+    })
+}
+
+/// Hang up the terminal and cancel whatever is still running in it.
+pub fn close(session_id: &str) -> Result<(), String> {
+    let session = registry()?.lock().unwrap().remove(session_id);
+    if session.is_none() {
+        return Err(format!("No PTY session '{}'", session_id));
+    }
+    drop(session);
+    jobs::cancel(session_id, jobs::CANCEL_GRACE_PERIOD)
+}
+
+pub fn list() -> Result<Vec<PtySessionInfo>, String> {
+    let sessions = registry()?.lock().unwrap();
+    let mut infos: Vec<PtySessionInfo> = sessions
+        .values()
+        .map(|session| session.info.clone())
+        .collect();
+    infos.sort_by(|a, b| a.started_at.total_cmp(&b.started_at));
+    Ok(infos)
+}
+
+// `f` applied to the session under the registry lock, which it must not do I/O under
+fn session_handle<T, F>(session_id: &str, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut PtySession) -> T,
+{
+    let mut sessions = registry()?.lock().unwrap();
+    let session = sessions
+        .get_mut(session_id)
+        .ok_or_else(|| format!("No PTY session '{}'", session_id))?;
+    Ok(f(session))
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+// Read until the terminal is closed, handing the output to `on_output` as text with secrets
+// masked. A multi-byte character split across two reads is held back until it is complete, and
+// so is text that may be the start of a secret, until the next read or `MASK_HOLD_TIMEOUT`.
+// Returns the tail of everything passed on, and whether it was cut.
+fn pump_output<F>(
+    reader: Box<dyn Read + Send>,
+    mut mask: MaskStream,
+    on_output: F,
+) -> (String, bool)
+where
+    F: Fn(&str),
+{
+    let chunks = read_chunks(reader);
+    let mut pending = Vec::new();
+    let mut transcript = String::new();
+    let mut cut = false;
+    let mut pass_on = |text: String| {
+        if text.is_empty() {
+            return;
+        }
+        on_output(&text);
+        transcript.push_str(&text);
+        if transcript.len() > TRANSCRIPT_LIMIT * 2 {
+            let mut start = transcript.len() - TRANSCRIPT_LIMIT;
+            while !transcript.is_char_boundary(start) {
+                start += 1;
+            }
+            transcript.drain(..start);
+            cut = true;
+        }
+    };
+    loop {
+        match chunks.recv_timeout(MASK_HOLD_TIMEOUT) {
+            Ok(chunk) => {
+                pending.extend_from_slice(&chunk);
+                pass_on(mask.push(&take_utf8(&mut pending)));
+            }
+            Err(RecvTimeoutError::Timeout) => pass_on(mask.flush()),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    pass_on(mask.flush());
+    (transcript, cut)
+}
+
+// Reads `reader` on a thread of its own, so that waiting for the next chunk can time out
+fn read_chunks(mut reader: Box<dyn Read + Send>) -> mpsc::Receiver<Vec<u8>> {
+    let (chunks, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; READ_BUFFER_SIZE];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if chunks.send(buf[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                // Linux reports EIO once the last process holding the terminal is gone
+                Err(_) => break,
+            }
+        }
+    });
+    receiver
+}
+
+// Decode the complete part of `pending`, leaving an unfinished trailing sequence in place.
+// Invalid bytes become U+FFFD.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut rest: &[u8] = pending;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                text.push_str(valid);
+                rest = &[];
+                break;
+            }
+            Err(e) => {
+                let (valid, after) = rest.split_at(e.valid_up_to());
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                match e.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        rest = &after[len..];
+                    }
+                    None => {
+                        rest = after;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    let remaining = rest.to_vec();
+    *pending = remaining;
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::SecretMask;
+
+    // Hands out the chunks sent to it one read at a time, blocking like a terminal in between
+    struct ChunkReader(mpsc::Receiver<&'static [u8]>);
+
+    impl Read for ChunkReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.recv() {
+                Ok(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    Ok(chunk.len())
+                }
+                Err(_) => Ok(0),
+            }
+        }
+    }
+
+    // A pump fed through `sender`, collecting what it passes on
+    struct Pump {
+        sender: mpsc::Sender<&'static [u8]>,
+        passed_on: Arc<Mutex<Vec<String>>>,
+        thread: std::thread::JoinHandle<(String, bool)>,
+    }
+
+    fn pump(secret: &str) -> Pump {
+        let (sender, receiver) = mpsc::channel();
+        let passed_on = Arc::new(Mutex::new(Vec::new()));
+        let mask = SecretMask::new([secret.to_string()]).stream();
+        let thread = {
+            let passed_on = passed_on.clone();
+            std::thread::spawn(move || {
+                pump_output(Box::new(ChunkReader(receiver)), mask, |data| {
+                    passed_on.lock().unwrap().push(data.to_string())
+                })
+            })
+        };
+        Pump {
+            sender,
+            passed_on,
+            thread,
+        }
+    }
+
+    #[test]
+    fn masks_a_secret_split_across_reads() {
+        let Pump {
+            sender,
+            passed_on,
+            thread,
+        } = pump("hunter2é");
+        sender.send(b"password: hun").unwrap();
+        sender.send(b"ter2\xc3").unwrap();
+        sender.send(b"\xa9 ok\n").unwrap();
+        drop(sender);
+
+        let (transcript, cut) = thread.join().unwrap();
+        assert_eq!(transcript, "password: **** ok\n");
+        assert!(!cut);
+        let passed_on = passed_on.lock().unwrap();
+        assert!(
+            passed_on.iter().all(|data| !data.contains("hun")),
+            "{:?}",
+            passed_on
+        );
+    }
+
+    #[test]
+    fn passes_on_held_back_text_once_the_terminal_is_quiet() {
+        let Pump {
+            sender,
+            passed_on,
+            thread,
+        } = pump("hunter2");
+        sender.send(b"name [h").unwrap();
+        std::thread::sleep(MASK_HOLD_TIMEOUT * 5);
+        assert_eq!(passed_on.lock().unwrap().concat(), "name [h");
+
+        sender.send(b"]: ").unwrap();
+        drop(sender);
+        assert_eq!(thread.join().unwrap().0, "name [h]: ");
+    }
+}
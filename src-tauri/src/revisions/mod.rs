@@ -0,0 +1,217 @@
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::db::repository::app_state_repository::AppStateRepository;
+use crate::db::repository::revision_repository::RevisionRepository;
+use crate::db::repository::script_repository::ScriptRepository;
+use crate::prisma::script_revision;
+
+// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionInfo {
+    pub id: i32,
+    pub script_id: i32,
+    pub name: String,
+    /// Epoch milliseconds.
+    pub created_at: f64,
+    /// Compared with the revision before it; the first revision counts every line as added.
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionDiff {
+    pub from_name: String,
+    pub to_name: String,
+    /// The commands as a unified diff, empty when they are equal.
+    pub unified: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+/// A script's revisions, newest first.
+pub async fn list(script_id: i32) -> Result<Vec<RevisionInfo>, String> {
+    let revisions = RevisionRepository::new()
+        .get_by_script(script_id)
+        .await
+        .map_err(|e| format!("Failed to load revisions: {}", e))?;
+
+    let mut infos = Vec::with_capacity(revisions.len());
+    for (index, revision) in revisions.iter().enumerate() {
+        let previous = revisions.get(index + 1).map_or("", |r| r.command.as_str());
+        let (lines_added, lines_removed) = count(previous, &revision.command);
+        infos.push(RevisionInfo {
+            id: revision.id,
+            script_id: revision.shell_script_id,
+            name: revision.name.clone(),
+            created_at: revision.created_at,
+            lines_added,
+            lines_removed,
+        });
+    }
+    Ok(infos)
+}
+
+/// Diff two revisions of the same script, or a revision against the script as it is now
+/// when `to_revision_id` is `None`.
+pub async fn diff(
+    from_revision_id: i32,
+    to_revision_id: Option<i32>,
+) -> Result<RevisionDiff, String> {
+    let from = revision(from_revision_id).await?;
+    let (to_label, to_name, to_command) = match to_revision_id {
+        Some(id) => {
+            let to = revision(id).await?;
+            if to.shell_script_id != from.shell_script_id {
+                return Err(format!(
+                    "Revisions {} and {} belong to different scripts",
+                    from.id, to.id
+                ));
+            }
+            (
+                format!("{} (revision {})", to.name, to.id),
+                to.name,
+                to.command,
+            )
+        }
+        None => {
+            let script = ScriptRepository::new()
+                .get_script(from.shell_script_id)
+                .await
+                .map_err(|e| format!("Failed to load script: {}", e))?
+                .ok_or_else(|| format!("Script {} not found", from.shell_script_id))?;
+            (
+                format!("{} (current)", script.name),
+                script.name,
+                script.command,
+            )
+        }
+    };
+
+    let from_label = format!("{} (revision {})", from.name, from.id);
+    let unified = unified(&from.command, &to_command, &from_label, &to_label);
+    let (lines_added, lines_removed) = count(&from.command, &to_command);
+
+    Ok(RevisionDiff {
+        from_name: from.name,
+        to_name,
+        unified,
+        lines_added,
+        lines_removed,
+    })
+}
+
+/// Put a revision's name and command back on its script. The database records the restore like
+/// any other edit, so the state it replaces stays in the history.
+pub async fn restore(revision_id: i32) -> Result<(), String> {
+    let revision = revision(revision_id).await?;
+    RevisionRepository::new()
+        .restore(&revision)
+        .await
+        .map_err(|e| format!("Failed to restore revision: {}", e))
+}
+
+/// Revisions kept per script; `None` keeps them all.
+pub async fn get_retention() -> Result<Option<i32>, String> {
+    RevisionRepository::new()
+        .get_revisions_kept()
+        .await
+        .map_err(|e| format!("Failed to load revision retention: {}", e))
+}
+
+/// Change how many revisions are kept per script and drop the ones now beyond it.
+pub async fn set_retention(keep: Option<i32>) -> Result<(), String> {
+    if keep.is_some_and(|keep| keep < 1) {
+        return Err("At least one revision has to be kept".to_string());
+    }
+    AppStateRepository::new()
+        .set_revisions_kept(keep)
+        .await
+        .map_err(|e| format!("Failed to save revision retention: {}", e))?;
+    RevisionRepository::new()
+        .prune(None, keep)
+        .await
+        .map_err(|e| format!("Failed to prune revisions: {}", e))
+}
+
+async fn revision(revision_id: i32) -> Result<script_revision::Data, String> {
+    RevisionRepository::new()
+        .get(revision_id)
+        .await
+        .map_err(|e| format!("Failed to load revision: {}", e))?
+        .ok_or_else(|| format!("Revision {} not found", revision_id))
+}
+
+// `old` to `new` as a unified diff under the two labels, empty when they are equal
+fn unified(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+// Lines added and removed going from `old` to `new`
+fn count(old: &str, new: &str) -> (usize, usize) {
+    let mut added = 0;
+    let mut removed = 0;
+    for change in TextDiff::from_lines(old, new).iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_with_labels_and_context() {
+        let old = "one\ntwo\nthree\n";
+        let new = "one\n2\nthree\n";
+        assert_eq!(
+            unified(old, new, "deploy (revision 1)", "deploy (current)"),
+            "--- deploy (revision 1)\n\
+             +++ deploy (current)\n\
+             @@ -1,3 +1,3 @@\n \
+             one\n\
+             -two\n\
+             +2\n \
+             three\n"
+        );
+    }
+
+    #[test]
+    fn limits_the_context_around_a_change() {
+        let old: String = (1..=10).map(|line| format!("{}\n", line)).collect();
+        let new = old.replacen("1\n", "first\n", 1);
+        let diff = unified(&old, &new, "a", "b");
+        assert!(diff.contains("@@ -1,4 +1,4 @@"), "{}", diff);
+        assert!(diff.contains(" 4\n"));
+        assert!(!diff.contains(" 5\n"));
+    }
+
+    #[test]
+    fn equal_commands_have_no_diff() {
+        assert_eq!(unified("echo hi\n", "echo hi\n", "a", "b"), "");
+        assert_eq!(count("echo hi\n", "echo hi\n"), (0, 0));
+    }
+
+    #[test]
+    fn counts_added_and_removed_lines() {
+        assert_eq!(count("", "a\nb\n"), (2, 0));
+        assert_eq!(count("a\nb\nc\n", "a\nc\n"), (0, 1));
+        assert_eq!(count("a\nb\n", "a\nB\nC\n"), (2, 1));
+    }
+}
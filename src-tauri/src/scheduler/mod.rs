@@ -0,0 +1,435 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+use tauri::Emitter;
+use tokio::sync::Notify;
+
+use crate::backup;
+use crate::db::repository::schedule_repository::{ScheduleFields, ScheduleRepository};
+use crate::executor;
+use crate::executor::history::ExecutionMode;
+use crate::jobs;
+use crate::SCHEDULER;
+
+/// Emitted for every run the scheduler starts, including catch-up runs.
+pub const SCHEDULE_FIRED_EVENT: &str = "schedule-fired";
+
+/// A fire time this late is still run as scheduled; anything older counts as missed and is
+/// handled by the schedule's misfire policy.
+const MISFIRE_GRACE: Duration = Duration::from_secs(60);
+/// `RUN_ALL` catches up on at most this many missed fire times, the most recent ones.
+const MAX_CATCH_UP_RUNS: usize = 10;
+/// Schedules are re-read at least this often, which also covers the machine having slept.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+const UPCOMING_PREVIEW_COUNT: usize = 5;
+const MAX_UPCOMING_COUNT: usize = 100;
+
+/// Wakes the scheduler loop when schedules change.
+#[derive(Default)]
+pub struct Scheduler {
+    changed: Notify,
+}
+
+/// What happens to fire times that passed while the app was closed (or the machine asleep).
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MisfirePolicy {
+    /// Forget them.
+    Skip,
+    /// Run once for all of them together.
+    RunOnce,
+    /// Run once for each of them, up to a limit.
+    RunAll,
+}
+
+impl MisfirePolicy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MisfirePolicy::Skip => "SKIP",
+            MisfirePolicy::RunOnce => "RUN_ONCE",
+            MisfirePolicy::RunAll => "RUN_ALL",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "RUN_ONCE" => MisfirePolicy::RunOnce,
+            "RUN_ALL" => MisfirePolicy::RunAll,
+            _ => MisfirePolicy::Skip,
+        }
+    }
+}
+
+/// A schedule as sent by the frontend to be saved.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleInput {
+    /// `None` when creating a schedule.
+    pub id: Option<i32>,
+    pub script_id: i32,
+    /// Standard five-field cron (`30 2 * * Mon-Fri`), or six/seven fields starting with seconds,
+    /// or a shorthand such as `@daily`.
+    pub cron_expression: String,
+    /// IANA time zone the expression is read in; UTC if not given.
+    pub timezone: Option<String>,
+    pub misfire_policy: Option<MisfirePolicy>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleInfo {
+    pub id: i32,
+    pub script_id: i32,
+    pub cron_expression: String,
+    pub timezone: String,
+    pub misfire_policy: MisfirePolicy,
+    pub enabled: bool,
+    pub last_fired_at: Option<f64>,
+    /// The next few fire times; empty for disabled schedules.
+    pub upcoming: Vec<FireTime>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FireTime {
+    /// Epoch milliseconds.
+    pub at: f64,
+    /// The same instant in the schedule's time zone, RFC 3339.
+    pub local: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleFiredEvent {
+    pub schedule_id: i32,
+    pub script_id: i32,
+    pub run_id: String,
+    /// The fire time this run stands for, epoch milliseconds.
+    pub scheduled_for: f64,
+    /// Whether the fire time had been missed and is run according to the misfire policy.
+    pub catch_up: bool,
+}
+
+// A stored schedule with its expression parsed
+struct ParsedSchedule {
+    id: i32,
+    script_id: i32,
+    cron: cron::Schedule,
+    timezone: Tz,
+    misfire_policy: MisfirePolicy,
+    // Fire times up to here have been handled
+    handled_until: f64,
+}
+
+/// Start the scheduler loop. It runs for as long as the app is open; schedules are only fired
+/// while it is.
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let Ok(scheduler) = scheduler() else {
+            eprintln!("Scheduler not initialized, schedules will not fire");
+            return;
+        };
+        println!("Scheduler started");
+        loop {
+            let database = backup::use_database().await;
+            let sleep = match tick(&app).await {
+                Ok(next_fire) => next_fire.map_or(MAX_SLEEP, |next| {
+                    (next - Utc::now())
+                        .to_std()
+                        .unwrap_or_default()
+                        .min(MAX_SLEEP)
+                }),
+                Err(e) => {
+                    eprintln!("Scheduler: {}", e);
+                    MAX_SLEEP
+                }
+            };
+            drop(database);
+            tokio::select! {
+                _ = tokio::time::sleep(sleep) => {}
+                _ = scheduler.changed.notified() => {}
+            }
+        }
+    });
+}
+
+pub async fn list_schedules() -> Result<Vec<ScheduleInfo>, String> {
+    let schedules = ScheduleRepository::new()
+        .get_all()
+        .await
+        .map_err(|e| format!("Failed to load schedules: {}", e))?;
+    Ok(schedules.into_iter().map(to_info).collect())
+}
+
+pub async fn save_schedule(input: ScheduleInput) -> Result<ScheduleInfo, String> {
+    let cron_expression = input.cron_expression.trim().to_string();
+    let timezone = input
+        .timezone
+        .map(|timezone| timezone.trim().to_string())
+        .filter(|timezone| !timezone.is_empty())
+        .unwrap_or_else(|| "UTC".to_string());
+    parse_expression(&cron_expression, &timezone)?;
+    let fields = ScheduleFields {
+        cron_expression,
+        timezone,
+        misfire_policy: input
+            .misfire_policy
+            .unwrap_or(MisfirePolicy::Skip)
+            .as_str()
+            .to_string(),
+        enabled: input.enabled.unwrap_or(true),
+    };
+
+    let repository = ScheduleRepository::new();
+    let schedule = match input.id {
+        Some(schedule_id) => repository.update(schedule_id, fields, jobs::now_ms()).await,
+        None => repository.create(input.script_id, fields).await,
+    }
+    .map_err(|e| format!("Failed to save schedule: {}", e))?;
+    notify_changed();
+    Ok(to_info(schedule))
+}
+
+pub async fn delete_schedule(schedule_id: i32) -> Result<(), String> {
+    ScheduleRepository::new()
+        .delete(schedule_id)
+        .await
+        .map_err(|e| format!("Failed to delete schedule {}: {}", schedule_id, e))?;
+    notify_changed();
+    Ok(())
+}
+
+/// The next `count` fire times of a stored schedule.
+pub async fn upcoming_fire_times(schedule_id: i32, count: usize) -> Result<Vec<FireTime>, String> {
+    let schedule = ScheduleRepository::new()
+        .get(schedule_id)
+        .await
+        .map_err(|e| format!("Failed to load schedule {}: {}", schedule_id, e))?
+        .ok_or_else(|| format!("Schedule {} not found", schedule_id))?;
+    preview_fire_times(&schedule.cron_expression, &schedule.timezone, count)
+}
+
+/// The next `count` fire times of an expression that has not been saved yet.
+pub fn preview_fire_times(
+    cron_expression: &str,
+    timezone: &str,
+    count: usize,
+) -> Result<Vec<FireTime>, String> {
+    let (cron, timezone) = parse_expression(cron_expression, timezone)?;
+    Ok(cron
+        .upcoming(timezone)
+        .take(count.min(MAX_UPCOMING_COUNT))
+        .map(|at| FireTime {
+            at: at.timestamp_millis() as f64,
+            local: at.to_rfc3339(),
+        })
+        .collect())
+}
+
+// Fire whatever is due and return the next time anything is
+async fn tick(app: &tauri::AppHandle) -> Result<Option<DateTime<Utc>>, String> {
+    let repository = ScheduleRepository::new();
+    let schedules = repository
+        .get_enabled()
+        .await
+        .map_err(|e| format!("Failed to load schedules: {}", e))?;
+    let now = Utc::now();
+
+    let mut next_fire: Option<DateTime<Utc>> = None;
+    for schedule in schedules {
+        let schedule = match parse_stored(&schedule) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                eprintln!("Skipping schedule {}: {}", schedule.id, e);
+                continue;
+            }
+        };
+
+        let due = due_fire_times(&schedule, now);
+        if let Some(latest) = due.first() {
+            let latest_ms = latest.timestamp_millis() as f64;
+            // Recorded before starting anything, so a crash never fires the same time twice
+            repository
+                .set_last_fired_at(schedule.id, latest_ms)
+                .await
+                .map_err(|e| format!("Failed to update schedule {}: {}", schedule.id, e))?;
+            for (scheduled_for, catch_up) in runs_to_start(&schedule, &due, now) {
+                fire(app, &schedule, scheduled_for, catch_up);
+            }
+        }
+
+        let now_local = now.with_timezone(&schedule.timezone);
+        if let Some(next) = schedule.cron.after(&now_local).next() {
+            let next = next.with_timezone(&Utc);
+            next_fire = Some(next_fire.map_or(next, |current| current.min(next)));
+        }
+    }
+    Ok(next_fire)
+}
+
+// Fire times after the last handled one up to `now`, newest first. Only as many as a catch-up
+// could use are looked at, so a frequent schedule missed for weeks stays cheap.
+fn due_fire_times(schedule: &ParsedSchedule, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let now_local = now.with_timezone(&schedule.timezone);
+    schedule
+        .cron
+        .after(&now_local)
+        .rev()
+        .map(|at| at.with_timezone(&Utc))
+        .take_while(|at| at.timestamp_millis() as f64 > schedule.handled_until)
+        .take(MAX_CATCH_UP_RUNS + 1)
+        .collect()
+}
+
+// Which of the due fire times (newest first) actually start a run, oldest first
+fn runs_to_start(
+    schedule: &ParsedSchedule,
+    due: &[DateTime<Utc>],
+    now: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, bool)> {
+    let (on_time, missed) = match due.split_first() {
+        Some((latest, older))
+            if (now - *latest).num_milliseconds() <= MISFIRE_GRACE.as_millis() as i64 =>
+        {
+            (Some(*latest), older)
+        }
+        _ => (None, due),
+    };
+
+    let mut runs: Vec<(DateTime<Utc>, bool)> = match schedule.misfire_policy {
+        MisfirePolicy::Skip => vec![],
+        // A run on time covers the missed ones as well
+        MisfirePolicy::RunOnce if on_time.is_some() => vec![],
+        MisfirePolicy::RunOnce => missed.first().map(|at| (*at, true)).into_iter().collect(),
+        MisfirePolicy::RunAll => missed
+            .iter()
+            .take(MAX_CATCH_UP_RUNS)
+            .rev()
+            .map(|at| (*at, true))
+            .collect(),
+    };
+    if !missed.is_empty() {
+        println!(
+            "Schedule {} missed fire times ({}), starting {} catch-up run(s)",
+            schedule.id,
+            schedule.misfire_policy.as_str(),
+            runs.len()
+        );
+    }
+    runs.extend(on_time.map(|at| (at, false)));
+    runs
+}
+
+fn fire(
+    app: &tauri::AppHandle,
+    schedule: &ParsedSchedule,
+    scheduled_for: DateTime<Utc>,
+    catch_up: bool,
+) {
+    let run_id = executor::new_run_id();
+    println!(
+        "Schedule {} firing script {} for {} as {}{}",
+        schedule.id,
+        schedule.script_id,
+        scheduled_for.to_rfc3339(),
+        run_id,
+        if catch_up { " (catch-up)" } else { "" }
+    );
+    let fired = ScheduleFiredEvent {
+        schedule_id: schedule.id,
+        script_id: schedule.script_id,
+        run_id: run_id.clone(),
+        scheduled_for: scheduled_for.timestamp_millis() as f64,
+        catch_up,
+    };
+    if let Err(e) = app.emit(SCHEDULE_FIRED_EVENT, fired) {
+        eprintln!("Failed to emit {}: {}", SCHEDULE_FIRED_EVENT, e);
+    }
+
+    let script_id = schedule.script_id;
+    tauri::async_runtime::spawn(async move {
+        let _database = backup::use_database().await;
+        let outcome = executor::run_stored_script(
+            script_id,
+            &run_id,
+            ExecutionMode::Scheduled,
+            vec![],
+            |_, _| {},
+        )
+        .await;
+        match outcome {
+            Ok(outcome) if !outcome.success => eprintln!(
+                "Scheduled run {} failed: {}",
+                run_id,
+                outcome.error_message()
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("Scheduled run {} could not be started: {}", run_id, e),
+        }
+    });
+}
+
+fn parse_stored(schedule: &crate::prisma::script_schedule::Data) -> Result<ParsedSchedule, String> {
+    let (cron, timezone) = parse_expression(&schedule.cron_expression, &schedule.timezone)?;
+    Ok(ParsedSchedule {
+        id: schedule.id,
+        script_id: schedule.shell_script_id,
+        cron,
+        timezone,
+        misfire_policy: MisfirePolicy::parse(&schedule.misfire_policy),
+        handled_until: schedule.last_fired_at.unwrap_or(schedule.created_at),
+    })
+}
+
+/// Parse a cron expression and time zone name. The cron crate wants a seconds field first, so
+/// standard five-field expressions get one.
+fn parse_expression(cron_expression: &str, timezone: &str) -> Result<(cron::Schedule, Tz), String> {
+    let expression = if cron_expression.split_whitespace().count() == 5 {
+        format!("0 {}", cron_expression)
+    } else {
+        cron_expression.to_string()
+    };
+    let cron = cron::Schedule::from_str(&expression)
+        .map_err(|e| format!("Invalid cron expression '{}': {}", cron_expression, e))?;
+    let timezone =
+        Tz::from_str(timezone).map_err(|_| format!("Unknown time zone '{}'", timezone))?;
+    Ok((cron, timezone))
+}
+
+fn to_info(schedule: crate::prisma::script_schedule::Data) -> ScheduleInfo {
+    let upcoming = if schedule.enabled {
+        preview_fire_times(
+            &schedule.cron_expression,
+            &schedule.timezone,
+            UPCOMING_PREVIEW_COUNT,
+        )
+        .unwrap_or_default()
+    } else {
+        vec![]
+    };
+    ScheduleInfo {
+        id: schedule.id,
+        script_id: schedule.shell_script_id,
+        misfire_policy: MisfirePolicy::parse(&schedule.misfire_policy),
+        cron_expression: schedule.cron_expression,
+        timezone: schedule.timezone,
+        enabled: schedule.enabled,
+        last_fired_at: schedule.last_fired_at,
+        upcoming,
+    }
+}
+
+fn scheduler() -> Result<&'static Scheduler, String> {
+    SCHEDULER
+        .get()
+        .ok_or_else(|| "Scheduler not initialized".to_string())
+}
+
+fn notify_changed() {
+    if let Ok(scheduler) = scheduler() {
+        scheduler.changed.notify_one();
+    }
+}
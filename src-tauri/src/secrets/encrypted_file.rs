@@ -0,0 +1,150 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use rand::RngCore;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// When set, the file key is derived from this passphrase instead of being read from
+/// `secrets.key`, so the key never has to sit on disk of a headless machine.
+pub const PASSPHRASE_ENV: &str = "SHELL_SCRIPT_MANAGER_SECRETS_PASSPHRASE";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Secrets kept in one AES-256-GCM encrypted file, used where no keyring is reachable (no
+/// D-Bus session, CI, SSH logins). Layout: salt | nonce | ciphertext of a JSON name → value map.
+pub struct EncryptedFile {
+    path: PathBuf,
+    key_path: PathBuf,
+    // Serializes read-modify-write cycles between concurrent commands
+    lock: Mutex<()>,
+}
+
+impl EncryptedFile {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join("secrets.enc"),
+            key_path: data_dir.join("secrets.key"),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.read_all()?.remove(name))
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.read_all()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write_all(&secrets)
+    }
+
+    /// Returns whether the secret was present.
+    pub fn delete(&self, name: &str) -> Result<bool, String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.read_all()?;
+        if secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.write_all(&secrets)?;
+        Ok(true)
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>, String> {
+        let data = match std::fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(format!("Failed to read secrets file: {}", e)),
+        };
+        if data.len() < SALT_LEN + NONCE_LEN {
+            return Err("Secrets file is corrupted".to_string());
+        }
+        let (salt, rest) = data.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let cipher = Aes256Gcm::new(&self.key(salt)?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                format!(
+                    "Failed to decrypt secrets file (wrong key or {} not set?)",
+                    PASSPHRASE_ENV
+                )
+            })?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Secrets file is corrupted: {}", e))
+    }
+
+    fn write_all(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let cipher = Aes256Gcm::new(&self.key(&salt)?);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let plaintext =
+            serde_json::to_vec(secrets).map_err(|e| format!("Failed to encode secrets: {}", e))?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+        let mut data = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        // Write next to the target and rename so a crash never leaves a half-written file
+        let tmp_path = self.path.with_extension("enc.tmp");
+        let _ = std::fs::remove_file(&tmp_path);
+        write_private(&tmp_path, &data)?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| format!("Failed to write secrets file: {}", e))
+    }
+
+    fn key(&self, salt: &[u8]) -> Result<Key<Aes256Gcm>, String> {
+        let mut key = [0u8; 32];
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) if !passphrase.is_empty() => {
+                Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| format!("Failed to derive secrets key: {}", e))?;
+            }
+            _ => key.copy_from_slice(&self.stored_key()?),
+        }
+        Ok(key.into())
+    }
+
+    // Random key created on first use, readable by the current user only
+    fn stored_key(&self) -> Result<Vec<u8>, String> {
+        match std::fs::read(&self.key_path) {
+            Ok(key) if key.len() == 32 => return Ok(key),
+            Ok(_) => return Err("Secrets key file is corrupted".to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read secrets key: {}", e)),
+        }
+        let mut key = vec![0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        write_private(&self.key_path, &key)?;
+        Ok(key)
+    }
+}
+
+// Create a new file with owner-only permissions; never reuses an existing file, whose mode could
+// be wider
+pub fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    file.write_all(data)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
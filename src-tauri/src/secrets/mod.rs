@@ -0,0 +1,422 @@
+mod encrypted_file;
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::db::repository::secret_repository::SecretRepository;
+use crate::executor::placeholders::shell_quote;
+use crate::executor::run_config::{is_valid_env_name, EnvVar};
+use crate::jobs;
+use crate::SECRET_STORE;
+use encrypted_file::EncryptedFile;
+
+/// What every resolved secret value is replaced with in output and history.
+pub const MASK: &str = "****";
+/// Set to `file` to bypass the OS keyring, e.g. on machines where unlocking it would prompt.
+pub const BACKEND_ENV: &str = "SHELL_SCRIPT_MANAGER_SECRETS_BACKEND";
+
+const KEYRING_SERVICE: &str = env!("CARGO_PKG_NAME");
+const REFERENCE_PREFIX: &str = "${secret:";
+// Secrets reach the shell through these variables, so their values never appear in the
+// command line
+const INJECTED_VAR_PREFIX: &str = "SSM_SECRET_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretBackend {
+    Keyring,
+    EncryptedFile,
+}
+
+impl SecretBackend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SecretBackend::Keyring => "KEYRING",
+            SecretBackend::EncryptedFile => "ENCRYPTED_FILE",
+        }
+    }
+}
+
+/// A stored secret as shown to the frontend; the value itself is never returned.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretInfo {
+    pub name: String,
+    pub backend: String,
+    pub updated_at: f64,
+}
+
+/// Reads and writes secret values: the OS keyring (Secret Service on Linux, Keychain on macOS,
+/// Credential Manager on Windows) when it is reachable, the encrypted secrets file otherwise.
+pub struct SecretStore {
+    file: EncryptedFile,
+    keyring_enabled: bool,
+}
+
+impl SecretStore {
+    /// `data_dir` holds the encrypted fallback file and its key.
+    pub fn new(data_dir: &Path) -> Self {
+        let keyring_enabled = !std::env::var(BACKEND_ENV).is_ok_and(|backend| backend == "file");
+        Self {
+            file: EncryptedFile::new(data_dir),
+            keyring_enabled,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, String> {
+        if let Some(entry) = self.keyring_entry(name) {
+            match entry.get_password() {
+                Ok(value) => return Ok(value),
+                Err(keyring::Error::NoEntry) => {}
+                Err(e) => eprintln!("Keyring unavailable, using the secrets file: {}", e),
+            }
+        }
+        self.file
+            .get(name)?
+            .ok_or_else(|| format!("Secret '{}' is not set", name))
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<SecretBackend, String> {
+        if let Some(entry) = self.keyring_entry(name) {
+            match entry.set_password(value) {
+                Ok(()) => {
+                    // A copy left in the file from an earlier headless session would go stale
+                    if let Err(e) = self.file.delete(name) {
+                        eprintln!(
+                            "Failed to remove secret '{}' from the secrets file: {}",
+                            name, e
+                        );
+                    }
+                    return Ok(SecretBackend::Keyring);
+                }
+                Err(e) => eprintln!("Keyring unavailable, using the secrets file: {}", e),
+            }
+        }
+        self.file.set(name, value)?;
+        Ok(SecretBackend::EncryptedFile)
+    }
+
+    /// Remove the secret from both backends.
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if let Some(entry) = self.keyring_entry(name) {
+            match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => eprintln!("Failed to delete secret '{}' from the keyring: {}", name, e),
+            }
+        }
+        self.file.delete(name)?;
+        Ok(())
+    }
+
+    fn keyring_entry(&self, name: &str) -> Option<keyring::Entry> {
+        if !self.keyring_enabled {
+            return None;
+        }
+        keyring::Entry::new(KEYRING_SERVICE, name)
+            .map_err(|e| eprintln!("Keyring unavailable, using the secrets file: {}", e))
+            .ok()
+    }
+}
+
+/// Replaces secret values with [`MASK`] in text produced by a run.
+#[derive(Clone, Default)]
+pub struct SecretMask {
+    // Longest first, so a secret containing another one is masked as a whole
+    values: Vec<String>,
+}
+
+impl SecretMask {
+    pub fn new(values: impl IntoIterator<Item = String>) -> Self {
+        let mut masked = vec![];
+        for value in values {
+            // Output is handled line by line, so each line of a multi-line secret is masked too
+            for line in value.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                masked.push(line.to_string());
+            }
+            if !value.is_empty() {
+                masked.push(value);
+            }
+        }
+        masked.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        masked.dedup();
+        Self { values: masked }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for value in &self.values {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), MASK);
+            }
+        }
+        text
+    }
+
+    /// Mask output that arrives in arbitrary chunks rather than whole lines.
+    pub fn stream(self) -> MaskStream {
+        MaskStream {
+            mask: self,
+            held: String::new(),
+        }
+    }
+
+    // The length of the longest end of `text` that is the start of a secret
+    fn partial_suffix_len(&self, text: &str) -> usize {
+        self.values
+            .iter()
+            .filter_map(|value| {
+                (1..value.len().min(text.len() + 1))
+                    .rev()
+                    .find(|&len| value.is_char_boundary(len) && text.ends_with(&value[..len]))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A [`SecretMask`] over a stream: text that may be the start of a secret is held back until the
+/// next chunk shows whether it is one.
+pub struct MaskStream {
+    mask: SecretMask,
+    held: String,
+}
+
+impl MaskStream {
+    /// The masked text that can be passed on now.
+    pub fn push(&mut self, text: &str) -> String {
+        self.held.push_str(text);
+        let mut masked = self.mask.apply(&self.held);
+        let keep = self.mask.partial_suffix_len(&masked);
+        self.held = masked.split_off(masked.len() - keep);
+        masked
+    }
+
+    /// Pass on whatever is held back, for when no more text is coming for now.
+    pub fn flush(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// The secrets a run uses, resolved right before it starts.
+#[derive(Clone, Default)]
+pub struct InjectedSecrets {
+    /// Set on the process next to the regular variables, never exported in a shell prelude.
+    pub env: Vec<EnvVar>,
+    pub mask: SecretMask,
+    /// Owner-only file the terminal prelude sources and deletes, see [`Self::stage_env_file`].
+    pub env_file: Option<PathBuf>,
+}
+
+// Never print the values
+impl std::fmt::Debug for InjectedSecrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InjectedSecrets")
+            .field(
+                "env",
+                &self.env.iter().map(|var| &var.name).collect::<Vec<_>>(),
+            )
+            .field("env_file", &self.env_file)
+            .finish()
+    }
+}
+
+impl InjectedSecrets {
+    /// Terminal windows run a shell we do not spawn, so the values are handed over in a file
+    /// readable by the current user only rather than on a command line other processes can see.
+    /// Windows terminals get them as process environment instead.
+    pub fn stage_env_file(&mut self) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            if self.env.is_empty() {
+                return Ok(());
+            }
+            let mut content = String::new();
+            for var in &self.env {
+                content.push_str(&format!(
+                    "export {}={}\n",
+                    var.name,
+                    shell_quote(&var.value)
+                ));
+            }
+            let path = std::env::temp_dir().join(format!(
+                "ssm-secrets-{}-{:08x}.env",
+                std::process::id(),
+                rand::random::<u32>()
+            ));
+            encrypted_file::write_private(&path, content.as_bytes())?;
+            self.env_file = Some(path);
+        }
+        Ok(())
+    }
+
+    /// The shell line that loads the staged file and removes it straight away.
+    pub fn shell_prelude(&self) -> String {
+        match &self.env_file {
+            Some(path) => {
+                let path = shell_quote(&path.to_string_lossy());
+                format!(". {}; rm -f {}\n", path, path)
+            }
+            None => String::new(),
+        }
+    }
+}
+
+/// Resolve every `${secret:NAME}` in `command` and in the values of `env`. References in the
+/// command are replaced by `reference("SSM_SECRET_NAME")`, which reads a variable set on the
+/// process in the language of whatever runs the command; variables whose values use secrets
+/// move from `env` to the returned secrets. Returns the command to run.
+pub async fn inject<F>(
+    command: &str,
+    env: &mut Vec<EnvVar>,
+    reference: F,
+) -> Result<(String, InjectedSecrets), String>
+where
+    F: Fn(&str) -> String,
+{
+    let mut names = reference_names(command);
+    for var in env.iter() {
+        names.extend(reference_names(&var.value));
+    }
+    names.sort();
+    names.dedup();
+    if names.is_empty() {
+        return Ok((command.to_string(), InjectedSecrets::default()));
+    }
+
+    let store = store()?;
+    let mut values = Vec::with_capacity(names.len());
+    for name in names {
+        let lookup = name.clone();
+        // The keyring may talk to D-Bus or show an unlock prompt
+        let value = tauri::async_runtime::spawn_blocking(move || store.get(&lookup))
+            .await
+            .map_err(|e| format!("Failed to read secret '{}': {}", name, e))??;
+        values.push((name, value));
+    }
+    let value_of = |name: &str| {
+        values
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    let mut secrets = InjectedSecrets::default();
+    let shell_command = replace_references(command, |name| {
+        reference(&format!("{}{}", INJECTED_VAR_PREFIX, name))
+    });
+    for name in reference_names(command) {
+        let var_name = format!("{}{}", INJECTED_VAR_PREFIX, name);
+        if !secrets.env.iter().any(|var| var.name == var_name) {
+            secrets.env.push(EnvVar {
+                name: var_name,
+                value: value_of(&name),
+            });
+        }
+    }
+    env.retain(|var| {
+        if reference_names(&var.value).is_empty() {
+            return true;
+        }
+        secrets.env.push(EnvVar {
+            name: var.name.clone(),
+            value: replace_references(&var.value, &value_of),
+        });
+        false
+    });
+    secrets.mask = SecretMask::new(values.into_iter().map(|(_, value)| value));
+
+    Ok((shell_command, secrets))
+}
+
+pub async fn list_secrets() -> Result<Vec<SecretInfo>, String> {
+    let secrets = SecretRepository::new()
+        .get_all()
+        .await
+        .map_err(|e| format!("Failed to load secrets: {}", e))?;
+    Ok(secrets
+        .into_iter()
+        .map(|secret| SecretInfo {
+            name: secret.name,
+            backend: secret.backend,
+            updated_at: secret.updated_at,
+        })
+        .collect())
+}
+
+pub async fn set_secret(name: String, value: String) -> Result<SecretInfo, String> {
+    let name = name.trim().to_string();
+    if !is_valid_env_name(&name) {
+        return Err(format!(
+            "'{}' is not a valid secret name (letters, digits and _)",
+            name
+        ));
+    }
+    let store = store()?;
+    let key = name.clone();
+    let backend = tauri::async_runtime::spawn_blocking(move || store.set(&key, &value))
+        .await
+        .map_err(|e| format!("Failed to store secret '{}': {}", name, e))??;
+
+    let secret = SecretRepository::new()
+        .upsert(&name, backend.as_str(), jobs::now_ms())
+        .await
+        .map_err(|e| format!("Failed to save secret '{}': {}", name, e))?;
+    Ok(SecretInfo {
+        name: secret.name,
+        backend: secret.backend,
+        updated_at: secret.updated_at,
+    })
+}
+
+pub async fn delete_secret(name: String) -> Result<(), String> {
+    let store = store()?;
+    let key = name.clone();
+    tauri::async_runtime::spawn_blocking(move || store.delete(&key))
+        .await
+        .map_err(|e| format!("Failed to delete secret '{}': {}", name, e))??;
+    SecretRepository::new()
+        .delete(&name)
+        .await
+        .map_err(|e| format!("Failed to delete secret '{}': {}", name, e))
+}
+
+fn store() -> Result<&'static SecretStore, String> {
+    SECRET_STORE
+        .get()
+        .ok_or_else(|| "Secret store not initialized".to_string())
+}
+
+/// Names referenced as `${secret:NAME}` in `text`, in order of appearance.
+fn reference_names(text: &str) -> Vec<String> {
+    let mut names = vec![];
+    replace_references(text, |name| {
+        names.push(name.to_string());
+        String::new()
+    });
+    names
+}
+
+// Malformed references (no closing brace, invalid name) are left as they are
+fn replace_references<F>(text: &str, mut replacement: F) -> String
+where
+    F: FnMut(&str) -> String,
+{
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(REFERENCE_PREFIX) {
+        let after = &rest[start + REFERENCE_PREFIX.len()..];
+        match after.find('}') {
+            Some(end) if is_valid_env_name(&after[..end]) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&replacement(&after[..end]));
+                rest = &after[end + 1..];
+            }
+            _ => {
+                result.push_str(&rest[..start + REFERENCE_PREFIX.len()]);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,452 @@
+#[cfg(test)]
+mod tests;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Child;
+
+use crate::db::repository::ssh_target_repository::{SshTargetFields, SshTargetRepository};
+use crate::executor;
+use crate::executor::interpreter;
+use crate::executor::placeholders::shell_quote;
+use crate::executor::run_config::{expand_path, ResolvedRunConfig};
+use crate::jobs;
+use crate::prisma::ssh_target;
+
+const DEFAULT_PORT: u16 = 22;
+const CONNECT_TIMEOUT_SECONDS: u32 = 15;
+/// A shared connection stays open this long after the last run on its target ended.
+#[cfg(unix)]
+const CONTROL_PERSIST_SECONDS: u32 = 600;
+/// Upper bound for [`test_target`], which also covers a server that accepts the connection
+/// and then never answers.
+const TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a timed-out remote script gets to exit after SIGTERM before it is killed.
+const REMOTE_KILL_AFTER_SECONDS: u64 = 5;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SshAuth {
+    /// Whatever keys the running ssh-agent offers.
+    Agent,
+    /// One private key file, nothing else.
+    Key,
+}
+
+impl SshAuth {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SshAuth::Agent => "AGENT",
+            SshAuth::Key => "KEY",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "KEY" => SshAuth::Key,
+            _ => SshAuth::Agent,
+        }
+    }
+}
+
+/// A machine scripts can run on. Runs go through the system `ssh` client, so its agent and
+/// configuration apply, but host keys are always checked against known_hosts.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshTarget {
+    pub id: i32,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub auth: SshAuth,
+    pub key_path: Option<String>,
+    /// `~/.ssh/known_hosts` when not set.
+    pub known_hosts_path: Option<String>,
+    /// Where remote runs start; the login directory when not set.
+    pub remote_dir: Option<String>,
+}
+
+impl SshTarget {
+    /// `user@host:port`, as recorded in the history.
+    pub fn address(&self) -> String {
+        format!("{}@{}:{}", self.username, self.host, self.port)
+    }
+}
+
+impl From<ssh_target::Data> for SshTarget {
+    fn from(data: ssh_target::Data) -> Self {
+        Self {
+            id: data.id,
+            name: data.name,
+            host: data.host,
+            port: u16::try_from(data.port).unwrap_or(DEFAULT_PORT),
+            username: data.username,
+            auth: SshAuth::parse(&data.auth_method),
+            key_path: data.key_path,
+            known_hosts_path: data.known_hosts_path,
+            remote_dir: data.remote_dir,
+        }
+    }
+}
+
+/// A target as sent by the frontend to be saved.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshTargetInput {
+    /// `None` when creating a target.
+    pub id: Option<i32>,
+    pub name: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: String,
+    pub auth: SshAuth,
+    pub key_path: Option<String>,
+    pub known_hosts_path: Option<String>,
+    pub remote_dir: Option<String>,
+}
+
+impl SshTargetInput {
+    // Trimmed, with empty optional values as `None`
+    fn fields(&self) -> SshTargetFields {
+        SshTargetFields {
+            name: self.name.trim().to_string(),
+            host: self.host.trim().to_string(),
+            port: i32::from(self.port.unwrap_or(DEFAULT_PORT)),
+            username: self.username.trim().to_string(),
+            auth_method: self.auth.as_str().to_string(),
+            key_path: non_empty(&self.key_path),
+            known_hosts_path: non_empty(&self.known_hosts_path),
+            remote_dir: non_empty(&self.remote_dir),
+        }
+    }
+}
+
+pub async fn list_targets() -> Result<Vec<SshTarget>, String> {
+    let targets = SshTargetRepository::new()
+        .get_all()
+        .await
+        .map_err(|e| format!("Failed to load SSH targets: {}", e))?;
+    Ok(targets.into_iter().map(SshTarget::from).collect())
+}
+
+pub async fn load_target(target_id: i32) -> Result<SshTarget, String> {
+    SshTargetRepository::new()
+        .get(target_id)
+        .await
+        .map_err(|e| format!("Failed to load SSH target {}: {}", target_id, e))?
+        .map(SshTarget::from)
+        .ok_or_else(|| format!("SSH target {} not found", target_id))
+}
+
+pub async fn save_target(input: SshTargetInput) -> Result<SshTarget, String> {
+    let fields = validate(input.fields())?;
+    let repository = SshTargetRepository::new();
+    let saved = match input.id {
+        Some(target_id) => {
+            // The shared connection was authenticated with the old settings
+            close_connection(target_id).await;
+            repository.update(target_id, fields).await
+        }
+        None => repository.create(fields).await,
+    }
+    .map_err(|e| format!("Failed to save SSH target: {}", e))?;
+    Ok(saved.into())
+}
+
+pub async fn delete_target(target_id: i32) -> Result<(), String> {
+    close_connection(target_id).await;
+    SshTargetRepository::new()
+        .delete(target_id)
+        .await
+        .map_err(|e| format!("Failed to delete SSH target {}: {}", target_id, e))
+}
+
+/// Connect to the target and run `true`, the way a run would connect. Fails with what ssh
+/// reported, e.g. an unknown host key or a rejected key.
+pub async fn test_target(target_id: i32) -> Result<(), String> {
+    let target = load_target(target_id).await?;
+    let mut cmd = ssh_command(&target, &[])?;
+    cmd.arg("true")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(TEST_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| {
+            format!(
+                "{} did not answer within {:?}",
+                target.address(),
+                TEST_TIMEOUT
+            )
+        })?
+        .map_err(|e| format!("Failed to start ssh: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.contains("Host key verification failed") {
+        return Err(format!(
+            "{}\nThe host key of {} is not in known_hosts or does not match it. Check the \
+             fingerprint and add it, e.g. with: ssh-keyscan -p {} {} >> ~/.ssh/known_hosts",
+            stderr, target.host, target.port, target.host
+        ));
+    }
+    if stderr.is_empty() {
+        return Err(format!("ssh exited with code {:?}", output.status.code()));
+    }
+    Err(stderr)
+}
+
+/// Close the shared connection to the target, if one is open. The next run reconnects.
+pub async fn disconnect_target(target_id: i32) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        let target = load_target(target_id).await?;
+        let mut cmd = ssh_command(&target, &["-O", "exit"])?;
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // Fails when no connection is open, which is fine
+        let _ = cmd.status().await;
+    }
+    #[cfg(not(unix))]
+    let _ = target_id;
+    Ok(())
+}
+
+// Leaving a stale connection open is not worth failing a save or delete for
+async fn close_connection(target_id: i32) {
+    if let Err(e) = disconnect_target(target_id).await {
+        eprintln!(
+            "Failed to close the SSH connection of target {}: {}",
+            target_id, e
+        );
+    }
+}
+
+/// Start `command` on `target` as a registered job with piped output, the remote counterpart of
+/// [`executor::spawn_job`]. The run's variables and secrets are exported on the remote side, and
+/// the command runs with the run's interpreter as found on the remote `PATH`, or with the remote
+/// login shell when it has none.
+/// Stdin carries the script first; it stays open for the script with `pipe_stdin` and is closed
+/// after the script otherwise, so remote reads see EOF.
+/// Cancelling closes the connection; remote processes that neither read nor write may keep
+/// running after that. `timeout_seconds` stops them on the host too, if it has `timeout`.
+pub async fn spawn_job(
+    run_id: &str,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+    target: &SshTarget,
+    pipe_stdin: bool,
+    timeout_seconds: Option<u64>,
+) -> Result<Child, String> {
+    let script = remote_script(command, run_config, target.remote_dir.as_deref())?;
+    let mut cmd = ssh_command(target, &[])?;
+    cmd.arg(bootstrap(timeout_seconds))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    jobs::isolate_process_group(&mut cmd);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start ssh: {}", e))?;
+
+    // Written before the job is registered so input sent to the run can only come after it
+    let line = format!("{}\n", STANDARD.encode(script));
+    if let Some(stdin) = child.stdin.as_mut() {
+        if let Err(e) = stdin.write_all(line.as_bytes()).await {
+            // ssh itself explains on stderr why it went away
+            eprintln!("Failed to send the script to {}: {}", target.address(), e);
+        }
+    }
+    if !pipe_stdin {
+        drop(child.stdin.take());
+    }
+    executor::register_job(run_id, command, child)
+}
+
+// Run by the remote user's shell, whichever it is. Reads the first line of stdin, decodes it into
+// a private temp file and runs that with the login shell; the rest of stdin stays for the script.
+// Nothing of the script, secrets included, appears on a command line on either machine.
+// Killing ssh only closes the connection, so with a timeout the script runs under `timeout` where
+// the host has one that can kill too, and is stopped there as well.
+fn bootstrap(timeout_seconds: Option<u64>) -> String {
+    let login_shell = r#""${SHELL:-/bin/sh}" -l "$f""#;
+    let run = match timeout_seconds {
+        Some(seconds) => format!(
+            "{{ if timeout -k 1 1 true 2>/dev/null; then exec timeout -k {kill} {seconds} {shell}; fi; exec {shell}; }}",
+            kill = REMOTE_KILL_AFTER_SECONDS,
+            seconds = seconds,
+            shell = login_shell
+        ),
+        None => format!("exec {}", login_shell),
+    };
+    format!(
+        r#"sh -c 'f=$(mktemp) || exit 1; IFS= read -r s && printf %s "$s" | base64 -d > "$f" && {}; rm -f "$f"; exit 1'"#,
+        run
+    )
+}
+
+// `ssh` with everything needed to reach the target plus `options`, up to and including the
+// destination; the remote command goes after it
+fn ssh_command(target: &SshTarget, options: &[&str]) -> Result<tokio::process::Command, String> {
+    let mut cmd = tokio::process::Command::new("ssh");
+    // Never prompt: there is nobody to answer
+    cmd.arg("-o")
+        .arg("BatchMode=yes")
+        .arg("-o")
+        .arg("StrictHostKeyChecking=yes")
+        .arg("-o")
+        .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECONDS));
+    if let Some(known_hosts) = &target.known_hosts_path {
+        cmd.arg("-o").arg(format!(
+            "UserKnownHostsFile={}",
+            config_path(&expand_local_path(known_hosts)?)
+        ));
+    }
+    if target.auth == SshAuth::Key {
+        let key_path = target
+            .key_path
+            .as_deref()
+            .ok_or_else(|| format!("SSH target '{}' has no key file", target.name))?;
+        cmd.arg("-o")
+            .arg(format!(
+                "IdentityFile={}",
+                config_path(&expand_local_path(key_path)?)
+            ))
+            .arg("-o")
+            .arg("IdentitiesOnly=yes");
+    }
+    #[cfg(unix)]
+    {
+        // One connection per target, shared by its runs. %C is a hash of host, port and user
+        // only, so the id keeps targets that reach the same account with other keys apart.
+        cmd.arg("-o")
+            .arg("ControlMaster=auto")
+            .arg("-o")
+            .arg(format!(
+                "ControlPath=\"{}/ssm-{}-%C\"",
+                control_dir()?.replace('%', "%%"),
+                target.id
+            ))
+            .arg("-o")
+            .arg(format!("ControlPersist={}", CONTROL_PERSIST_SECONDS));
+    }
+    cmd.args(options)
+        .arg("-p")
+        .arg(target.port.to_string())
+        .arg("-l")
+        .arg(&target.username)
+        .arg("--")
+        .arg(&target.host);
+    Ok(cmd)
+}
+
+// The script the bootstrap runs: it deletes itself first, then recreates the run configuration
+// and hands the command to its interpreter
+fn remote_script(
+    command: &str,
+    run_config: &ResolvedRunConfig,
+    remote_dir: Option<&str>,
+) -> Result<String, String> {
+    let mut script = String::from("rm -f -- \"$0\"\n");
+    for var in run_config.env.iter().chain(&run_config.secrets.env) {
+        script.push_str(&format!(
+            "export {}={}\n",
+            var.name,
+            shell_quote(&var.value)
+        ));
+    }
+    if let Some(dir) = remote_dir {
+        script.push_str(&format!("cd {} || exit 1\n", remote_quote_dir(dir)));
+    }
+    match interpreter::resolve(run_config.interpreter, command)? {
+        Some(interpreter) => script.push_str(&interpreter.remote_invocation(command)),
+        None => script.push_str(command),
+    }
+    script.push('\n');
+    Ok(script)
+}
+
+// A leading `~/` is left for the remote shell to expand
+fn remote_quote_dir(dir: &str) -> String {
+    match dir.strip_prefix("~/") {
+        Some(rest) => format!("~/{}", shell_quote(rest)),
+        None if dir == "~" => "~".to_string(),
+        None => shell_quote(dir),
+    }
+}
+
+// Control sockets live in ~/.ssh: unix socket paths are limited to about 100 bytes, which rules
+// out the app data directory on macOS
+#[cfg(unix)]
+fn control_dir() -> Result<String, String> {
+    use std::os::unix::fs::DirBuilderExt;
+    let dir = format!("{}/.ssh", executor::resolve_home());
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+    Ok(dir)
+}
+
+fn expand_local_path(path: &str) -> Result<String, String> {
+    expand_path(path, |name| std::env::var(name).ok())
+}
+
+// Paths in -o options are parsed like ssh_config lines and expand %-tokens
+fn config_path(path: &str) -> String {
+    format!("\"{}\"", path.replace('%', "%%"))
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_ref()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn validate(mut fields: SshTargetFields) -> Result<SshTargetFields, String> {
+    if fields.name.is_empty() {
+        return Err("SSH target name must not be empty".to_string());
+    }
+    // Anything starting with '-' would be read as an option by ssh
+    let is_word = |value: &str| {
+        !value.is_empty() && !value.starts_with('-') && !value.contains(char::is_whitespace)
+    };
+    if !is_word(&fields.host) {
+        return Err(format!("'{}' is not a valid host", fields.host));
+    }
+    if !is_word(&fields.username) || fields.username.contains('@') {
+        return Err(format!("'{}' is not a valid user name", fields.username));
+    }
+    if fields.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    if fields.auth_method == SshAuth::Key.as_str() {
+        let key_path = fields
+            .key_path
+            .as_deref()
+            .ok_or("Key authentication needs a private key file")?;
+        if !std::path::Path::new(&expand_local_path(key_path)?).is_file() {
+            return Err(format!("Key file '{}' does not exist", key_path));
+        }
+    } else {
+        fields.key_path = None;
+    }
+    if let Some(known_hosts) = &fields.known_hosts_path {
+        if !std::path::Path::new(&expand_local_path(known_hosts)?).is_file() {
+            return Err(format!("Known hosts file '{}' does not exist", known_hosts));
+        }
+    }
+    Ok(fields)
+}
@@ -0,0 +1,385 @@
+//! The tests that connect run against a throwaway OpenSSH server on 127.0.0.1, so they are
+//! ignored by default:
+//!
+//!     cargo test ssh::tests -- --ignored
+//!
+//! `sshd` is taken from `$SSHD`, else `/usr/sbin/sshd`. It runs as the current user, who must be
+//! allowed to log in to it; as root OpenSSH also wants its privilege separation directory
+//! (usually `/run/sshd`) to exist.
+
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::*;
+use crate::executor::interpreter::Interpreter;
+use crate::executor::limits::ExecutionLimits;
+use crate::executor::run_config::EnvVar;
+use crate::executor::ExecutionOutcome;
+use crate::secrets::SecretMask;
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+// An sshd with its own host key that accepts one client key, removed again on drop
+struct Sshd {
+    dir: PathBuf,
+    port: u16,
+    process: std::process::Child,
+}
+
+impl Sshd {
+    fn start() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "ssm-sshd-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        keygen(&dir.join("host_key"));
+        keygen(&dir.join("client_key"));
+        std::fs::copy(dir.join("client_key.pub"), dir.join("authorized_keys")).unwrap();
+
+        // Free right now; the small race with other processes is acceptable in a test
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = dir.join("sshd_config");
+        std::fs::write(
+            &config,
+            format!(
+                "ListenAddress 127.0.0.1\n\
+                 Port {port}\n\
+                 HostKey {dir}/host_key\n\
+                 AuthorizedKeysFile {dir}/authorized_keys\n\
+                 PidFile {dir}/sshd.pid\n\
+                 StrictModes no\n\
+                 UsePAM no\n\
+                 PasswordAuthentication no\n\
+                 KbdInteractiveAuthentication no\n\
+                 PubkeyAuthentication yes\n",
+                port = port,
+                dir = dir.display()
+            ),
+        )
+        .unwrap();
+
+        let sshd = std::env::var("SSHD").unwrap_or_else(|_| "/usr/sbin/sshd".to_string());
+        let log = std::fs::File::create(dir.join("sshd.log")).unwrap();
+        let process = Command::new(&sshd)
+            .arg("-D")
+            .arg("-e")
+            .arg("-f")
+            .arg(&config)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(log)
+            .spawn()
+            .unwrap_or_else(|e| panic!("Failed to start {}: {}", sshd, e));
+        let sshd = Self { dir, port, process };
+
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                started.elapsed() < STARTUP_TIMEOUT,
+                "sshd did not start listening: {}",
+                sshd.log()
+            );
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        sshd
+    }
+
+    // known_hosts that trusts this server's host key
+    fn known_hosts(&self) -> PathBuf {
+        let host_key = std::fs::read_to_string(self.dir.join("host_key.pub")).unwrap();
+        let mut fields = host_key.split_whitespace();
+        let (kind, key) = (fields.next().unwrap(), fields.next().unwrap());
+        let path = self.dir.join("known_hosts");
+        std::fs::write(
+            &path,
+            format!("[127.0.0.1]:{} {} {}\n", self.port, kind, key),
+        )
+        .unwrap();
+        path
+    }
+
+    fn target(&self, known_hosts: &Path) -> SshTarget {
+        SshTarget {
+            id: 1,
+            name: "local sshd".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: self.port,
+            username: current_user(),
+            auth: SshAuth::Key,
+            key_path: Some(self.dir.join("client_key").display().to_string()),
+            known_hosts_path: Some(known_hosts.display().to_string()),
+            remote_dir: None,
+        }
+    }
+
+    fn log(&self) -> String {
+        std::fs::read_to_string(self.dir.join("sshd.log")).unwrap_or_default()
+    }
+}
+
+impl Drop for Sshd {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn keygen(path: &Path) {
+    let status = Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(path)
+        .status()
+        .expect("Failed to run ssh-keygen");
+    assert!(status.success());
+}
+
+fn current_user() -> String {
+    let output = Command::new("id").arg("-un").output().unwrap();
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+async fn run(
+    target: &SshTarget,
+    run_id: &str,
+    command: &str,
+    run_config: &ResolvedRunConfig,
+) -> ExecutionOutcome {
+    let _ = crate::JOB_REGISTRY.set(Default::default());
+    let child = spawn_job(run_id, command, run_config, target, false, None)
+        .await
+        .unwrap();
+    executor::run_to_completion(
+        run_id,
+        child,
+        &ExecutionLimits::default(),
+        &SecretMask::default(),
+        Instant::now(),
+        |_, _| {},
+    )
+    .await
+}
+
+// Whether ssh has a shared connection open for the target
+async fn master_running(target: &SshTarget) -> bool {
+    let mut cmd = ssh_command(target, &["-O", "check"]).unwrap();
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    cmd.status().await.unwrap().success()
+}
+
+async fn close_master(target: &SshTarget) {
+    let mut cmd = ssh_command(target, &["-O", "exit"]).unwrap();
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let _ = cmd.status().await;
+}
+
+// What the remote side does with the first line of stdin, here done by the local sh
+fn bootstrap_output(
+    command: &str,
+    run_config: &ResolvedRunConfig,
+    input: &str,
+    timeout_seconds: Option<u64>,
+) -> std::process::Output {
+    let script = remote_script(command, run_config, None).unwrap();
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(bootstrap(timeout_seconds))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdin = format!("{}\n{}", STANDARD.encode(script), input);
+    std::io::Write::write_all(&mut child.stdin.take().unwrap(), stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn run_bootstrap(command: &str, run_config: &ResolvedRunConfig, input: &str) -> String {
+    let output = bootstrap_output(command, run_config, input, None);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn bootstrap_runs_the_script_with_its_interpreter() {
+    let mut run_config = ResolvedRunConfig::default();
+    run_config.env.push(EnvVar {
+        name: "GREETING".to_string(),
+        value: "it's \"quoted\"".to_string(),
+    });
+
+    if Interpreter::Python.locate().is_ok() {
+        let command = "#!/usr/bin/env python3\nimport os, sys\n\
+                       print(os.environ['GREETING'], sys.stdin.readline().strip())";
+        assert_eq!(
+            run_bootstrap(command, &run_config, "typed\n"),
+            "it's \"quoted\" typed\n"
+        );
+    }
+    if Interpreter::Node.locate().is_ok() {
+        run_config.interpreter = Some(Interpreter::Node);
+        assert_eq!(
+            run_bootstrap("console.log(process.env.GREETING)", &run_config, ""),
+            "it's \"quoted\"\n"
+        );
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn targets_for_the_same_account_do_not_share_a_connection() {
+    let target = |id, key_path: &str| SshTarget {
+        id,
+        name: format!("target {}", id),
+        host: "example.com".to_string(),
+        port: 22,
+        username: "deploy".to_string(),
+        auth: SshAuth::Key,
+        key_path: Some(key_path.to_string()),
+        known_hosts_path: None,
+        remote_dir: None,
+    };
+    let control_path = |target: &SshTarget| {
+        let cmd = ssh_command(target, &[]).unwrap();
+        cmd.as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .find(|arg| arg.starts_with("ControlPath="))
+            .unwrap()
+    };
+
+    let first = control_path(&target(1, "/keys/ops"));
+    assert!(first.ends_with("/ssm-1-%C\""), "{}", first);
+    assert_ne!(first, control_path(&target(2, "/keys/readonly")));
+}
+
+#[test]
+fn bootstrap_stops_the_script_at_its_timeout() {
+    let has_timeout = Command::new("sh")
+        .arg("-c")
+        .arg("timeout -k 1 1 true")
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !has_timeout {
+        return;
+    }
+    let marker = std::env::temp_dir().join(format!("ssm-timeout-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    let mut run_config = ResolvedRunConfig::default();
+    run_config.env.push(EnvVar {
+        name: "MARKER".to_string(),
+        value: marker.display().to_string(),
+    });
+
+    // The background child must go as well, not just the script's shell
+    let started = Instant::now();
+    let output = bootstrap_output(
+        "(sleep 2; touch \"$MARKER\") & sleep 10; echo late",
+        &run_config,
+        "",
+        Some(1),
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(output.status.code(), Some(124));
+    assert!(output.stdout.is_empty());
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(!marker.exists());
+}
+
+#[tokio::test]
+#[ignore = "starts sshd on 127.0.0.1"]
+async fn runs_a_script_remotely() {
+    let sshd = Sshd::start();
+    let mut target = sshd.target(&sshd.known_hosts());
+    target.remote_dir = Some(sshd.dir.display().to_string());
+    let mut run_config = ResolvedRunConfig::default();
+    run_config.env.push(EnvVar {
+        name: "GREETING".to_string(),
+        value: "it's \"quoted\"".to_string(),
+    });
+
+    let outcome = run(
+        &target,
+        "ssh-run",
+        "echo \"$GREETING\"; pwd; read -r line; echo \"read:$line\"; exit 3",
+        &run_config,
+    )
+    .await;
+    close_master(&target).await;
+
+    assert_eq!(
+        outcome.stdout,
+        format!("it's \"quoted\"\n{}\nread:\n", sshd.dir.display()),
+        "stderr: {}\nsshd: {}",
+        outcome.stderr,
+        sshd.log()
+    );
+    assert_eq!(outcome.exit_code, Some(3));
+}
+
+#[tokio::test]
+#[ignore = "starts sshd on 127.0.0.1"]
+async fn rejects_an_unknown_host_key() {
+    let sshd = Sshd::start();
+    let known_hosts = sshd.dir.join("empty_known_hosts");
+    std::fs::write(&known_hosts, "").unwrap();
+    let target = sshd.target(&known_hosts);
+
+    let outcome = run(
+        &target,
+        "ssh-unknown-host",
+        "echo reached",
+        &ResolvedRunConfig::default(),
+    )
+    .await;
+    close_master(&target).await;
+
+    assert!(!outcome.success);
+    assert!(outcome.stdout.is_empty(), "stdout: {}", outcome.stdout);
+    assert!(
+        outcome.stderr.contains("Host key verification failed"),
+        "stderr: {}",
+        outcome.stderr
+    );
+}
+
+#[tokio::test]
+#[ignore = "starts sshd on 127.0.0.1"]
+async fn reuses_the_shared_connection() {
+    let mut sshd = Sshd::start();
+    let target = sshd.target(&sshd.known_hosts());
+    let run_config = ResolvedRunConfig::default();
+
+    let first = run(&target, "ssh-first", "echo first", &run_config).await;
+    assert_eq!(first.stdout, "first\n", "stderr: {}", first.stderr);
+    assert!(master_running(&target).await);
+
+    // With the listener gone only the open connection can carry the next run
+    let _ = sshd.process.kill();
+    let _ = sshd.process.wait();
+    assert!(TcpStream::connect(("127.0.0.1", sshd.port)).is_err());
+
+    let second = run(&target, "ssh-second", "echo second", &run_config).await;
+    close_master(&target).await;
+    assert_eq!(second.stdout, "second\n", "stderr: {}", second.stderr);
+}
@@ -0,0 +1,343 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::db::repository::app_state_repository::AppStateRepository;
+use crate::executor::placeholders::shell_quote;
+
+/// Stands for the program to run in a custom command template: as separate arguments when it
+/// is an unquoted word of its own, as one shell-quoted string otherwise.
+pub const COMMAND_PLACEHOLDER: &str = "{command}";
+
+/// The terminal emulators scripts can be opened in on Linux, in auto-detection order.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TerminalEmulator {
+    Kitty,
+    Alacritty,
+    Wezterm,
+    Konsole,
+    Foot,
+    GnomeTerminal,
+    Xterm,
+    /// The user's own command template.
+    Custom,
+}
+
+const BUILT_IN: [TerminalEmulator; 7] = [
+    TerminalEmulator::Kitty,
+    TerminalEmulator::Alacritty,
+    TerminalEmulator::Wezterm,
+    TerminalEmulator::Konsole,
+    TerminalEmulator::Foot,
+    TerminalEmulator::GnomeTerminal,
+    TerminalEmulator::Xterm,
+];
+
+impl TerminalEmulator {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TerminalEmulator::Kitty => "KITTY",
+            TerminalEmulator::Alacritty => "ALACRITTY",
+            TerminalEmulator::Wezterm => "WEZTERM",
+            TerminalEmulator::Konsole => "KONSOLE",
+            TerminalEmulator::Foot => "FOOT",
+            TerminalEmulator::GnomeTerminal => "GNOME_TERMINAL",
+            TerminalEmulator::Xterm => "XTERM",
+            TerminalEmulator::Custom => "CUSTOM",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        BUILT_IN
+            .into_iter()
+            .chain([TerminalEmulator::Custom])
+            .find(|emulator| emulator.as_str() == value)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            TerminalEmulator::Kitty => "kitty",
+            TerminalEmulator::Alacritty => "Alacritty",
+            TerminalEmulator::Wezterm => "WezTerm",
+            TerminalEmulator::Konsole => "Konsole",
+            TerminalEmulator::Foot => "foot",
+            TerminalEmulator::GnomeTerminal => "GNOME Terminal",
+            TerminalEmulator::Xterm => "xterm",
+            TerminalEmulator::Custom => "Custom command",
+        }
+    }
+
+    fn binary(&self) -> Option<&'static str> {
+        match self {
+            TerminalEmulator::Kitty => Some("kitty"),
+            TerminalEmulator::Alacritty => Some("alacritty"),
+            TerminalEmulator::Wezterm => Some("wezterm"),
+            TerminalEmulator::Konsole => Some("konsole"),
+            TerminalEmulator::Foot => Some("foot"),
+            TerminalEmulator::GnomeTerminal => Some("gnome-terminal"),
+            TerminalEmulator::Xterm => Some("xterm"),
+            TerminalEmulator::Custom => None,
+        }
+    }
+
+    // How each emulator is told to run a program instead of the user's shell
+    fn command_line(&self, program: &[String]) -> Vec<String> {
+        let prefix: &[&str] = match self {
+            TerminalEmulator::Kitty | TerminalEmulator::Foot => &[],
+            TerminalEmulator::Alacritty | TerminalEmulator::Konsole | TerminalEmulator::Xterm => {
+                &["-e"]
+            }
+            TerminalEmulator::Wezterm => &["start", "--"],
+            TerminalEmulator::GnomeTerminal => &["--"],
+            TerminalEmulator::Custom => &[],
+        };
+        let mut args = vec![self.binary().unwrap_or_default().to_string()];
+        args.extend(prefix.iter().map(|arg| arg.to_string()));
+        args.extend(program.iter().cloned());
+        args
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalInfo {
+    pub emulator: TerminalEmulator,
+    pub name: String,
+    pub installed: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalSettings {
+    /// The chosen emulator; `None` means auto-detect.
+    pub emulator: Option<TerminalEmulator>,
+    /// Used when `emulator` is `CUSTOM`, e.g. `tilix -e {command}`.
+    pub custom_template: Option<String>,
+    /// What auto-detection picks on this machine right now.
+    pub detected: Option<TerminalEmulator>,
+    pub available: Vec<TerminalInfo>,
+}
+
+pub async fn get_settings() -> Result<TerminalSettings, String> {
+    let (emulator, custom_template) = load_preference().await?;
+    Ok(TerminalSettings {
+        emulator,
+        custom_template,
+        detected: detect(),
+        available: BUILT_IN
+            .into_iter()
+            .map(|emulator| TerminalInfo {
+                emulator,
+                name: emulator.name().to_string(),
+                installed: is_installed(emulator),
+            })
+            .collect(),
+    })
+}
+
+/// Store the emulator to open scripts in, `None` for auto-detection. A custom template must
+/// contain [`COMMAND_PLACEHOLDER`]; it is kept even when another emulator is chosen.
+pub async fn set_preference(
+    emulator: Option<TerminalEmulator>,
+    custom_template: Option<String>,
+) -> Result<TerminalSettings, String> {
+    let custom_template = custom_template
+        .map(|template| template.trim().to_string())
+        .filter(|template| !template.is_empty());
+    match &custom_template {
+        Some(template) => {
+            custom_command_line(template, &["true".to_string()])?;
+        }
+        None if emulator == Some(TerminalEmulator::Custom) => {
+            return Err("A custom terminal needs a command template".to_string());
+        }
+        None => {}
+    }
+    AppStateRepository::new()
+        .set_terminal_preference(
+            emulator.map(|emulator| emulator.as_str().to_string()),
+            custom_template,
+        )
+        .await
+        .map_err(|e| format!("Failed to save terminal preference: {}", e))?;
+    get_settings().await
+}
+
+/// Open a terminal window running the executable `program`, in the preferred emulator or the
+/// first installed one.
+#[cfg(target_os = "linux")]
+pub async fn open(program: &std::path::Path) -> Result<(), String> {
+    let program = vec![program.to_string_lossy().to_string()];
+    let (emulator, custom_template) = load_preference().await?;
+    let args = match emulator {
+        Some(TerminalEmulator::Custom) => {
+            let template = custom_template
+                .ok_or_else(|| "No custom terminal command is configured".to_string())?;
+            custom_command_line(&template, &program)?
+        }
+        Some(emulator) => emulator.command_line(&program),
+        None => detect()
+            .ok_or_else(|| {
+                format!(
+                    "No supported terminal emulator found. Install one of {} or configure a custom command",
+                    BUILT_IN
+                        .iter()
+                        .filter_map(|emulator| emulator.binary())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?
+            .command_line(&program),
+    };
+
+    println!("Opening terminal: {}", args[0]);
+    std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .spawn()
+        .map_err(|e| format!("Failed to open terminal '{}': {}", args[0], e))?;
+    Ok(())
+}
+
+async fn load_preference() -> Result<(Option<TerminalEmulator>, Option<String>), String> {
+    let state = AppStateRepository::new()
+        .get_app_state()
+        .await
+        .map_err(|e| format!("Failed to load terminal preference: {}", e))?;
+    Ok(state.map_or((None, None), |state| {
+        (
+            state
+                .terminal_emulator
+                .as_deref()
+                .and_then(TerminalEmulator::parse),
+            state.terminal_template,
+        )
+    }))
+}
+
+// $TERMINAL wins when it names one of the built-in emulators, then the first one installed
+fn detect() -> Option<TerminalEmulator> {
+    let from_env = std::env::var("TERMINAL").ok().and_then(|terminal| {
+        let binary = std::path::Path::new(&terminal)
+            .file_name()?
+            .to_string_lossy()
+            .to_string();
+        BUILT_IN
+            .into_iter()
+            .find(|emulator| emulator.binary() == Some(binary.as_str()))
+    });
+    from_env
+        .into_iter()
+        .chain(BUILT_IN)
+        .find(|emulator| is_installed(*emulator))
+}
+
+fn is_installed(emulator: TerminalEmulator) -> bool {
+    emulator.binary().and_then(find_in_path).is_some()
+}
+
+/// The first executable named `binary` on the `PATH`.
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Whether `path` is a file the current user may execute.
+#[cfg(unix)]
+pub fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+// Split the template into words like a shell would (quotes and backslashes, no expansions) and
+// put `program` in place of the placeholder
+fn custom_command_line(template: &str, program: &[String]) -> Result<Vec<String>, String> {
+    if !template.contains(COMMAND_PLACEHOLDER) {
+        return Err(format!(
+            "The terminal command must contain {}",
+            COMMAND_PLACEHOLDER
+        ));
+    }
+    let quoted = program
+        .iter()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut args = vec![];
+    for (word, quoted_word) in split_words(template)? {
+        if word == COMMAND_PLACEHOLDER && !quoted_word {
+            args.extend(program.iter().cloned());
+        } else {
+            args.push(word.replace(COMMAND_PLACEHOLDER, &quoted));
+        }
+    }
+    if args.is_empty() || args[0].is_empty() {
+        return Err("The terminal command must start with a program".to_string());
+    }
+    Ok(args)
+}
+
+// Each word comes with whether any part of it was quoted or escaped
+fn split_words(text: &str) -> Result<Vec<(String, bool)>, String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push((word, quoted));
+                }
+                quoted = false;
+            }
+            '\'' => {
+                quoted = true;
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated ' in terminal command".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                quoted = true;
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("Unterminated \" in terminal command".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated \" in terminal command".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                quoted = true;
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| "Trailing \\ in terminal command".to_string())?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word.map(|word| (word, quoted)));
+    Ok(words)
+}
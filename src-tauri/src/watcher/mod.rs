@@ -0,0 +1,397 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::Emitter;
+
+use crate::backup;
+use crate::db::repository::app_state_repository::AppStateRepository;
+use crate::db::repository::watch_trigger_repository::{WatchTriggerFields, WatchTriggerRepository};
+use crate::executor;
+use crate::executor::history::ExecutionMode;
+use crate::executor::run_config::EnvVar;
+use crate::WATCHERS;
+
+/// Emitted for every run a trigger starts.
+pub const WATCH_TRIGGERED_EVENT: &str = "watch-triggered";
+/// The paths that changed, one per line, as seen by the triggered script.
+pub const CHANGED_PATHS_VAR: &str = "SSM_CHANGED_PATHS";
+
+const DEFAULT_DEBOUNCE_MS: i32 = 500;
+const MIN_DEBOUNCE_MS: i32 = 50;
+const MAX_DEBOUNCE_MS: i32 = 60_000;
+/// More changed paths than this are not listed in the variable, which would otherwise risk
+/// exceeding the size limit of the process environment.
+const MAX_CHANGED_PATHS: usize = 1000;
+
+/// The active file watchers, one per enabled trigger, unless they are paused.
+#[derive(Default)]
+pub struct Watchers {
+    state: Mutex<WatcherState>,
+}
+
+#[derive(Default)]
+struct WatcherState {
+    paused: bool,
+    // Dropping a debouncer stops its watcher
+    active: HashMap<i32, Debouncer<RecommendedWatcher>>,
+    // Why a trigger could not be watched, e.g. its directory is missing
+    errors: HashMap<i32, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchTriggerInput {
+    /// `None` when creating a trigger.
+    pub id: Option<i32>,
+    pub script_id: i32,
+    /// Absolute directory that is watched recursively.
+    pub base_dir: String,
+    /// Globs relative to `base_dir`, e.g. `src/**/*.rs` or `prisma/schema.prisma`.
+    pub patterns: Vec<String>,
+    /// Quiet period after the last change before the script starts.
+    pub debounce_ms: Option<i32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchTriggerInfo {
+    pub id: i32,
+    pub script_id: i32,
+    pub base_dir: String,
+    pub patterns: Vec<String>,
+    pub debounce_ms: i32,
+    pub enabled: bool,
+    /// Whether a watcher is currently running for the trigger.
+    pub active: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherStatus {
+    pub paused: bool,
+    pub active_count: usize,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchTriggeredEvent {
+    pub trigger_id: i32,
+    pub script_id: i32,
+    pub run_id: String,
+    pub changed_paths: Vec<String>,
+}
+
+/// Restore the pause state and start watching for every enabled trigger.
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let paused = match AppStateRepository::new().get_app_state().await {
+            Ok(state) => state.is_some_and(|state| state.watchers_paused),
+            Err(e) => {
+                eprintln!("Failed to load watcher pause state: {}", e);
+                false
+            }
+        };
+        if let Ok(watchers) = watchers() {
+            watchers.state.lock().unwrap().paused = paused;
+        }
+        if let Err(e) = reload(&app).await {
+            eprintln!("Failed to start file watchers: {}", e);
+        }
+    });
+}
+
+pub fn status() -> Result<WatcherStatus, String> {
+    let state = watchers()?.state.lock().unwrap();
+    Ok(WatcherStatus {
+        paused: state.paused,
+        active_count: state.active.len(),
+    })
+}
+
+pub async fn list_triggers() -> Result<Vec<WatchTriggerInfo>, String> {
+    let triggers = WatchTriggerRepository::new()
+        .get_all()
+        .await
+        .map_err(|e| format!("Failed to load watch triggers: {}", e))?;
+    let state = watchers()?.state.lock().unwrap();
+    Ok(triggers
+        .into_iter()
+        .map(|trigger| WatchTriggerInfo {
+            active: state.active.contains_key(&trigger.id),
+            error: state.errors.get(&trigger.id).cloned(),
+            patterns: parse_patterns(&trigger.patterns),
+            id: trigger.id,
+            script_id: trigger.shell_script_id,
+            base_dir: trigger.base_dir,
+            debounce_ms: trigger.debounce_ms,
+            enabled: trigger.enabled,
+        })
+        .collect())
+}
+
+pub async fn save_trigger(app: &tauri::AppHandle, input: WatchTriggerInput) -> Result<(), String> {
+    let base_dir = input.base_dir.trim().to_string();
+    if !Path::new(&base_dir).is_absolute() {
+        return Err(format!("'{}' is not an absolute directory", base_dir));
+    }
+    let patterns: Vec<String> = input
+        .patterns
+        .iter()
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Err("A watch trigger needs at least one pattern".to_string());
+    }
+    build_matcher(&patterns)?;
+    let debounce_ms = input.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS);
+    if !(MIN_DEBOUNCE_MS..=MAX_DEBOUNCE_MS).contains(&debounce_ms) {
+        return Err(format!(
+            "Debounce must be between {} and {} ms",
+            MIN_DEBOUNCE_MS, MAX_DEBOUNCE_MS
+        ));
+    }
+
+    let fields = WatchTriggerFields {
+        base_dir,
+        patterns: serde_json::to_string(&patterns)
+            .map_err(|e| format!("Failed to encode patterns: {}", e))?,
+        debounce_ms,
+        enabled: input.enabled.unwrap_or(true),
+    };
+    let repository = WatchTriggerRepository::new();
+    match input.id {
+        Some(trigger_id) => repository.update(trigger_id, fields).await.map(|_| ()),
+        None => repository.create(input.script_id, fields).await.map(|_| ()),
+    }
+    .map_err(|e| format!("Failed to save watch trigger: {}", e))?;
+    reload(app).await
+}
+
+pub async fn delete_trigger(app: &tauri::AppHandle, trigger_id: i32) -> Result<(), String> {
+    WatchTriggerRepository::new()
+        .delete(trigger_id)
+        .await
+        .map_err(|e| format!("Failed to delete watch trigger {}: {}", trigger_id, e))?;
+    reload(app).await
+}
+
+/// Stop (or restart) every watcher at once. The state is remembered across restarts.
+pub async fn set_paused(app: &tauri::AppHandle, paused: bool) -> Result<WatcherStatus, String> {
+    AppStateRepository::new()
+        .set_watchers_paused(paused)
+        .await
+        .map_err(|e| format!("Failed to save watcher pause state: {}", e))?;
+    watchers()?.state.lock().unwrap().paused = paused;
+    reload(app).await?;
+    println!(
+        "File watchers {}",
+        if paused { "paused" } else { "resumed" }
+    );
+    status()
+}
+
+// Replace every watcher with one per enabled trigger as currently stored
+async fn reload(app: &tauri::AppHandle) -> Result<(), String> {
+    let triggers = WatchTriggerRepository::new()
+        .get_all()
+        .await
+        .map_err(|e| format!("Failed to load watch triggers: {}", e))?;
+
+    let mut state = watchers()?.state.lock().unwrap();
+    state.active.clear();
+    state.errors.clear();
+    if state.paused {
+        return Ok(());
+    }
+    for trigger in triggers.into_iter().filter(|trigger| trigger.enabled) {
+        let runner = Arc::new(TriggerRunner {
+            app: app.clone(),
+            trigger_id: trigger.id,
+            script_id: trigger.shell_script_id,
+            queue: Mutex::default(),
+        });
+        let patterns = parse_patterns(&trigger.patterns);
+        match watch(runner, &trigger.base_dir, &patterns, trigger.debounce_ms) {
+            Ok(debouncer) => {
+                state.active.insert(trigger.id, debouncer);
+            }
+            Err(e) => {
+                eprintln!("Watch trigger {}: {}", trigger.id, e);
+                state.errors.insert(trigger.id, e);
+            }
+        }
+    }
+    println!("Watching {} trigger(s)", state.active.len());
+    Ok(())
+}
+
+fn watch(
+    runner: Arc<TriggerRunner>,
+    base_dir: &str,
+    patterns: &[String],
+    debounce_ms: i32,
+) -> Result<Debouncer<RecommendedWatcher>, String> {
+    let matcher = build_matcher(patterns)?;
+    // Events may report the resolved path (e.g. /private/var on macOS)
+    let base_dir = PathBuf::from(base_dir);
+    let resolved_dir = std::fs::canonicalize(&base_dir)
+        .map_err(|e| format!("Cannot watch {}: {}", base_dir.display(), e))?;
+
+    let trigger_id = runner.trigger_id;
+    let watched_dir = resolved_dir.clone();
+    let handler = move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            let changed: BTreeSet<PathBuf> = events
+                .into_iter()
+                .map(|event| event.path)
+                .filter(|path| {
+                    let relative = path
+                        .strip_prefix(&resolved_dir)
+                        .or_else(|_| path.strip_prefix(&base_dir));
+                    relative.is_ok_and(|relative| matcher.is_match(relative))
+                })
+                .collect();
+            if !changed.is_empty() {
+                runner.clone().enqueue(changed);
+            }
+        }
+        Err(e) => eprintln!("Watch trigger {}: {}", trigger_id, e),
+    };
+
+    let mut debouncer = new_debouncer(Duration::from_millis(debounce_ms.max(0) as u64), handler)
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+    debouncer
+        .watcher()
+        .watch(&watched_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Cannot watch {}: {}", watched_dir.display(), e))?;
+    Ok(debouncer)
+}
+
+// Starts the trigger's script for batches of changes, one run at a time. Changes arriving during
+// a run are collected and handled by a single follow-up run.
+struct TriggerRunner {
+    app: tauri::AppHandle,
+    trigger_id: i32,
+    script_id: i32,
+    queue: Mutex<RunQueue>,
+}
+
+#[derive(Default)]
+struct RunQueue {
+    running: bool,
+    pending: BTreeSet<PathBuf>,
+}
+
+impl TriggerRunner {
+    fn enqueue(self: Arc<Self>, changed: BTreeSet<PathBuf>) {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.pending.extend(changed);
+            if queue.running {
+                return;
+            }
+            queue.running = true;
+        }
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let changed = {
+                    let mut queue = self.queue.lock().unwrap();
+                    if queue.pending.is_empty() {
+                        queue.running = false;
+                        break;
+                    }
+                    std::mem::take(&mut queue.pending)
+                };
+                self.run(changed).await;
+            }
+        });
+    }
+
+    async fn run(&self, changed: BTreeSet<PathBuf>) {
+        let _database = backup::use_database().await;
+        let changed_paths: Vec<String> = changed
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        let run_id = executor::new_run_id();
+        println!(
+            "Watch trigger {} starting script {} as {} for {} changed path(s)",
+            self.trigger_id,
+            self.script_id,
+            run_id,
+            changed_paths.len()
+        );
+        let triggered = WatchTriggeredEvent {
+            trigger_id: self.trigger_id,
+            script_id: self.script_id,
+            run_id: run_id.clone(),
+            changed_paths: changed_paths.clone(),
+        };
+        if let Err(e) = self.app.emit(WATCH_TRIGGERED_EVENT, triggered) {
+            eprintln!("Failed to emit {}: {}", WATCH_TRIGGERED_EVENT, e);
+        }
+
+        let env = vec![EnvVar {
+            name: CHANGED_PATHS_VAR.to_string(),
+            value: changed_paths
+                .iter()
+                .take(MAX_CHANGED_PATHS)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }];
+        let outcome = executor::run_stored_script(
+            self.script_id,
+            &run_id,
+            ExecutionMode::Watch,
+            env,
+            |_, _| {},
+        )
+        .await;
+        match outcome {
+            Ok(outcome) if !outcome.success => eprintln!(
+                "Triggered run {} failed: {}",
+                run_id,
+                outcome.error_message()
+            ),
+            Ok(_) => {}
+            Err(e) => eprintln!("Triggered run {} could not be started: {}", run_id, e),
+        }
+    }
+}
+
+fn build_matcher(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // `*` stays within one directory, `**` crosses them
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Invalid patterns: {}", e))
+}
+
+fn parse_patterns(patterns: &str) -> Vec<String> {
+    serde_json::from_str(patterns).unwrap_or_else(|e| {
+        eprintln!("Failed to parse watch patterns {}: {}", patterns, e);
+        vec![]
+    })
+}
+
+fn watchers() -> Result<&'static Watchers, String> {
+    WATCHERS
+        .get()
+        .ok_or_else(|| "File watchers not initialized".to_string())
+}
@@ -310,9 +310,15 @@ function App() {
                 );
                 try {
                     if (payload.showShell) {
-                        await invoke("execute_command_in_shell", { command: payload.command });
+                        await invoke("execute_command_in_shell", {
+                            command: payload.command,
+                            scriptId: payload.scriptId,
+                        });
                     } else {
-                        await invoke("execute_command", { command: payload.command });
+                        await invoke("execute_command", {
+                            command: payload.command,
+                            scriptId: payload.scriptId,
+                        });
                     }
                     await notifyScriptExecutedRef.current({ scriptId: payload.scriptId });
                 } catch (err) {
@@ -380,9 +386,9 @@ function App() {
                 );
                 try {
                     if (script.showShell) {
-                        await invoke("execute_command_in_shell", { command: script.command });
+                        await invoke("execute_command_in_shell", { command: script.command, scriptId });
                     } else {
-                        await invoke("execute_command", { command: script.command });
+                        await invoke("execute_command", { command: script.command, scriptId });
                     }
                     await notifyScriptExecutedRef.current({ scriptId });
                 } finally {
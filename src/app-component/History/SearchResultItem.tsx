@@ -72,9 +72,9 @@ export default function SearchResultItem({
             );
             const command = script.command ?? "";
             if (script.showShell) {
-                await invoke("execute_command_in_shell", { command });
+                await invoke("execute_command_in_shell", { command, scriptId: script.id });
             } else {
-                await invoke("execute_command", { command });
+                await invoke("execute_command", { command, scriptId: script.id });
             }
             await notifyScriptExecuted({ scriptId: script.id });
         } catch (error) {
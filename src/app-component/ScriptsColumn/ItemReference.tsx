@@ -38,9 +38,15 @@ export default function ItemReference({
         if (!script) return;
         try {
             if (script.showShell) {
-                await invoke("execute_command_in_shell", { command: script.command });
+                await invoke("execute_command_in_shell", {
+                    command: script.command,
+                    scriptId: script.id,
+                });
             } else {
-                await invoke("execute_command", { command: script.command });
+                await invoke("execute_command", {
+                    command: script.command,
+                    scriptId: script.id,
+                });
             }
         } catch (err) {
             console.error("ItemReference execute error:", err);
@@ -113,9 +113,15 @@ export default function ScriptItem({
             );
 
             if (script.showShell) {
-                await invoke("execute_command_in_shell", { command: script.command });
+                await invoke("execute_command_in_shell", {
+                    command: script.command,
+                    scriptId: script.id,
+                });
             } else {
-                await invoke("execute_command", { command: script.command });
+                await invoke("execute_command", {
+                    command: script.command,
+                    scriptId: script.id,
+                });
             }
             await notifyScriptExecuted({ scriptId: script.id! });
         } catch (error) {